@@ -0,0 +1,74 @@
+#[path = "../tests/common/mod.rs"]
+mod common;
+
+use common::*;
+use sat_rs::functions::solve::*;
+use sat_rs::models::solverstate::*;
+use sat_rs::parsers::dimacs::*;
+use std::fs;
+use std::time::Instant;
+
+// Solves the SATLIB instances in benches/instances together with uniform random 3-SAT
+// problems (uf-style, clause/variable ratio 4.26) with the settings of the binary and prints the
+// mean wall time per instance.
+//
+// Results of 'cargo bench --bench satlib' on one core of an Intel Xeon, rustc 1.95:
+//
+//   aim-100-no.cnf            UNSATISFIABLE          7 conflicts        0.335 ms
+//   aim-50-yes.cnf              SATISFIABLE          1 conflicts        0.162 ms
+//   uf50-00                   UNSATISFIABLE         18 conflicts        0.297 ms
+//   uf50-01                     SATISFIABLE          2 conflicts        0.236 ms
+//   uf50-02                   UNSATISFIABLE         44 conflicts        0.395 ms
+//   uf100-00                    SATISFIABLE        306 conflicts        2.478 ms
+//   uf100-01                  UNSATISFIABLE        275 conflicts        2.195 ms
+//   uf100-02                    SATISFIABLE        151 conflicts        1.394 ms
+//   uf150-00                  UNSATISFIABLE       1953 conflicts       19.125 ms
+//   uf150-01                  UNSATISFIABLE       2064 conflicts       19.560 ms
+//   uf150-02                  UNSATISFIABLE       4558 conflicts       40.915 ms
+//   uf200-00                  UNSATISFIABLE       7094 conflicts       83.572 ms
+//   uf200-01                    SATISFIABLE       2412 conflicts       36.795 ms
+//   uf200-02                  UNSATISFIABLE      14928 conflicts      175.096 ms
+
+const RUNS: u32 = 5;
+
+fn bench(name: &str, problem: &str) {
+    let mut total = 0.0;
+    let mut state = SolverState::new_simp();
+    for _ in 0..RUNS {
+        state = SolverState::new_simp();
+        parse_dimacs(problem, &mut state);
+        let start = Instant::now();
+        state.solve_no_assumptions();
+        total += start.elapsed().as_secs_f64();
+    }
+    println!(
+        "{:<24} {:>14} {:>10} conflicts {:>12.3} ms",
+        name,
+        if state.ok && !state.model.is_empty() {
+            "SATISFIABLE"
+        } else {
+            "UNSATISFIABLE"
+        },
+        state.solver_stats.conflicts,
+        total * 1000.0 / RUNS as f64
+    );
+}
+
+fn main() {
+    let mut instances: Vec<_> = fs::read_dir("benches/instances")
+        .unwrap()
+        .map(|e| e.unwrap().path())
+        .collect();
+    instances.sort();
+    for path in instances {
+        let problem = fs::read_to_string(&path).unwrap();
+        bench(path.file_name().unwrap().to_str().unwrap(), &problem);
+    }
+
+    let mut seed: u64 = 0x2545F4914F6CDD1D;
+    for n in [50, 100, 150, 200] {
+        for i in 0..3 {
+            bench(&format!("uf{}-{:02}", n, i), &uniform_3sat(n, &mut seed));
+        }
+    }
+}
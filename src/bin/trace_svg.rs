@@ -0,0 +1,345 @@
+use sat_rs::models::events::*;
+use std::env;
+use std::fmt::Write;
+use std::fs;
+
+const WIDTH: f64 = 960.0;
+const PANEL: f64 = 240.0;
+const MARGIN: f64 = 50.0;
+const BUCKETS: usize = 60;
+const COLORS: [&str; 6] = [
+    "#1f77b4", "#d62728", "#2ca02c", "#ff7f0e", "#9467bd", "#8c564b",
+];
+
+// The events of one run that the charts need.
+#[derive(Default)]
+struct Run {
+    name: String,
+    // microseconds of every conflict and restart
+    conflicts: Vec<u64>,
+    restarts: Vec<u64>,
+    // level of every conflict and the level the search jumped back to after it
+    conflict_levels: Vec<u32>,
+    backjumps: Vec<u32>,
+    // per decision level, the times it was opened and the literals assigned on it
+    opened: Vec<u64>,
+    assigned: Vec<u64>,
+}
+
+/*_________________________________________________________________________________________________
+|
+|  trace_svg
+|
+|  Description:
+|    Draws the event traces written by 'sat_rs --trace-events' as one SVG file:
+|      trace_svg OUT.svg TRACE.jsonl [TRACE.jsonl ...]
+|    The first chart overlays the conflicts per second of all runs over time, with a tick per
+|    restart. Then every run gets the level of each conflict and the level it jumped back to,
+|    and the mean number of literals on the trail per decision level.
+|________________________________________________________________________________________________@*/
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    if args.len() < 3 {
+        eprintln!("usage: trace_svg OUT.svg TRACE.jsonl [TRACE.jsonl ...]");
+        std::process::exit(2);
+    }
+    let runs: Vec<Run> = args[2..]
+        .iter()
+        .map(|path| read_run(path, &fs::read_to_string(path).unwrap()))
+        .collect();
+    fs::write(&args[1], render(&runs)).unwrap();
+}
+
+fn read_run(name: &str, text: &str) -> Run {
+    let mut run: Run = Run {
+        name: name.to_string(),
+        opened: vec![1],
+        assigned: vec![0],
+        ..Run::default()
+    };
+    for (t, e) in text.lines().filter_map(parse_event) {
+        match e {
+            SearchEvent::Decision { level, .. } => {
+                let level: usize = level as usize;
+                if run.opened.len() <= level {
+                    run.opened.resize(level + 1, 0);
+                    run.assigned.resize(level + 1, 0);
+                }
+                run.opened[level] += 1;
+                run.assigned[level] += 1;
+            }
+            SearchEvent::Propagation { level, assigned } => {
+                let level: usize = level as usize;
+                if run.assigned.len() <= level {
+                    run.opened.resize(level + 1, 0);
+                    run.assigned.resize(level + 1, 0);
+                }
+                run.assigned[level] += assigned as u64;
+            }
+            SearchEvent::Conflict { level, .. } => {
+                run.conflicts.push(t);
+                run.conflict_levels.push(level);
+            }
+            SearchEvent::Backjump { to, .. } => run.backjumps.push(to),
+            SearchEvent::Restart { .. } => run.restarts.push(t),
+            SearchEvent::Learnt { .. } | SearchEvent::Reduce { .. } => (),
+        }
+    }
+    run
+}
+
+fn render(runs: &[Run]) -> String {
+    let height: f64 = PANEL * (runs.len() + 1) as f64 + MARGIN;
+    let mut svg: String = String::new();
+    writeln!(
+        svg,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" \
+         font-family=\"sans-serif\" font-size=\"11\">",
+        WIDTH, height
+    )
+    .unwrap();
+    writeln!(svg, "<rect width=\"100%\" height=\"100%\" fill=\"white\"/>").unwrap();
+
+    // conflict rate of all runs over a common time axis
+    let end: u64 = runs
+        .iter()
+        .flat_map(|r| r.conflicts.last().into_iter().chain(r.restarts.last()))
+        .copied()
+        .max()
+        .unwrap_or(0)
+        + 1;
+    let bucket: f64 = end as f64 / BUCKETS as f64;
+    let rates: Vec<Vec<f64>> = runs
+        .iter()
+        .map(|r| {
+            let mut counts: Vec<f64> = vec![0.0; BUCKETS];
+            for &t in &r.conflicts {
+                counts[((t as f64 / bucket) as usize).min(BUCKETS - 1)] += 1.0;
+            }
+            counts.iter().map(|c| c / (bucket / 1e6)).collect()
+        })
+        .collect();
+    let max_rate: f64 = rates.iter().flatten().copied().fold(1.0, f64::max);
+    let frame: Frame = Frame::new(MARGIN, MARGIN, WIDTH - 2.0 * MARGIN, PANEL - 2.0 * MARGIN);
+    frame.axes(
+        &mut svg,
+        "conflicts per second",
+        &format!("{:.3} s", end as f64 / 1e6),
+        max_rate,
+    );
+    for (k, (run, rate)) in runs.iter().zip(&rates).enumerate() {
+        let color: &str = COLORS[k % COLORS.len()];
+        let points: Vec<(f64, f64)> = rate
+            .iter()
+            .enumerate()
+            .map(|(i, &r)| ((i as f64 + 0.5) / BUCKETS as f64, r / max_rate))
+            .collect();
+        frame.polyline(&mut svg, &points, color);
+        for &t in &run.restarts {
+            let x: f64 = frame.x(t as f64 / end as f64);
+            let y: f64 = frame.y(0.0) + 4.0 + 4.0 * k as f64;
+            writeln!(
+                svg,
+                "<line x1=\"{:.1}\" y1=\"{:.1}\" x2=\"{:.1}\" y2=\"{:.1}\" stroke=\"{}\"/>",
+                x,
+                y,
+                x,
+                y + 3.0,
+                color
+            )
+            .unwrap();
+        }
+        writeln!(
+            svg,
+            "<text x=\"{:.1}\" y=\"{:.1}\" fill=\"{}\" text-anchor=\"end\">{}</text>",
+            WIDTH - MARGIN,
+            MARGIN + 14.0 * k as f64,
+            color,
+            escape(&run.name)
+        )
+        .unwrap();
+    }
+
+    for (k, run) in runs.iter().enumerate() {
+        let top: f64 = PANEL * (k + 1) as f64 + MARGIN;
+        let color: &str = COLORS[k % COLORS.len()];
+        let split: f64 = (WIDTH - 3.0 * MARGIN) * 0.65;
+
+        // level of every conflict, and where the search went back to
+        let max_level: f64 = run
+            .conflict_levels
+            .iter()
+            .copied()
+            .max()
+            .unwrap_or(0)
+            .max(1) as f64;
+        let n: f64 = run.conflict_levels.len().max(2) as f64 - 1.0;
+        let frame: Frame = Frame::new(MARGIN, top, split, PANEL - 2.0 * MARGIN);
+        frame.axes(
+            &mut svg,
+            &format!("{}: conflict level and backjump", escape(&run.name)),
+            &format!("{} conflicts", run.conflict_levels.len()),
+            max_level,
+        );
+        let series = |levels: &[u32]| -> Vec<(f64, f64)> {
+            sample(levels)
+                .into_iter()
+                .map(|(i, l)| (i as f64 / n, l as f64 / max_level))
+                .collect()
+        };
+        frame.polyline(&mut svg, &series(&run.conflict_levels), color);
+        frame.polyline(&mut svg, &series(&run.backjumps), "#999999");
+
+        // mean number of literals assigned on each level while it was open
+        let means: Vec<f64> = run
+            .assigned
+            .iter()
+            .zip(&run.opened)
+            .map(|(&a, &o)| if o > 0 { a as f64 / o as f64 } else { 0.0 })
+            .collect();
+        let max_mean: f64 = means.iter().copied().fold(1.0, f64::max);
+        let frame: Frame = Frame::new(
+            2.0 * MARGIN + split,
+            top,
+            WIDTH - 3.0 * MARGIN - split,
+            PANEL - 2.0 * MARGIN,
+        );
+        frame.axes(
+            &mut svg,
+            "literals per level",
+            &format!("{} levels", means.len()),
+            max_mean,
+        );
+        let w: f64 = 1.0 / means.len() as f64;
+        for (level, m) in means.iter().enumerate() {
+            let (x0, y0) = (frame.x(level as f64 * w), frame.y(m / max_mean));
+            writeln!(
+                svg,
+                "<rect x=\"{:.1}\" y=\"{:.1}\" width=\"{:.2}\" height=\"{:.1}\" fill=\"{}\"/>",
+                x0,
+                y0,
+                (frame.w * w * 0.9).max(0.5),
+                frame.y(0.0) - y0,
+                color
+            )
+            .unwrap();
+        }
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+// At most 'WIDTH' points of a series: the highest value of every stretch, with its position.
+fn sample(values: &[u32]) -> Vec<(usize, u32)> {
+    let step: usize = values.len().div_ceil(WIDTH as usize).max(1);
+    values
+        .chunks(step)
+        .enumerate()
+        .map(|(c, chunk)| (c * step, *chunk.iter().max().unwrap()))
+        .collect()
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+// A chart area, positions inside it are given from 0 to 1 and y grows upwards.
+struct Frame {
+    left: f64,
+    top: f64,
+    w: f64,
+    h: f64,
+}
+
+impl Frame {
+    fn new(left: f64, top: f64, w: f64, h: f64) -> Self {
+        Self { left, top, w, h }
+    }
+
+    fn x(&self, x: f64) -> f64 {
+        self.left + x * self.w
+    }
+
+    fn y(&self, y: f64) -> f64 {
+        self.top + (1.0 - y) * self.h
+    }
+
+    fn axes(&self, svg: &mut String, title: &str, x_max: &str, y_max: f64) {
+        writeln!(
+            svg,
+            "<path d=\"M{:.1},{:.1}V{:.1}H{:.1}\" fill=\"none\" stroke=\"black\"/>",
+            self.x(0.0),
+            self.y(1.0),
+            self.y(0.0),
+            self.x(1.0)
+        )
+        .unwrap();
+        writeln!(
+            svg,
+            "<text x=\"{:.1}\" y=\"{:.1}\" font-weight=\"bold\">{}</text>",
+            self.x(0.0),
+            self.y(1.0) - 8.0,
+            title
+        )
+        .unwrap();
+        writeln!(
+            svg,
+            "<text x=\"{:.1}\" y=\"{:.1}\" text-anchor=\"end\">{:.0}</text>",
+            self.x(0.0) - 4.0,
+            self.y(1.0) + 4.0,
+            y_max
+        )
+        .unwrap();
+        writeln!(
+            svg,
+            "<text x=\"{:.1}\" y=\"{:.1}\" text-anchor=\"end\">{}</text>",
+            self.x(1.0),
+            self.y(0.0) + 24.0,
+            x_max
+        )
+        .unwrap();
+    }
+
+    fn polyline(&self, svg: &mut String, points: &[(f64, f64)], color: &str) {
+        let points: Vec<String> = points
+            .iter()
+            .map(|&(x, y)| format!("{:.1},{:.1}", self.x(x), self.y(y)))
+            .collect();
+        writeln!(
+            svg,
+            "<polyline points=\"{}\" fill=\"none\" stroke=\"{}\"/>",
+            points.join(" "),
+            color
+        )
+        .unwrap();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    #[test]
+    fn runs_become_charts() {
+        let trace = "{\"t\":5,\"e\":\"decision\",\"level\":1,\"lit\":-3}\n\
+            {\"t\":6,\"e\":\"propagate\",\"level\":1,\"assigned\":4}\n\
+            {\"t\":9,\"e\":\"decision\",\"level\":2,\"lit\":7}\n\
+            {\"t\":10,\"e\":\"conflict\",\"level\":2,\"clause\":12}\n\
+            {\"t\":11,\"e\":\"backjump\",\"from\":2,\"to\":0}\n\
+            {\"t\":11,\"e\":\"learnt\",\"lits\":[3],\"lbd\":1}\n\
+            {\"t\":20,\"e\":\"restart\",\"conflicts\":1}\n";
+        let run: Run = read_run("a<b", trace);
+        assert_eq!(run.conflicts, vec![10]);
+        assert_eq!(run.restarts, vec![20]);
+        assert_eq!((run.conflict_levels, run.backjumps), (vec![2], vec![0]));
+        assert_eq!((run.opened, run.assigned), (vec![1, 1, 1], vec![0, 5, 1]));
+
+        let svg: String = render(&[read_run("a<b", trace), read_run("b", trace)]);
+        assert!(svg.starts_with("<svg") && svg.ends_with("</svg>\n"));
+        assert!(svg.contains("a&lt;b") && !svg.contains("a<b"));
+        assert_eq!(svg.matches("<polyline").count(), 2 + 2 * 2);
+        assert_eq!(svg.matches("<rect").count(), 1 + 2 * 3);
+    }
+}
@@ -1,8 +1,11 @@
+use crate::functions::dpll::*;
+use crate::functions::share::*;
+use crate::models::arena::*;
 use crate::models::clause::*;
 use crate::models::lbool::*;
 use crate::models::lit::*;
 use crate::models::solverstate::*;
-use std::cmp::max;
+use std::cmp::{max, Reverse};
 
 /*_________________________________________________________________________________________________
 |
@@ -14,23 +17,38 @@ use std::cmp::max;
 |    Pre-conditions:
 |      * 'out_learnt' is assumed to be cleared.
 |      * Current decision level must be greater than root level.
+|      * The conflict must be at the current decision level.
 |
 |    Post-conditions:
 |      * 'out_learnt[0]' is the asserting literal at level 'out_btlevel'.
 |
+|    Reasons are read through 'reason_lit' so binary implications, which keep their other literal
+|    inline, are resolved without visiting the clause arena.
+|
+|    The clause is minimised recursively ('expensive_ccmin'), then shrunk to one literal per
+|    decision level where possible ('shrink_ccmin') and strengthened with the binary clauses of the
+|    asserting literal ('binary_ccmin'). 'max_literals' counts the literals before and
+|    'tot_literals' after all of these.
+|
 |  Effect:
 |    Will undo part of the trail, upto but not beyond the assumption of the current decision level.
 |________________________________________________________________________________________________@*/
 pub trait Analyze {
-    fn analyze(&mut self, confl: Option<Clause>, out_learnt: &mut Vec<Lit>) -> i32;
+    fn analyze(&mut self, confl: Option<ClauseRef>, out_learnt: &mut Vec<Lit>) -> i32;
     fn analyze_removeable(&mut self, _p: Lit, min_level: u32) -> bool;
+    fn shrink(&mut self, out_learnt: &mut Vec<Lit>);
+    fn block_uip(&mut self, lv: i32, open: usize) -> Option<Lit>;
+    fn binary_minimize(&mut self, out_learnt: &mut Vec<Lit>);
 }
 
+// learnt clauses longer than this are not minimised with binary clauses
+const BINARY_CCMIN_LIM: usize = 30;
+
 impl Analyze for SolverState {
-    fn analyze(&mut self, mut confl: Option<Clause>, out_learnt: &mut Vec<Lit>) -> i32 {
+    fn analyze(&mut self, confl: Option<ClauseRef>, out_learnt: &mut Vec<Lit>) -> i32 {
         trace!(
             "{}|{}|{}|{:?}",
-            "analyse".to_string(),
+            "analyse",
             file!(),
             line!(),
             confl
@@ -41,20 +59,26 @@ impl Analyze for SolverState {
         let mut p: Lit = Lit::undefined();
         out_learnt.push(Lit::empty()); // (leave room for the asserting literal)
         let mut index: i32 = (self.trail.len() - 1) as i32;
+        let mut reason: Option<Reason> = confl.map(Reason::Clause);
 
         while {
             {
-                assert!(confl.is_some());
-                let c: &Clause = &confl.unwrap();
+                assert!(reason.is_some());
+                let r: Reason = reason.unwrap();
 
-                if c.learnt() {
-                    self.cla_bump_activity(&mut c.clone());
+                if let Reason::Clause(cr) = r
+                    && self.ca[cr].learnt()
+                {
+                    self.cla_bump_activity(cr);
+                }
+                if self.sharing.is_some() {
+                    self.shared_used(r, p);
                 }
 
                 let start: usize = if p == Lit::undefined() { 0 } else { 1 };
 
-                for idata in start..c.data.len() {
-                    let q: Lit = c.data[idata];
+                for idata in start..self.reason_size(r) {
+                    let q: Lit = self.reason_lit(r, idata);
                     if self.analyze_seen[var(&q) as usize] == Lbool::Undef0
                         && self.level[var(&q) as usize] > 0
                     {
@@ -68,23 +92,25 @@ impl Analyze for SolverState {
                         }
                     }
                 }
+                // lower level literals can sit above the current level ones on the trail
                 loop {
-                    if self.analyze_seen[var(&self.trail[index as usize]) as usize] == Lbool::Undef0
+                    let x: usize = var(&self.trail[index as usize]) as usize;
+                    index -= 1;
+                    if self.analyze_seen[x] != Lbool::Undef0
+                        && self.level[x] == self.decision_level()
                     {
-                        index -= 1;
-                    } else {
-                        index -= 1;
                         break;
                     }
                 }
                 p = self.trail[(index + 1) as usize];
-                confl = self.reason[var(&p) as usize].clone();
+                reason = self.reason_of(var(&p) as usize);
                 self.analyze_seen[var(&p) as usize] = Lbool::Undef0;
                 path_c -= 1;
             }
             path_c > 0
         } {}
         out_learnt[0] = !p;
+        let learnt_len: usize = out_learnt.len();
 
         {
             let mut i: usize = 1;
@@ -102,7 +128,7 @@ impl Analyze for SolverState {
                 i = 1;
                 j = 1;
                 for _y in 1..out_learnt.len() {
-                    match self.reason[var(&out_learnt[i]) as usize] {
+                    match self.reason_of(var(&out_learnt[i]) as usize) {
                         None => {
                             out_learnt[j] = out_learnt[i];
                             j += 1;
@@ -122,17 +148,17 @@ impl Analyze for SolverState {
                 j = 1;
                 for _y in 1..out_learnt.len() {
                     let mut keep: bool = false;
-                    match self.reason[var(&out_learnt[i]) as usize] {
+                    match self.reason_of(var(&out_learnt[i]) as usize) {
                         None => {
                             out_learnt[j] = out_learnt[i];
                             j += 1;
                             keep = true;
                         }
-                        Some(ref p) => {
-                            let c: &Clause = p;
-                            for k in 1..c.data.len() {
-                                if self.analyze_seen[var(&c.data[k]) as usize] == Lbool::Undef0
-                                    && self.level[var(&c.data[k]) as usize] != 0
+                        Some(r) => {
+                            for k in 1..self.reason_size(r) {
+                                let q: Lit = self.reason_lit(r, k);
+                                if self.analyze_seen[var(&q) as usize] == Lbool::Undef0
+                                    && self.level[var(&q) as usize] != 0
                                 {
                                     out_learnt[j] = out_learnt[i];
                                     j += 1;
@@ -161,16 +187,162 @@ impl Analyze for SolverState {
 
             self.solver_stats.max_literals += out_learnt.len() as f64;
             out_learnt.truncate(out_learnt.len() - (i - j));
-            self.solver_stats.tot_literals += out_learnt.len() as f64;
         }
 
+        if self.shrink_ccmin {
+            self.shrink(out_learnt);
+        }
+        if self.binary_ccmin && out_learnt.len() <= BINARY_CCMIN_LIM {
+            self.binary_minimize(out_learnt);
+        }
+        self.solver_stats.tot_literals += out_learnt.len() as f64;
+        self.solver_stats.minimized_literals += (learnt_len - out_learnt.len()) as u64;
+
+        // minimisation may have removed every literal of the old backtrack level
+        out_btlevel = 0;
+        for q in out_learnt.iter().skip(1) {
+            out_btlevel = max(out_btlevel, self.level[var(q) as usize]);
+        }
         out_btlevel
     }
 
+    // All-UIP shrinking: the literals of the learnt clause at one decision level are replaced by
+    // the negation of a single literal of that level that implies all of them (a block UIP). The
+    // implication must only go through reasons whose literals of other levels are already in the
+    // clause, otherwise the level is left as it is.
+    fn shrink(&mut self, out_learnt: &mut Vec<Lit>) {
+        for q in out_learnt.iter() {
+            self.analyze_seen[var(q) as usize] = Lbool::True;
+        }
+        let level = &self.level;
+        out_learnt[1..].sort_unstable_by_key(|q| Reverse(level[var(q) as usize]));
+
+        let mut i: usize = 1;
+        let mut j: usize = 1;
+        while i < out_learnt.len() {
+            let lv: i32 = self.level[var(&out_learnt[i]) as usize];
+            let mut e: usize = i + 1;
+            while e < out_learnt.len() && self.level[var(&out_learnt[e]) as usize] == lv {
+                e += 1;
+            }
+
+            let uip: Option<Lit> = if e - i > 1 {
+                self.block_uip(lv, e - i)
+            } else {
+                None
+            };
+            match uip {
+                Some(u) => {
+                    for q in &out_learnt[i..e] {
+                        self.analyze_seen[var(q) as usize] = Lbool::Undef0;
+                    }
+                    out_learnt[j] = !u;
+                    j += 1;
+                }
+                None => {
+                    for k in i..e {
+                        out_learnt[j] = out_learnt[k];
+                        j += 1;
+                    }
+                }
+            }
+            i = e;
+        }
+        out_learnt.truncate(j);
+
+        for q in out_learnt.iter() {
+            self.analyze_seen[var(q) as usize] = Lbool::Undef0;
+        }
+    }
+
+    // Walks the trail of level 'lv' down from the 'open' marked literals of the clause until a
+    // single one is left. Literals visited on the way are marked 'False' in 'analyze_seen'.
+    fn block_uip(&mut self, lv: i32, mut open: usize) -> Option<Lit> {
+        // with chronological backtracking literals of 'lv' can be anywhere above its start
+        let start: usize = self.trail_lim[lv as usize - 1] as usize;
+        let end: usize = if lv < self.decision_level() && self.chrono_backtrack < 0 {
+            self.trail_lim[lv as usize] as usize
+        } else {
+            self.trail.len()
+        };
+
+        self.analyze_stack.clear();
+        let mut uip: Option<Lit> = None;
+        'trail: for pos in (start..end).rev() {
+            let p: Lit = self.trail[pos];
+            if self.analyze_seen[var(&p) as usize] == Lbool::Undef0
+                || self.level[var(&p) as usize] != lv
+            {
+                continue;
+            }
+            if open == 1 {
+                uip = Some(p);
+                break;
+            }
+            let r: Reason = match self.reason_of(var(&p) as usize) {
+                Some(r) => r,
+                None => break,
+            };
+            for k in 1..self.reason_size(r) {
+                let q: Lit = self.reason_lit(r, k);
+                let y: usize = var(&q) as usize;
+                if self.level[y] == lv {
+                    if self.analyze_seen[y] == Lbool::Undef0 {
+                        self.analyze_seen[y] = Lbool::False;
+                        self.analyze_stack.push(q);
+                        open += 1;
+                    }
+                } else if self.level[y] > 0 && self.analyze_seen[y] != Lbool::True {
+                    break 'trail;
+                }
+            }
+            open -= 1;
+        }
+
+        for k in 0..self.analyze_stack.len() {
+            let y: usize = var(&self.analyze_stack[k]) as usize;
+            if self.analyze_seen[y] == Lbool::False {
+                self.analyze_seen[y] = Lbool::Undef0;
+            }
+        }
+        uip
+    }
+
+    // Glucose style minimisation with binary clauses: a binary clause (p | q), 'p' being the
+    // asserting literal, with 'q' true removes '~q' from the learnt clause.
+    fn binary_minimize(&mut self, out_learnt: &mut Vec<Lit>) {
+        let p: Lit = out_learnt[0];
+        for q in out_learnt.iter().skip(1) {
+            self.analyze_seen[var(q) as usize] = Lbool::True;
+        }
+
+        let mut removed: bool = false;
+        for k in 0..self.bin_watches[(!p).x as usize].len() {
+            let imp: Lit = self.bin_watches[(!p).x as usize][k].implied;
+            if self.analyze_seen[var(&imp) as usize] == Lbool::True
+                && self.value_by_lit(imp) == L_TRUE
+            {
+                self.analyze_seen[var(&imp) as usize] = Lbool::Undef0;
+                removed = true;
+            }
+        }
+
+        let mut j: usize = 1;
+        for i in 1..out_learnt.len() {
+            let q: Lit = out_learnt[i];
+            if !removed || self.analyze_seen[var(&q) as usize] == Lbool::True {
+                out_learnt[j] = q;
+                j += 1;
+            }
+            self.analyze_seen[var(&q) as usize] = Lbool::Undef0;
+        }
+        out_learnt.truncate(j);
+    }
+
     fn analyze_removeable(&mut self, _p: Lit, min_level: u32) -> bool {
         trace!(
             "{}|{}|{}|{:?}",
-            "analyze removeable".to_string(),
+            "analyze removeable",
             file!(),
             line!(),
             _p,
@@ -183,13 +355,11 @@ impl Analyze for SolverState {
 
         while !self.analyze_stack.is_empty() {
             assert!(self.reason[var(self.analyze_stack.last().unwrap()) as usize].is_some());
-            let c: &Clause;
-            match &self.reason[var(self.analyze_stack.last().unwrap()) as usize] {
-                Some(clause) => {
-                    c = clause;
+            match self.reason_of(var(self.analyze_stack.last().unwrap()) as usize) {
+                Some(r) => {
                     self.analyze_stack.pop();
-                    for i in 1..c.data.len() {
-                        let p: Lit = c.data[i];
+                    for i in 1..self.reason_size(r) {
+                        let p: Lit = self.reason_lit(r, i);
                         if self.analyze_seen[var(&p) as usize] == Lbool::Undef0
                             && self.level[var(&p) as usize] != 0
                         {
@@ -220,3 +390,60 @@ impl Analyze for SolverState {
         true
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::functions::analyse::*;
+    use crate::functions::new_clause::*;
+    use crate::functions::propagate::*;
+
+    // x1 implies x2 and x3 at level 1, x4 implies x5 and x6 at level 2 and
+    // (-x5 | -x6 | -x2 | -x3) is the conflict, the first UIP clause is (-x4 | -x2 | -x3).
+    fn learnt_clause(extra: &[Vec<i32>], shrink: bool, binary: bool) -> (Vec<Lit>, SolverState) {
+        let mut state = SolverState::new();
+        for _ in 0..6 {
+            state.new_var();
+        }
+        state.shrink_ccmin = shrink;
+        state.binary_ccmin = binary;
+        for c in [vec![-1, 2], vec![-1, 3], vec![-4, 5], vec![-4, 6], vec![-5, -6, -2, -3]]
+            .iter()
+            .chain(extra)
+        {
+            state.add_clause(&mut lits(c));
+        }
+
+        state.assume(lits(&[1])[0]);
+        assert!(state.propagate().is_none());
+        state.assume(lits(&[4])[0]);
+        let confl = state.propagate();
+        assert!(confl.is_some());
+
+        let mut learnt: Vec<Lit> = Vec::new();
+        let backtrack_level = state.analyze(confl, &mut learnt);
+        assert_eq!(backtrack_level, 1);
+        learnt[1..].sort_by_key(|p| p.x);
+        (learnt, state)
+    }
+
+    #[test]
+    fn shrink_to_block_uip() {
+        let (learnt, state) = learnt_clause(&[], false, false);
+        assert_eq!(learnt, lits(&[-4, -2, -3]));
+        assert_eq!(state.solver_stats.tot_literals, 3.0);
+
+        let (learnt, state) = learnt_clause(&[], true, false);
+        assert_eq!(learnt, lits(&[-4, -1]));
+        assert_eq!(state.solver_stats.max_literals, 3.0);
+        assert_eq!(state.solver_stats.tot_literals, 2.0);
+    }
+
+    #[test]
+    fn binary_clause_minimisation() {
+        // (-x4 | x2) removes -x2 from the learnt clause
+        let (learnt, state) = learnt_clause(&[vec![-4, 2]], false, true);
+        assert_eq!(learnt, lits(&[-4, -3]));
+        assert_eq!(state.solver_stats.max_literals, 3.0);
+        assert_eq!(state.solver_stats.tot_literals, 2.0);
+    }
+}
@@ -1,4 +1,5 @@
-use crate::models::clause::*;
+use crate::functions::dpll::*;
+use crate::models::arena::*;
 use crate::models::lbool::*;
 use crate::models::lit::*;
 use crate::models::solverstate::*;
@@ -14,14 +15,14 @@ use crate::models::solverstate::*;
 |    if conflict arose before search even started).
 |________________________________________________________________________________________________@*/
 pub trait Final {
-    fn analyse_final(&mut self, _confl: &Clause, _skip_first: bool);
+    fn analyse_final(&mut self, _confl: Reason, _skip_first: bool);
 }
 
 impl Final for SolverState {
-    fn analyse_final(&mut self, _confl: &Clause, _skip_first: bool) {
+    fn analyse_final(&mut self, _confl: Reason, _skip_first: bool) {
         trace!(
             "{}|{}|{}|{:?}",
-            "analyse final".to_string(),
+            "analyse final",
             file!(),
             line!(),
             _confl,
@@ -37,8 +38,8 @@ impl Final for SolverState {
             false => 0,
         };
 
-        for _y in istart.._confl.data.len() as i32 {
-            let x: usize = var(&_confl.data[_y as usize]) as usize;
+        for _y in istart..self.reason_size(_confl) as i32 {
+            let x: usize = var(&self.reason_lit(_confl, _y as usize)) as usize;
             if self.level[x] > 0 {
                 self.analyze_seen[x] = Lbool::True;
             }
@@ -53,11 +54,12 @@ impl Final for SolverState {
             let x: usize = var(&self.trail[y as usize]) as usize;
 
             if self.analyze_seen[x] != Lbool::Undef0 {
-                match &self.reason[x] {
-                    Some(clause) => {
-                        for j in 1..clause.data.len() {
-                            if self.level[var(&clause.data[j]) as usize] > 0 {
-                                self.analyze_seen[var(&clause.data[j]) as usize] = Lbool::True;
+                match self.reason_of(x) {
+                    Some(r) => {
+                        for j in 1..self.reason_size(r) {
+                            let q: Lit = self.reason_lit(r, j);
+                            if self.level[var(&q) as usize] > 0 {
+                                self.analyze_seen[var(&q) as usize] = Lbool::True;
                             }
                         }
                     }
@@ -0,0 +1,195 @@
+use crate::functions::solve::*;
+use crate::models::circuit::*;
+use crate::models::lit::*;
+use crate::models::solverstate::*;
+use crate::parsers::aiger::*;
+
+/*_________________________________________________________________________________________________
+|
+|  bmc
+|
+|  Description:
+|    Bounded model checking of the bad state properties of an AIGER circuit. The transition
+|    relation is unrolled one frame at a time through a 'Circuit', which hashes the gates of all
+|    frames together and folds the constant initial values of the latches into the first ones.
+|    Frame 't' gets its own inputs, named 'i<k>@<t>', and the latches of frame 0 without a reset
+|    value are inputs 'l<k>@0'. The invariant constraints are asserted in every frame.
+|
+|    Each frame is checked by a call to 'solve()' assuming that one of the properties fails in it.
+|    If none can, its negation is added for good and the next frame is unrolled, so the clauses
+|    learnt in earlier frames carry over.
+|
+|  Output:
+|    A witness for the first frame in which a property fails. SAFE if the constraints rule out
+|    every longer trace, UNKNOWN if no property fails up to frame 'bound'.
+|________________________________________________________________________________________________@*/
+pub trait Bmc {
+    fn bmc(&mut self, aig: &Aiger, bound: usize) -> BmcResult;
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum BmcResult {
+    Counterexample(Witness),
+    Safe,
+    Unknown,
+}
+
+impl Bmc for SolverState {
+    fn bmc(&mut self, aig: &Aiger, bound: usize) -> BmcResult {
+        let mut c: Circuit = Circuit::new();
+        let mut nodes: Vec<Node> = vec![C_FALSE; aig.max_var as usize + 1];
+        let init: Vec<Node> = aig
+            .latches
+            .iter()
+            .enumerate()
+            .map(|(k, l)| match l.reset {
+                0 => C_FALSE,
+                1 => C_TRUE,
+                _ => c.input(&format!("l{}@0", k)),
+            })
+            .collect();
+        let mut latches: Vec<Node> = init.clone();
+
+        for t in 0..=bound {
+            trace!("{}|{}|{}|{}", "bmc_frame", file!(), line!(), t);
+
+            for (k, &lit) in aig.inputs.iter().enumerate() {
+                nodes[(lit >> 1) as usize] = c.input(&format!("i{}@{}", k, t));
+            }
+            for (l, &node) in aig.latches.iter().zip(&latches) {
+                nodes[(l.lit >> 1) as usize] = node;
+            }
+            for g in &aig.ands {
+                let node: Node = c.and(node_of(&nodes, g.rhs0), node_of(&nodes, g.rhs1));
+                nodes[(g.lhs >> 1) as usize] = node;
+            }
+            for &lit in &aig.constraints {
+                c.assert(self, node_of(&nodes, lit));
+            }
+            if !self.ok {
+                return BmcResult::Safe;
+            }
+
+            let bad: Vec<Node> = aig
+                .properties()
+                .iter()
+                .map(|&lit| node_of(&nodes, lit))
+                .collect();
+            let any: Node = c.or_all(&bad);
+            if any != C_FALSE {
+                let p: Lit = c.lit(self, any);
+                self.solve(vec![p]);
+                if !self.model.is_empty() {
+                    let model = &self.model;
+                    let property: usize = bad.iter().position(|&b| c.value(model, b)).unwrap();
+                    let inputs: Vec<Vec<bool>> = (0..=t)
+                        .map(|s| {
+                            (0..aig.inputs.len())
+                                .map(|k| {
+                                    let input: Node = c.find(&format!("i{}@{}", k, s)).unwrap();
+                                    c.value(model, input)
+                                })
+                                .collect()
+                        })
+                        .collect();
+                    return BmcResult::Counterexample(Witness {
+                        property,
+                        init: init.iter().map(|&l| c.value(model, l)).collect(),
+                        inputs,
+                    });
+                }
+                if !self.ok {
+                    return BmcResult::Safe;
+                }
+                self.add_clause(&mut vec![!p]);
+            }
+
+            latches = aig
+                .latches
+                .iter()
+                .map(|l| node_of(&nodes, l.next))
+                .collect();
+        }
+        BmcResult::Unknown
+    }
+}
+
+fn node_of(nodes: &[Node], lit: u32) -> Node {
+    let node: Node = nodes[(lit >> 1) as usize];
+    if lit & 1 == 1 { !node } else { node }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::functions::bmc::*;
+
+    // A two bit counter that counts while the input is on, bad once it reaches 3.
+    static COUNTER: &[u8] = b"aag 11 1 2 0 8 1\n2\n4 13\n6 21\n22\n\
+        8 4 3\n10 5 2\n12 9 11\n14 4 2\n16 6 15\n18 7 14\n20 17 19\n22 6 4\n";
+
+    // Replays a witness on the circuit, returns the values of the properties in the last frame.
+    fn simulate(aig: &Aiger, w: &Witness) -> Vec<bool> {
+        let mut values: Vec<bool> = vec![false; aig.max_var as usize + 1];
+        let of = |values: &Vec<bool>, lit: u32| values[(lit >> 1) as usize] != (lit & 1 == 1);
+        let mut latches: Vec<bool> = w.init.clone();
+        let mut bad: Vec<bool> = Vec::new();
+        for frame in &w.inputs {
+            for (&lit, &v) in aig.inputs.iter().zip(frame) {
+                values[(lit >> 1) as usize] = v;
+            }
+            for (l, &v) in aig.latches.iter().zip(&latches) {
+                values[(l.lit >> 1) as usize] = v;
+            }
+            for g in &aig.ands {
+                values[(g.lhs >> 1) as usize] = of(&values, g.rhs0) && of(&values, g.rhs1);
+            }
+            bad = aig.properties().iter().map(|&p| of(&values, p)).collect();
+            latches = aig.latches.iter().map(|l| of(&values, l.next)).collect();
+        }
+        bad
+    }
+
+    #[test]
+    fn counter_reaches_three() {
+        let aig: Aiger = parse_aiger(COUNTER).unwrap();
+
+        let mut state = SolverState::new();
+        assert_eq!(state.bmc(&aig, 2), BmcResult::Unknown);
+
+        let mut state = SolverState::new();
+        let BmcResult::Counterexample(w) = state.bmc(&aig, 10) else {
+            panic!("the counter reaches 3");
+        };
+        assert_eq!(w.inputs.len(), 4);
+        assert_eq!(w.init, vec![false, false]);
+        assert_eq!(simulate(&aig, &w), vec![true]);
+        assert!(w.to_string().starts_with("1\nb0\n00\n1\n1\n1\n"));
+    }
+
+    #[test]
+    fn constraints_and_uninitialized_latches() {
+        // with the input constrained off the counter stays at 0
+        let mut aig: Aiger = parse_aiger(COUNTER).unwrap();
+        aig.constraints.push(3);
+        let mut state = SolverState::new();
+        assert_eq!(state.bmc(&aig, 5), BmcResult::Unknown);
+
+        // and a false constraint leaves no traces at all
+        aig.constraints.push(0);
+        let mut state = SolverState::new();
+        assert_eq!(state.bmc(&aig, 5), BmcResult::Safe);
+
+        // latches without a reset can start in the bad state
+        let mut aig: Aiger = parse_aiger(COUNTER).unwrap();
+        for l in aig.latches.iter_mut() {
+            l.reset = l.lit;
+        }
+        let mut state = SolverState::new();
+        let BmcResult::Counterexample(w) = state.bmc(&aig, 10) else {
+            panic!("the initial state can be bad");
+        };
+        assert_eq!(w.init, vec![true, true]);
+        assert_eq!(w.inputs.len(), 1);
+        assert_eq!(simulate(&aig, &w), vec![true]);
+    }
+}
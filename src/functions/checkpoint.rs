@@ -0,0 +1,467 @@
+use crate::functions::enqueue::*;
+use crate::functions::new_clause::*;
+use crate::models::arena::*;
+use crate::models::clause::*;
+use crate::models::extension::*;
+use crate::models::heap::*;
+use crate::models::lit::*;
+use crate::models::solverstate::*;
+use crate::models::statsparams::*;
+use crate::models::varorder::*;
+use std::fs;
+
+const MAGIC: &[u8; 8] = b"SATRSCKP";
+const VERSION: u32 = 3;
+
+/*_________________________________________________________________________________________________
+|
+|  checkpoint / restore
+|
+|  Description:
+|    Writes the solver to a versioned binary file and reads it back, so that a preempted run can
+|    continue. The file holds the facts of the root level, the problem and learnt clauses with
+|    their activities and LBDs, the model extension of the eliminated variables, the variable
+|    activities and saved phases, the random seed, the search configuration, the statistics and
+|    the restart counters of the current 'solve()' call. The watch lists and the variable heap
+|    are kept in their order as well, so the resumed search makes the same decisions and learns
+|    the same clauses as an uninterrupted one. All numbers are little endian.
+|
+|    A checkpoint is taken between two calls to 'search()', only the root level of the trail is
+|    kept and the assumptions are put back by the next 'solve()'. A restored state is marked
+|    'resumed': calling 'solve()' with the same assumptions skips preprocessing and continues
+|    with the saved restart counters. A solver with a 'Theory' attached can not be saved, the
+|    link to a portfolio is not saved.
+|
+|  Output:
+|    'restore()' fails on a file of another format or version, or a truncated one.
+|________________________________________________________________________________________________@*/
+pub trait Checkpoint {
+    fn checkpoint(&self) -> Result<Vec<u8>, String>;
+    fn write_checkpoint(&self, path: &str) -> Result<(), String>;
+    fn restore(data: &[u8]) -> Result<SolverState, String>;
+}
+
+impl Checkpoint for SolverState {
+    fn checkpoint(&self) -> Result<Vec<u8>, String> {
+        if self.theory.is_some() {
+            return Err("a solver with a theory can not be saved".to_string());
+        }
+        let mut w: Writer = Writer(MAGIC.to_vec());
+        w.u32(VERSION);
+        w.bool(self.ok);
+
+        let n: usize = self.n_vars() as usize;
+        w.u32(n as u32);
+        for v in 0..n {
+            w.f64(self.activity.col[v]);
+            w.bool(self.order.saved_phase[v]);
+            w.bool(self.order.target_phase[v]);
+            w.u32(match self.order.user_polarity[v] {
+                None => 0,
+                Some(false) => 1,
+                Some(true) => 2,
+            });
+            w.bool(self.order.decision[v]);
+            w.bool(self.frozen[v]);
+            w.bool(self.eliminated[v]);
+        }
+        let root: usize = self
+            .trail_lim
+            .first()
+            .map_or(self.trail.len(), |&l| l as usize);
+        w.lits(&self.trail[..root]);
+        w.lits(&self.assumptions);
+
+        w.u32(self.clauses.len() as u32);
+        for &cr in &self.clauses {
+            w.u32(self.ca[cr].id);
+            w.lits(&self.ca[cr].data);
+        }
+        w.u32(self.learnts.len() as u32);
+        for &cr in &self.learnts {
+            let c: &Clause = &self.ca[cr];
+            w.u32(c.id);
+            w.f64(c.activity);
+            w.bool(c.vivified);
+            w.u32(c.lbd);
+            w.lits(&c.data);
+        }
+        w.u32(self.extension.len() as u32);
+        for (witness, clause) in self.extension.witnesses.iter().zip(&self.extension.clauses) {
+            w.lits(&[*witness]);
+            w.lits(clause);
+        }
+
+        // watchers refer to the clauses by their position, the learnt ones after the others
+        let mut index: Vec<u32> = vec![u32::MAX; self.ca.size()];
+        for (k, cr) in self.clauses.iter().chain(&self.learnts).enumerate() {
+            index[cr.0 as usize] = k as u32;
+        }
+        for x in 0..2 * n {
+            w.u32(self.watches[x].len() as u32);
+            for watcher in &self.watches[x] {
+                w.u32(index[watcher.cref.0 as usize]);
+                w.lits(&[watcher.blocker]);
+            }
+            w.u32(self.bin_watches[x].len() as u32);
+            for watcher in &self.bin_watches[x] {
+                w.u32(index[watcher.cref.0 as usize]);
+                w.lits(&[watcher.implied]);
+            }
+        }
+        w.u32(self.order.heap.heap.len() as u32 - 1);
+        for &v in &self.order.heap.heap[1..] {
+            w.u32(v as u32);
+        }
+
+        w.f64(self.var_inc);
+        w.f64(self.cla_inc);
+        w.f64(self.order.random_seed);
+        w.u32(self.order.polarity_mode as u32);
+        w.f64(self.default_parms.var_decay);
+        w.f64(self.default_parms.clause_decay);
+        w.f64(self.default_parms.random_var_freq);
+        match self.restart_policy {
+            RestartPolicy::Geometric { first, factor } => {
+                w.u32(0);
+                w.f64(first);
+                w.f64(factor);
+            }
+            RestartPolicy::Luby { unit } => {
+                w.u32(1);
+                w.f64(unit);
+                w.f64(0.0);
+            }
+        }
+        w.u32(self.chrono_backtrack as u32);
+        w.u32(self.restarts);
+        w.f64(self.nof_learnts);
+        w.f64(self.next_probe);
+        w.f64(self.next_vivify);
+        w.u32(self.probe_head as u32);
+        w.u32(self.clause_id_counter);
+        w.u32(self.simp_db_assigns as u32);
+        w.f64(self.simp_db_props);
+        let mut stats: SolverStats = self.solver_stats;
+        for x in counters(&mut stats) {
+            w.f64(*x);
+        }
+        for x in int_counters(&mut stats) {
+            w.u64(*x);
+        }
+        Ok(w.0)
+    }
+
+    fn write_checkpoint(&self, path: &str) -> Result<(), String> {
+        trace!("{}|{}|{}|{}", "write_checkpoint", file!(), line!(), path);
+        let data: Vec<u8> = self.checkpoint()?;
+        // a preemption while writing must not destroy the previous checkpoint
+        let tmp: String = format!("{}.tmp", path);
+        fs::write(&tmp, data).map_err(|e| e.to_string())?;
+        fs::rename(&tmp, path).map_err(|e| e.to_string())
+    }
+
+    fn restore(data: &[u8]) -> Result<SolverState, String> {
+        let mut r: Reader = Reader { data, pos: 0 };
+        if r.bytes(MAGIC.len())? != MAGIC {
+            return Err("not a checkpoint".to_string());
+        }
+        let version: u32 = r.u32()?;
+        if version != VERSION {
+            return Err(format!("checkpoint version {} is not supported", version));
+        }
+        let mut state: SolverState = SolverState::new();
+        let ok: bool = r.bool()?;
+
+        let n: usize = r.u32()? as usize;
+        for v in 0..n {
+            state.new_var();
+            state.activity.col[v] = r.f64()?;
+            state.order.saved_phase[v] = r.bool()?;
+            state.order.target_phase[v] = r.bool()?;
+            state.order.user_polarity[v] = match r.u32()? {
+                0 => None,
+                1 => Some(false),
+                _ => Some(true),
+            };
+            state.order.decision[v] = r.bool()?;
+            state.frozen[v] = r.bool()?;
+            state.eliminated[v] = r.bool()?;
+        }
+        let check = |ps: Vec<Lit>| -> Result<Vec<Lit>, String> {
+            if ps.iter().any(|p| p.x < 0 || var(p) as usize >= n) {
+                return Err("literal out of range".to_string());
+            }
+            Ok(ps)
+        };
+        let units: Vec<Lit> = check(r.lits()?)?;
+        state.assumptions = check(r.lits()?)?;
+
+        for learnt in [false, true] {
+            for _ in 0..r.u32()? {
+                let id: u32 = r.u32()?;
+                let (activity, vivified, lbd) = if learnt {
+                    (r.f64()?, r.bool()?, Some(r.u32()?))
+                } else {
+                    (0.0, false, None)
+                };
+                let ps: Vec<Lit> = check(r.lits()?)?;
+                if ps.len() < 2 {
+                    return Err("clause with less than two literals".to_string());
+                }
+                let mut c: Clause = Clause::new(learnt, &ps, id);
+                c.activity = activity;
+                c.vivified = vivified;
+                c.lbd = lbd.unwrap_or(c.lbd);
+                let cr: ClauseRef = state.ca.alloc(c);
+                if learnt {
+                    state.learnts.push(cr);
+                } else {
+                    state.clauses.push(cr);
+                }
+            }
+        }
+        let crefs: Vec<ClauseRef> = state
+            .clauses
+            .iter()
+            .chain(&state.learnts)
+            .copied()
+            .collect();
+        for _ in 0..r.u32()? {
+            let witness: Vec<Lit> = check(r.lits()?)?;
+            let clause: Vec<Lit> = check(r.lits()?)?;
+            if witness.len() != 1 {
+                return Err("bad model extension".to_string());
+            }
+            state.extension.witnesses.push(witness[0]);
+            state.extension.clauses.push(clause);
+        }
+
+        let watcher = |r: &mut Reader| -> Result<(ClauseRef, Lit), String> {
+            let k: usize = r.u32()? as usize;
+            let p: Vec<Lit> = check(r.lits()?)?;
+            if k >= crefs.len() || p.len() != 1 {
+                return Err("bad watcher".to_string());
+            }
+            Ok((crefs[k], p[0]))
+        };
+        for x in 0..2 * n {
+            for _ in 0..r.u32()? {
+                let (cref, blocker) = watcher(&mut r)?;
+                state.watches[x].push(Watcher { cref, blocker });
+            }
+            for _ in 0..r.u32()? {
+                let (cref, implied) = watcher(&mut r)?;
+                state.bin_watches[x].push(BinWatcher { cref, implied });
+            }
+        }
+        let mut heap: Heap = Heap::new();
+        heap.set_bounds(n as i32);
+        for _ in 0..r.u32()? {
+            let v: usize = r.u32()? as usize;
+            if v >= n || heap.in_heap(v as i32) {
+                return Err("bad variable order".to_string());
+            }
+            heap.indices[v] = heap.heap.len() as i32;
+            heap.heap.push(v as i32);
+        }
+        state.order.heap = heap;
+
+        state.var_inc = r.f64()?;
+        state.cla_inc = r.f64()?;
+        state.order.random_seed = r.f64()?;
+        state.order.polarity_mode = match r.u32()? {
+            0 => PolarityMode::Saved,
+            1 => PolarityMode::False,
+            2 => PolarityMode::True,
+            3 => PolarityMode::Random,
+            _ => PolarityMode::Target,
+        };
+        state.default_parms = SearchParams {
+            var_decay: r.f64()?,
+            clause_decay: r.f64()?,
+            random_var_freq: r.f64()?,
+        };
+        let (tag, a, b) = (r.u32()?, r.f64()?, r.f64()?);
+        state.restart_policy = if tag == 0 {
+            RestartPolicy::Geometric {
+                first: a,
+                factor: b,
+            }
+        } else {
+            RestartPolicy::Luby { unit: a }
+        };
+        state.chrono_backtrack = r.u32()? as i32;
+        state.restarts = r.u32()?;
+        state.nof_learnts = r.f64()?;
+        state.next_probe = r.f64()?;
+        state.next_vivify = r.f64()?;
+        state.probe_head = r.u32()? as usize;
+        state.clause_id_counter = r.u32()?;
+        state.simp_db_assigns = r.u32()? as i32;
+        state.simp_db_props = r.f64()?;
+        let mut stats: SolverStats = SolverStats::new();
+        for x in counters(&mut stats) {
+            *x = r.f64()?;
+        }
+        for x in int_counters(&mut stats) {
+            *x = r.u64()?;
+        }
+        if r.pos != data.len() {
+            return Err("trailing bytes".to_string());
+        }
+
+        // the facts were propagated before the checkpoint
+        state.ok = ok;
+        for p in &units {
+            if state.ok && !state.internal_enqueue(p) {
+                state.ok = false;
+            }
+        }
+        state.qhead = state.trail.len() as i32;
+        state.bin_qhead = state.trail.len() as i32;
+        state.solver_stats = stats;
+        state.resumed = true;
+        Ok(state)
+    }
+}
+
+// The statistics in the order of the file, the start time is not kept.
+fn counters(s: &mut SolverStats) -> [&mut f64; 24] {
+    [
+        &mut s.starts,
+        &mut s.decisions,
+        &mut s.propagations,
+        &mut s.conflicts,
+        &mut s.clauses_literals,
+        &mut s.learnts_literals,
+        &mut s.max_literals,
+        &mut s.tot_literals,
+        &mut s.subsumed_clauses,
+        &mut s.strengthened_clauses,
+        &mut s.eliminated_vars,
+        &mut s.blocked_clauses,
+        &mut s.covered_clauses,
+        &mut s.failed_literals,
+        &mut s.hyper_binaries,
+        &mut s.substituted_vars,
+        &mut s.vivified_clauses,
+        &mut s.vivify_strengthened,
+        &mut s.vivify_literals,
+        &mut s.theory_lemmas,
+        &mut s.theory_propagations,
+        &mut s.shared_exported,
+        &mut s.shared_imported,
+        &mut s.shared_used,
+    ]
+}
+
+fn int_counters(s: &mut SolverStats) -> [&mut u64; 6] {
+    [
+        &mut s.restarts,
+        &mut s.reductions,
+        &mut s.deleted_clauses,
+        &mut s.minimized_literals,
+        &mut s.simplify_calls,
+        &mut s.max_decision_level,
+    ]
+}
+
+struct Writer(Vec<u8>);
+
+impl Writer {
+    fn u32(&mut self, x: u32) {
+        self.0.extend_from_slice(&x.to_le_bytes());
+    }
+    fn u64(&mut self, x: u64) {
+        self.0.extend_from_slice(&x.to_le_bytes());
+    }
+    fn f64(&mut self, x: f64) {
+        self.0.extend_from_slice(&x.to_le_bytes());
+    }
+    fn bool(&mut self, x: bool) {
+        self.0.push(x as u8);
+    }
+    fn lits(&mut self, ps: &[Lit]) {
+        self.u32(ps.len() as u32);
+        for p in ps {
+            self.u32(p.x as u32);
+        }
+    }
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl Reader<'_> {
+    fn bytes(&mut self, n: usize) -> Result<&[u8], String> {
+        if self.data.len() - self.pos < n {
+            return Err("truncated checkpoint".to_string());
+        }
+        self.pos += n;
+        Ok(&self.data[self.pos - n..self.pos])
+    }
+    fn u32(&mut self) -> Result<u32, String> {
+        Ok(u32::from_le_bytes(self.bytes(4)?.try_into().unwrap()))
+    }
+    fn u64(&mut self) -> Result<u64, String> {
+        Ok(u64::from_le_bytes(self.bytes(8)?.try_into().unwrap()))
+    }
+    fn f64(&mut self) -> Result<f64, String> {
+        Ok(f64::from_le_bytes(self.bytes(8)?.try_into().unwrap()))
+    }
+    fn bool(&mut self) -> Result<bool, String> {
+        Ok(self.bytes(1)?[0] != 0)
+    }
+    fn lits(&mut self) -> Result<Vec<Lit>, String> {
+        let n: usize = self.u32()? as usize;
+        (0..n)
+            .map(|_| {
+                Ok(Lit {
+                    x: self.u32()? as i32,
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::functions::checkpoint::*;
+    use crate::functions::solve::*;
+    use crate::functions::test_support::*;
+
+    #[test]
+    fn resumed_run_reaches_the_same_answer() {
+        let path = std::env::temp_dir().join(format!("sat_rs_{}.ckp", std::process::id()));
+        let path: &str = path.to_str().unwrap();
+
+        let mut full = pigeons(8, 7);
+        full.checkpoint_path = Some(path.to_string());
+        full.checkpoint_interval = 2;
+        full.solve_no_assumptions();
+        assert!(!full.ok);
+
+        let data: Vec<u8> = fs::read(path).unwrap();
+        fs::remove_file(path).unwrap();
+        let mut resumed: SolverState = SolverState::restore(&data).unwrap();
+        assert!(resumed.resumed && resumed.restarts > 0 && resumed.restarts.is_multiple_of(2));
+        assert!(!resumed.learnts.is_empty());
+        let conflicts: f64 = resumed.solver_stats.conflicts;
+        assert!(conflicts > 0.0 && conflicts < full.solver_stats.conflicts);
+        assert_eq!(resumed.checkpoint().unwrap(), data);
+
+        // the rest of the search is the same as in the uninterrupted run
+        resumed.solve_no_assumptions();
+        assert!(!resumed.ok);
+        assert_eq!(resumed.solver_stats.conflicts, full.solver_stats.conflicts);
+        assert_eq!(resumed.solver_stats.decisions, full.solver_stats.decisions);
+
+        assert!(SolverState::restore(&data[..data.len() - 1]).is_err());
+        let mut other: Vec<u8> = data.clone();
+        other[8] = VERSION as u8 + 1;
+        assert!(SolverState::restore(&other).is_err());
+    }
+}
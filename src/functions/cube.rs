@@ -0,0 +1,314 @@
+use crate::functions::new_clause::*;
+use crate::functions::propagate::*;
+use crate::functions::solve::*;
+use crate::models::lbool::*;
+use crate::models::lit::*;
+use crate::models::solverstate::*;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+/*_________________________________________________________________________________________________
+|
+|  cube
+|
+|  Description:
+|    Splits the formula into cubes by lookahead, the first phase of cube-and-conquer (Heule et
+|    al.). At every node the 'cube_parms.candidates' free variables with most occurrences are
+|    looked ahead: both literals are assumed in turn and propagated, and the variable scores the
+|    product of the numbers of literals each of them assigns. A literal whose propagation fails
+|    is a failed literal under the cube, its negation is added to the cube and the lookahead
+|    starts over. If both literals fail the cube is refuted and dropped. Otherwise the formula is
+|    split on the best variable, until the cube holds 'cube_parms.depth' decisions or no free
+|    variable is left.
+|
+|    The cubes together cover every assignment that is not refuted, so the formula is
+|    satisfiable if and only if one of them is.
+|
+|  Output:
+|    The cubes, none if the formula is unsatisfiable ('ok' is cleared when propagation at the root
+|    level already fails).
+|________________________________________________________________________________________________@*/
+pub trait Cube {
+    fn cube(&mut self) -> Vec<Vec<Lit>>;
+    fn split(&mut self, cube: &mut Vec<Lit>, depth: usize, ranked: &[i32], out: &mut Vec<Vec<Lit>>);
+    fn lookahead(&mut self, p: Lit) -> Option<usize>;
+    fn conquer(&mut self, cubes: &[Vec<Lit>]) -> Vec<CubeResult>;
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum CubeResult {
+    Sat,
+    Unsat,
+    Unknown,
+}
+
+impl Cube for SolverState {
+    fn cube(&mut self) -> Vec<Vec<Lit>> {
+        trace!("{}|{}|{}", "cube", file!(), line!());
+        assert!(self.decision_level() == 0);
+
+        if !self.ok || self.propagate().is_some() {
+            self.ok = false;
+            return Vec::new();
+        }
+
+        let mut occurrences: Vec<usize> = vec![0; self.n_vars() as usize];
+        for &cr in &self.clauses {
+            for p in &self.ca[cr].data {
+                occurrences[var(p) as usize] += 1;
+            }
+        }
+        let mut ranked: Vec<i32> = (0..self.n_vars())
+            .filter(|&v| self.order.decision[v as usize] && !self.eliminated[v as usize])
+            .collect();
+        ranked.sort_by_key(|&v| std::cmp::Reverse(occurrences[v as usize]));
+
+        let mut cubes: Vec<Vec<Lit>> = Vec::new();
+        self.split(&mut Vec::new(), self.cube_parms.depth, &ranked, &mut cubes);
+        info!("{} cubes", cubes.len());
+        cubes
+    }
+
+    // Adds the cubes that extend 'cube', whose literals are assumed and propagated.
+    fn split(
+        &mut self,
+        cube: &mut Vec<Lit>,
+        depth: usize,
+        ranked: &[i32],
+        out: &mut Vec<Vec<Lit>>,
+    ) {
+        let level: i32 = self.decision_level();
+        let len: usize = cube.len();
+
+        if depth == 0 {
+            out.push(cube.clone());
+            return;
+        }
+
+        let best: Option<i32> = 'lookahead: loop {
+            let mut best: Option<(usize, i32)> = None;
+            let free: Vec<i32> = ranked
+                .iter()
+                .copied()
+                .filter(|&v| is_undefined(self.value_by_var(v)))
+                .take(self.cube_parms.candidates)
+                .collect();
+            for v in free {
+                let pos: Option<usize> = self.lookahead(Lit::new(v, false));
+                let neg: Option<usize> = self.lookahead(Lit::new(v, true));
+                let forced: Lit = match (pos, neg) {
+                    (None, None) => {
+                        self.cancel_until(level);
+                        cube.truncate(len);
+                        return;
+                    }
+                    (None, Some(_)) => Lit::new(v, true),
+                    (Some(_), None) => Lit::new(v, false),
+                    (Some(a), Some(b)) => {
+                        let score: usize = (a + 1) * (b + 1);
+                        if best.is_none_or(|(s, _)| score > s) {
+                            best = Some((score, v));
+                        }
+                        continue;
+                    }
+                };
+                cube.push(forced);
+                self.assume(forced);
+                if self.propagate().is_some() {
+                    self.cancel_until(level);
+                    cube.truncate(len);
+                    return;
+                }
+                continue 'lookahead;
+            }
+            break best.map(|(_, v)| v);
+        };
+
+        match best {
+            None => out.push(cube.clone()),
+            Some(v) => {
+                for p in [Lit::new(v, false), Lit::new(v, true)] {
+                    let at: i32 = self.decision_level();
+                    cube.push(p);
+                    self.assume(p);
+                    if self.propagate().is_none() {
+                        self.split(cube, depth - 1, ranked, out);
+                    }
+                    self.cancel_until(at);
+                    cube.pop();
+                }
+            }
+        }
+        self.cancel_until(level);
+        cube.truncate(len);
+    }
+
+    // The number of literals assigned by assuming 'p', None if propagation fails.
+    fn lookahead(&mut self, p: Lit) -> Option<usize> {
+        let level: i32 = self.decision_level();
+        let before: usize = self.trail.len();
+        self.assume(p);
+        let confl = self.propagate();
+        let assigned: usize = self.trail.len() - before;
+        self.cancel_until(level);
+        confl.is_none().then_some(assigned)
+    }
+
+    // Solves the cubes one after the other with 'solve_limited()' until one of them has a model.
+    // The variables of the cubes are frozen first, so preprocessing keeps them. If every cube
+    // fails the formula is unsatisfiable and 'ok' is cleared.
+    fn conquer(&mut self, cubes: &[Vec<Lit>]) -> Vec<CubeResult> {
+        for p in cubes.iter().flatten() {
+            self.set_frozen(var(p), true);
+        }
+        let mut results: Vec<CubeResult> = vec![CubeResult::Unknown; cubes.len()];
+        for (k, cube) in cubes.iter().enumerate() {
+            if self.solve_limited(cube.clone()) == L_TRUE {
+                results[k] = CubeResult::Sat;
+                return results;
+            }
+            results[k] = CubeResult::Unsat;
+        }
+        self.ok = false;
+        results
+    }
+}
+
+/*_________________________________________________________________________________________________
+|
+|  conquer_parallel
+|
+|  Description:
+|    Solves the cubes with one thread per state of 'states', which must all hold the same
+|    clauses. The threads take the next unsolved cube until the cubes run out or one of them has
+|    a model, which interrupts the others.
+|
+|  Output:
+|    The result of every cube, cubes that were not finished are UNKNOWN, and the state that found
+|    a model. If every cube fails the first state is returned with 'ok' cleared.
+|________________________________________________________________________________________________@*/
+pub fn conquer_parallel(
+    states: Vec<SolverState>,
+    cubes: &[Vec<Lit>],
+) -> (Vec<CubeResult>, SolverState) {
+    assert!(!states.is_empty());
+    let stop: Arc<AtomicBool> = Arc::new(AtomicBool::new(false));
+    let next: AtomicUsize = AtomicUsize::new(0);
+    let results: Mutex<Vec<CubeResult>> = Mutex::new(vec![CubeResult::Unknown; cubes.len()]);
+
+    let mut finished: Vec<(bool, SolverState)> = thread::scope(|scope| {
+        let workers: Vec<thread::ScopedJoinHandle<(bool, SolverState)>> = states
+            .into_iter()
+            .map(|mut state| {
+                let stop: Arc<AtomicBool> = Arc::clone(&stop);
+                let (next, results) = (&next, &results);
+                scope.spawn(move || {
+                    for p in cubes.iter().flatten() {
+                        state.set_frozen(var(p), true);
+                    }
+                    state.interrupt = Some(Arc::clone(&stop));
+                    loop {
+                        let k: usize = next.fetch_add(1, Ordering::Relaxed);
+                        if k >= cubes.len() || state.interrupted() {
+                            break;
+                        }
+                        let result: CubeResult = match state.solve_limited(cubes[k].clone()) {
+                            Lbool::True => CubeResult::Sat,
+                            Lbool::False => CubeResult::Unsat,
+                            _ => CubeResult::Unknown,
+                        };
+                        results.lock().unwrap()[k] = result;
+                        if result == CubeResult::Sat {
+                            stop.store(true, Ordering::SeqCst);
+                            state.interrupt = None;
+                            return (true, state);
+                        }
+                    }
+                    state.interrupt = None;
+                    (false, state)
+                })
+            })
+            .collect();
+        workers.into_iter().map(|w| w.join().unwrap()).collect()
+    });
+
+    let results: Vec<CubeResult> = results.into_inner().unwrap();
+    let winner: usize = finished.iter().position(|(found, _)| *found).unwrap_or(0);
+    let mut state: SolverState = finished.swap_remove(winner).1;
+    if results.iter().all(|&r| r == CubeResult::Unsat) {
+        state.ok = false;
+    }
+    (results, state)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::functions::cube::*;
+
+    fn formula(seed: u64, n: i32, m: usize) -> (SolverState, Vec<Vec<Lit>>) {
+        let mut x: u64 = seed;
+        let mut next = move |k: i32| {
+            x = x
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            ((x >> 33) % k as u64) as i32
+        };
+        let clauses: Vec<Vec<Lit>> = (0..m)
+            .map(|_| (0..3).map(|_| Lit::new(next(n), next(2) == 1)).collect())
+            .collect();
+        let mut state = SolverState::new();
+        for _ in 0..n {
+            state.new_var();
+        }
+        for c in &clauses {
+            state.add_clause(&mut c.clone());
+        }
+        (state, clauses)
+    }
+
+    #[test]
+    fn cubes_cover_every_model() {
+        for seed in 0..40 {
+            let (mut state, clauses) = formula(seed, 12, 50);
+            state.cube_parms.depth = 3;
+            let cubes: Vec<Vec<Lit>> = state.cube();
+
+            // every model of the formula agrees with one of the cubes
+            let holds = |m: u32, p: &Lit| (m >> var(p) & 1 == 1) != sign(p);
+            let mut models: usize = 0;
+            for m in 0..1u32 << 12 {
+                if clauses.iter().all(|c| c.iter().any(|p| holds(m, p))) {
+                    models += 1;
+                    assert!(cubes.iter().any(|c| c.iter().all(|p| holds(m, p))));
+                }
+            }
+
+            let results: Vec<CubeResult> = state.conquer(&cubes);
+            let sat: bool = results.contains(&CubeResult::Sat);
+            assert_eq!(sat, models > 0);
+            assert_eq!(state.ok, sat);
+            if sat {
+                let k: usize = results.iter().position(|&r| r == CubeResult::Sat).unwrap();
+                let value = |p: &Lit| if sign(p) { L_FALSE } else { L_TRUE };
+                assert!(
+                    cubes[k]
+                        .iter()
+                        .all(|p| state.model[var(p) as usize] == value(p))
+                );
+            }
+
+            let states: Vec<SolverState> = (0..3).map(|_| formula(seed, 12, 50).0).collect();
+            let (results, state) = conquer_parallel(states, &cubes);
+            assert_eq!(results.contains(&CubeResult::Sat), sat);
+            assert_eq!(!state.model.is_empty(), sat);
+            assert_eq!(state.ok, sat);
+        }
+
+        // a formula without variables has the empty cube, which is satisfiable
+        let mut state = SolverState::new();
+        let cubes: Vec<Vec<Lit>> = state.cube();
+        assert_eq!(cubes, vec![Vec::<Lit>::new()]);
+        assert_eq!(state.conquer(&cubes), vec![CubeResult::Sat]);
+    }
+}
@@ -1,18 +1,304 @@
+use crate::functions::enqueue::*;
+use crate::functions::new_clause::*;
+use crate::functions::propagate::*;
+use crate::models::arena::*;
+use crate::models::clause::*;
+use crate::models::lbool::*;
+use crate::models::lit::*;
 use crate::models::solverstate::*;
+use crate::models::theory::*;
 
+/*_________________________________________________________________________________________________
+|
+|  theoryPropagate
+|
+|  Description:
+|    Alternates unit propagation with the propagation of the theory until neither of them has
+|    anything to add. The literals put on the trail since the last call are passed to the theory
+|    first. Lemmas are added as theory clauses through 'new_clause_pr()', a lemma that is false
+|    under the current assignment is a conflict. Of several conflicting lemmas the one with the
+|    lowest level is returned, backtracking below it makes the other ones non-false again.
+|
+|    Theory propagations are enqueued with a 'Reason::Theory' at the current decision level.
+|    'reason_of()' replaces it with the clause from 'Theory::explain()' once a reason is read.
+|
+|  Output:
+|    The conflicting clause if there is one, null otherwise. A conflict at the top level, found
+|    through a unit lemma, clears the 'ok' flag instead.
+|________________________________________________________________________________________________@*/
 pub trait Dpll {
+    fn theory_propagate(&mut self) -> Option<ClauseRef>;
     fn model_found(&mut self) -> bool;
+    fn apply_theory_output(&mut self) -> Option<ClauseRef>;
+    fn add_theory_clause(&mut self, ps: &mut Vec<Lit>) -> Option<ClauseRef>;
+    fn reason_of(&mut self, x: usize) -> Option<Reason>;
 }
 
 impl Dpll for SolverState {
+    fn theory_propagate(&mut self) -> Option<ClauseRef> {
+        trace!("{}|{}|{}", "theory_propagate", file!(), line!());
+
+        loop {
+            if !self.theory_out.is_empty() {
+                let confl: Option<ClauseRef> = self.apply_theory_output();
+                if confl.is_some() || !self.ok {
+                    return confl;
+                }
+            }
+            if let Some(confl) = self.propagate() {
+                return Some(confl);
+            }
+
+            let mut theory: Box<dyn Theory> = self.theory.take()?;
+            notify_theory(self, theory.as_mut());
+            theory.propagate(&mut self.theory_out);
+            self.theory = Some(theory);
+            if self.theory_out.is_empty() {
+                return None;
+            }
+        }
+    }
+
+    // Final check of the theory on a full assignment. Returns TRUE if the theory rejected it, the
+    // lemmas and propagations are applied by the next 'theory_propagate()'.
     fn model_found(&mut self) -> bool {
-        self.level_to_backtrack = i32::MAX;
-        let res: bool = false;
+        trace!("{}|{}|{}", "model_found", file!(), line!());
 
         if !self.ok {
             return false;
         }
+        let Some(mut theory) = self.theory.take() else {
+            return false;
+        };
+        notify_theory(self, theory.as_mut());
+        theory.final_check(&mut self.theory_out);
+        self.theory = Some(theory);
+        !self.theory_out.is_empty()
+    }
+
+    fn apply_theory_output(&mut self) -> Option<ClauseRef> {
+        let out: TheoryOutput = std::mem::take(&mut self.theory_out);
+        let head: usize = self.theory_head;
+
+        let mut conflicts: Vec<ClauseRef> = Vec::new();
+        for mut lemma in out.lemmas {
+            self.solver_stats.theory_lemmas += 1.0;
+            if let Some(cr) = self.add_theory_clause(&mut lemma) {
+                conflicts.push(cr);
+            }
+            if !self.ok {
+                return None;
+            }
+        }
+
+        // the propagations were found for the assignment before any lemma undid part of it
+        if conflicts.is_empty() && self.theory_head == head {
+            for p in out.propagations {
+                let val: Lbool = self.value_by_lit(p);
+                if is_undefined(val) {
+                    self.solver_stats.theory_propagations += 1.0;
+                    self.enqueue_at(&p, Some(Reason::Theory), self.decision_level());
+                } else if val == L_FALSE {
+                    let theory: &mut Box<dyn Theory> = self.theory.as_mut().unwrap();
+                    let mut explanation: Vec<Lit> = theory.explain(p);
+                    if let Some(cr) = self.add_theory_clause(&mut explanation) {
+                        conflicts.push(cr);
+                    }
+                    if !self.ok {
+                        return None;
+                    }
+                    break;
+                }
+            }
+        }
+
+        // a unit lemma can have undone the levels of an earlier conflict, the first literal of a
+        // theory clause is one of the last assigned
+        let ca: &ClauseArena = &self.ca;
+        conflicts
+            .into_iter()
+            .filter(|cr| self.value_by_lit(ca[*cr].data[0]) == L_FALSE)
+            .min_by_key(|cr| self.level[var(&ca[*cr].data[0]) as usize])
+    }
+
+    // Adds a lemma of the theory, returns it if it is false under the current assignment.
+    fn add_theory_clause(&mut self, ps: &mut Vec<Lit>) -> Option<ClauseRef> {
+        trace!(
+            "{}|{}|{}|{:?}",
+            "add_theory_clause",
+            file!(),
+            line!(),
+            ps
+        );
+
+        let before: usize = self.learnts.len();
+        self.new_clause_pr(ps, false, true, true);
+        if self.learnts.len() > before {
+            let cr: ClauseRef = self.learnts[before];
+            if self.value_by_lit(self.ca[cr].data[0]) == L_FALSE {
+                return Some(cr);
+            }
+        }
+        None
+    }
+
+    // 'reason[x]', asking the theory for the clause of a theory propagation.
+    fn reason_of(&mut self, x: usize) -> Option<Reason> {
+        if self.reason[x] != Some(Reason::Theory) {
+            return self.reason[x];
+        }
+
+        let p: Lit = Lit::new(x as i32, self.value_by_var(x as i32) == L_FALSE);
+        let theory: &mut Box<dyn Theory> = self.theory.as_mut().unwrap();
+        let mut c: Vec<Lit> = theory.explain(p);
+        trace!("{}|{}|{}|{:?}|{:?}", "explain", file!(), line!(), p, c);
+        assert!(c.len() > 1, "an explanation needs at least two literals");
+
+        // the implied literal first, the literal assigned last second
+        let i: usize = c.iter().position(|q| *q == p).unwrap();
+        c.swap(0, i);
+        let level = &self.level;
+        let k: usize = (1..c.len())
+            .max_by_key(|k| level[var(&c[*k]) as usize])
+            .unwrap();
+        c.swap(1, k);
+
+        let size = c.len() as f64;
+        let cr: ClauseRef = self.ca.alloc(Clause::new(true, &c, self.clause_id_counter));
+        self.clause_id_counter += 1;
+        self.learnts.push(cr);
+        self.solver_stats.learnts_literals += size;
+        self.attach(cr);
+
+        let r: Reason = if c.len() == 2 {
+            Reason::Binary(c[1])
+        } else {
+            Reason::Clause(cr)
+        };
+        self.reason[x] = Some(r);
+        Some(r)
+    }
+}
+
+// Passes the literals assigned since the last call to the theory.
+fn notify_theory(state: &mut SolverState, theory: &mut dyn Theory) {
+    for &p in &state.trail[state.theory_head..] {
+        theory.assign(p);
+    }
+    state.theory_head = state.trail.len();
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::functions::dpll::*;
+    use crate::functions::solve::*;
+    use crate::functions::test_support::*;
+
+    // At most one of the variables is true, propagated as soon as one of them is.
+    struct AtMostOne {
+        trail: Vec<Lit>,
+    }
+
+    impl AtMostOne {
+        fn true_lits(&self) -> Vec<Lit> {
+            self.trail.iter().copied().filter(|p| !sign(p)).collect()
+        }
+    }
+
+    impl Theory for AtMostOne {
+        fn assign(&mut self, p: Lit) {
+            self.trail.push(p);
+        }
+        fn backtrack(&mut self, n: usize) {
+            self.trail.truncate(n);
+        }
+        fn propagate(&mut self, out: &mut TheoryOutput) {
+            let on: Vec<Lit> = self.true_lits();
+            if on.len() > 1 {
+                out.add_lemma(&[!on[0], !on[1]]);
+            } else if on.len() == 1 {
+                for v in 0..4 {
+                    if !self.trail.iter().any(|p| var(p) == v) {
+                        out.propagate(Lit::new(v, true));
+                    }
+                }
+            }
+        }
+        fn final_check(&mut self, _out: &mut TheoryOutput) {}
+        fn explain(&mut self, p: Lit) -> Vec<Lit> {
+            vec![p, !self.true_lits()[0]]
+        }
+    }
+
+    // Accepts only models with an odd number of true variables, checked on full assignments.
+    struct OddParity {
+        trail: Vec<Lit>,
+    }
+
+    impl Theory for OddParity {
+        fn assign(&mut self, p: Lit) {
+            self.trail.push(p);
+        }
+        fn backtrack(&mut self, n: usize) {
+            self.trail.truncate(n);
+        }
+        fn propagate(&mut self, _out: &mut TheoryOutput) {}
+        fn final_check(&mut self, out: &mut TheoryOutput) {
+            if self.trail.iter().filter(|p| !sign(p)).count() % 2 == 0 {
+                let blocking: Vec<Lit> = self.trail.iter().map(|p| !*p).collect();
+                out.add_lemma(&blocking);
+            }
+        }
+        fn explain(&mut self, _p: Lit) -> Vec<Lit> {
+            unreachable!()
+        }
+    }
+
+    #[test]
+    fn theory_propagations_are_explained() {
+        let mut state = state_of(4, &[vec![1, 2], vec![3, 4]]);
+        state.theory = Some(Box::new(AtMostOne { trail: Vec::new() }));
+        state.solve_no_assumptions();
+        assert!(state.model.is_empty());
+        assert!(state.solver_stats.theory_propagations > 0.0);
+
+        let mut state = state_of(4, &[vec![1, 2], vec![2, 3]]);
+        state.theory = Some(Box::new(AtMostOne { trail: Vec::new() }));
+        state.solve_no_assumptions();
+        assert!(state.ok && !state.model.is_empty());
+        assert_eq!(state.model, vec![L_FALSE, L_TRUE, L_FALSE, L_FALSE]);
+    }
+
+    #[test]
+    fn final_check_rejects_models() {
+        let problem: Vec<Vec<i32>> = vec![vec![1, 2], vec![1, 3]];
+        let mut state = state_of(3, &problem);
+        state.theory = Some(Box::new(OddParity { trail: Vec::new() }));
+        state.set_polarity(0, Some(false));
+        state.set_polarity(1, Some(false));
+        state.set_polarity(2, Some(false));
+        state.solve_no_assumptions();
+
+        assert!(state.ok && !state.model.is_empty());
+        assert!(state.solver_stats.theory_lemmas > 0.0);
+        assert_eq!(state.model.iter().filter(|v| **v == L_TRUE).count() % 2, 1);
+        for c in &problem {
+            assert!(lits(c)
+                .iter()
+                .any(|p| state.model[var(p) as usize] == to_bool(!sign(p))));
+        }
+
+        // with x1 and x2 assumed the only odd model is x1, x2, x3
+        let mut state = state_of(3, &problem);
+        state.theory = Some(Box::new(OddParity { trail: Vec::new() }));
+        state.solve(lits(&[1, 2]));
+        assert_eq!(state.model, vec![L_TRUE, L_TRUE, L_TRUE]);
 
-        res
+        // and with x1 false there is none
+        let mut state = state_of(3, &problem);
+        state.theory = Some(Box::new(OddParity { trail: Vec::new() }));
+        state.solve(lits(&[-1]));
+        assert!(state.ok && state.model.is_empty());
+        assert_eq!(state.conflict, lits(&[1]));
     }
 }
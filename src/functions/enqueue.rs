@@ -1,4 +1,4 @@
-use crate::models::clause::*;
+use crate::models::arena::*;
 use crate::models::lbool::*;
 use crate::models::lit::*;
 use crate::models::solverstate::*;
@@ -14,26 +14,35 @@ use crate::models::solverstate::*;
 |  Input:
 |    p    - The fact to enqueue
 |    from - [Optional] Fact propagated from this (currently) unit clause. Stored in 'reason[]'.
-|           Default value is null (no reason).
+|           Binary clauses are stored as their other literal. Default value is null (no reason).
+|
+|    'enqueue_at()' assigns the fact at the given level instead of the current decision level. With
+|    chronological backtracking an implied literal belongs to the highest level of its reason, which
+|    can be lower than the current one, so the trail is no longer sorted by level.
 |
 |  Output:
 |    TRUE if fact was enqueued without conflict, FALSE otherwise.
 |________________________________________________________________________________________________@*/
 pub trait NQueue {
-    fn enqueue(&mut self, p: &Lit, from: Option<Clause>) -> bool;
+    fn enqueue(&mut self, p: &Lit, from: Option<Reason>) -> bool;
+    fn enqueue_at(&mut self, p: &Lit, from: Option<Reason>, level: i32) -> bool;
     fn internal_enqueue(&mut self, _fact: &Lit) -> bool;
 }
 
 impl NQueue for SolverState {
-    fn enqueue(&mut self, p: &Lit, from: Option<Clause>) -> bool {
-        trace!("{}|{}|{}|{:?}", "enqueue".to_string(), file!(), line!(), p);
+    fn enqueue(&mut self, p: &Lit, from: Option<Reason>) -> bool {
+        self.enqueue_at(p, from, self.decision_level())
+    }
+
+    fn enqueue_at(&mut self, p: &Lit, from: Option<Reason>, level: i32) -> bool {
+        trace!("{}|{}|{}|{:?}|{}", "enqueue", file!(), line!(), p, level);
 
         if !is_undefined(self.value_by_lit(*p)) {
             self.value_by_lit(*p) != L_FALSE
         } else {
             let x: usize = var(p) as usize;
             self.update_assigns(to_bool(!sign(p)), x);
-            self.level[x] = self.decision_level();
+            self.level[x] = level;
             self.trail_pos[x] = self.trail.len() as i32;
             self.reason[x] = from;
             self.trail.push(*p);
@@ -44,7 +53,7 @@ impl NQueue for SolverState {
     fn internal_enqueue(&mut self, _fact: &Lit) -> bool {
         trace!(
             "{}|{}|{}|{:?}",
-            "internal_enqueue".to_string(),
+            "internal_enqueue",
             file!(),
             line!(),
             _fact,
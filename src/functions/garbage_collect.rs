@@ -0,0 +1,65 @@
+use crate::models::arena::*;
+use crate::models::solverstate::*;
+
+/*_________________________________________________________________________________________________
+|
+|  garbageCollect
+|
+|  Description:
+|    Compacts the clause arena once more than half of it is taken by removed clauses. Every
+|    handle held by the solver (clause lists, watch lists and reasons) is relocated. Binary
+|    reasons keep their literal inline and are left untouched.
+|________________________________________________________________________________________________@*/
+pub trait GarbageCollect {
+    fn check_garbage(&mut self);
+    fn garbage_collect(&mut self);
+}
+
+impl GarbageCollect for SolverState {
+    fn check_garbage(&mut self) {
+        if self.ca.needs_collection() {
+            self.garbage_collect();
+        }
+    }
+
+    fn garbage_collect(&mut self) {
+        trace!(
+            "{}|{}|{}|{}|{}",
+            "garbage_collect",
+            file!(),
+            line!(),
+            self.ca.size(),
+            self.ca.wasted
+        );
+
+        let relocation: Vec<Option<ClauseRef>> = self.ca.collect();
+        let relocate = |cr: ClauseRef| relocation[cr.0 as usize];
+
+        for cr in self.clauses.iter_mut().chain(self.learnts.iter_mut()) {
+            *cr = relocate(*cr).unwrap();
+        }
+        for ws in self.bin_watches.iter_mut() {
+            ws.retain_mut(|w| match relocate(w.cref) {
+                Some(cr) => {
+                    w.cref = cr;
+                    true
+                }
+                None => false,
+            });
+        }
+        for ws in self.watches.iter_mut() {
+            ws.retain_mut(|w| match relocate(w.cref) {
+                Some(cr) => {
+                    w.cref = cr;
+                    true
+                }
+                None => false,
+            });
+        }
+        for r in self.reason.iter_mut() {
+            if let Some(Reason::Clause(cr)) = *r {
+                *r = relocate(cr).map(Reason::Clause);
+            }
+        }
+    }
+}
@@ -0,0 +1,142 @@
+use crate::functions::new_clause::*;
+use crate::functions::propagate::*;
+use crate::models::arena::*;
+use crate::models::clause::*;
+use crate::models::lbool::*;
+use crate::models::lit::*;
+use crate::models::solverstate::*;
+use crate::parsers::learnts::*;
+
+/*_________________________________________________________________________________________________
+|
+|  import_hints
+|
+|  Description:
+|    Adds learnt clauses of an earlier run, read by 'parse_learnts()', as learnt clauses at the
+|    root level, which 'reduce_db()' may delete again. The earlier run may have been on another
+|    formula, so a clause is only added if it is RUP (reverse unit propagation): assuming the
+|    negations of its literals and propagating on the clauses so far must fail, which shows the
+|    clause is implied. Clauses over variables the solver does not have or that were eliminated
+|    are dropped as well. The activities are scaled so the most active clause gets 'cla_inc'.
+|
+|  Output:
+|    The number of clauses that passed the check. 'ok' is cleared if they made the formula
+|    unsatisfiable.
+|________________________________________________________________________________________________@*/
+pub trait Hints {
+    fn import_hints(&mut self, hints: &[LearntHint]) -> usize;
+    fn is_rup(&mut self, ps: &[Lit]) -> bool;
+}
+
+impl Hints for SolverState {
+    fn import_hints(&mut self, hints: &[LearntHint]) -> usize {
+        trace!("{}|{}|{}|{}", "import_hints", file!(), line!(), hints.len());
+        assert!(self.decision_level() == 0);
+
+        let max: f64 = hints.iter().map(|h| h.activity).fold(0.0, f64::max);
+        let scale: f64 = if max > 0.0 { self.cla_inc / max } else { 0.0 };
+        let mut accepted: usize = 0;
+        for h in hints {
+            if !self.ok {
+                break;
+            }
+            // units enqueued by earlier hints are propagated before the next check
+            if self.propagate().is_some() {
+                self.ok = false;
+                break;
+            }
+            if h.lits
+                .iter()
+                .any(|p| p.x < 0 || var(p) >= self.n_vars() || self.eliminated[var(p) as usize])
+                || !self.is_rup(&h.lits)
+            {
+                continue;
+            }
+            accepted += 1;
+
+            let n_learnts: usize = self.learnts.len();
+            self.new_root_learnt(&mut h.lits.clone());
+            if self.learnts.len() > n_learnts {
+                let cr: ClauseRef = self.learnts[n_learnts];
+                self.ca[cr].activity = h.activity * scale;
+                self.ca[cr].lbd = h.lbd.min(self.ca[cr].size() as u32);
+            }
+        }
+        if self.ok && self.propagate().is_some() {
+            self.ok = false;
+        }
+        info!("{} of {} hints imported", accepted, hints.len());
+        accepted
+    }
+
+    // TRUE if unit propagation refutes the negation of the clause 'ps'. Called at level 0 after
+    // propagation, the solver is back at level 0 afterwards.
+    fn is_rup(&mut self, ps: &[Lit]) -> bool {
+        let mut rup: bool = false;
+        for &p in ps {
+            let val: Lbool = self.value_by_lit(p);
+            if val == L_TRUE {
+                rup = true;
+                break;
+            } else if val != L_FALSE {
+                self.assume(!p);
+                if self.propagate().is_some() {
+                    rup = true;
+                    break;
+                }
+            }
+        }
+        self.cancel_until(0);
+        rup
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::functions::hints::*;
+    use crate::functions::solve::*;
+    use crate::functions::test_support::*;
+
+    #[test]
+    fn only_implied_learnts_are_imported() {
+        let mut first = pigeons(7, 6);
+        first.solve_no_assumptions();
+        assert!(!first.ok);
+        let hints: Vec<LearntHint> = parse_learnts(&write_learnts(&first)).unwrap();
+        assert_eq!(hints.len(), first.learnts.len());
+        assert!(hints.iter().any(|h| h.lbd < h.lits.len() as u32));
+
+        let mut second = pigeons(7, 6);
+        let accepted: usize = second.import_hints(&hints);
+        assert!(accepted > 0);
+        assert!(
+            second
+                .learnts
+                .iter()
+                .all(|&cr| second.ca[cr].activity <= second.cla_inc)
+        );
+        second.solve_no_assumptions();
+        assert!(!second.ok);
+
+        // with a pigeon less there is room for all, the hints must not take it away
+        let mut third = pigeons(6, 6);
+        assert!(third.import_hints(&hints) < accepted);
+        third.solve_no_assumptions();
+        assert!(third.ok);
+
+        let mut state = SolverState::new();
+        for _ in 0..3 {
+            state.new_var();
+        }
+        state.add_clause(&mut vec![Lit::new(0, false), Lit::new(1, false)]);
+        state.add_clause(&mut vec![Lit::new(0, true), Lit::new(2, false)]);
+        let hints: Vec<LearntHint> =
+            parse_learnts("c learnt 2.5 2\n2 3 0\n1 3 0\n-1 0\n4 1 0\n").unwrap();
+        assert_eq!((hints[0].activity, hints[0].lbd), (2.5, 2));
+        assert_eq!((hints[1].activity, hints[1].lbd), (0.0, 2));
+        assert_eq!(state.import_hints(&hints), 1);
+        assert_eq!(state.ca[state.learnts[0]].activity, state.cla_inc);
+        state.solve_no_assumptions();
+        assert!(state.ok);
+    }
+}
@@ -1,10 +1,22 @@
 pub mod analyse;
 pub mod analyse_final;
+pub mod bmc;
+pub mod checkpoint;
+pub mod cube;
 pub mod dpll;
 pub mod enqueue;
+pub mod garbage_collect;
+pub mod hints;
+pub mod preprocess;
+pub mod probe;
 pub mod new_clause;
+pub mod portfolio;
 pub mod propagate;
 pub mod reduce_db;
 pub mod search;
+pub mod share;
 pub mod simplify_db;
 pub mod solve;
+#[cfg(test)]
+pub mod test_support;
+pub mod vivify;
@@ -1,4 +1,6 @@
 use crate::functions::enqueue::*;
+use crate::functions::share::*;
+use crate::models::arena::*;
 use crate::models::clause::*;
 use crate::models::lbool::*;
 use crate::models::lit::*;
@@ -18,7 +20,13 @@ use crate::models::varorder::*;
 |    learnt - Is the clause a learnt clause? For learnt clauses, 'ps[0]' is assumed to be the
 |             asserting literal. An appropriate 'enqueue()' operation will be performed on this
 |             literal. One of the watches will always be on this literal, the other will be set to
-|             the literal with the highest decision level.
+|             the literal with the highest decision level. The asserting literal is enqueued at
+|             that level, which is below the current one after a chronological backtrack.
+|    theory_clause - A theory lemma, added during the search. The watches are put on the literals
+|             assigned last, or not at all, and the clause is kept with the learnt clauses. If the
+|             lemma is unit its literal is enqueued at the level of the other literals, a single
+|             literal lemma becomes a top-level fact. A lemma that is false is left for the caller
+|             to handle as a conflict.
 |
 |  Effect:
 |    Activity heuristics are updated.
@@ -34,8 +42,11 @@ pub trait NewClause {
         _theory_clause: bool,
         _copy: bool,
     );
-    fn remove(&mut self, c: Clause, just_dealloc: bool);
-    fn simplify(&mut self, k: i32, t: i32) -> bool;
+    fn new_root_learnt(&mut self, ps: &mut Vec<Lit>);
+    fn lbd(&self, ps: &[Lit]) -> u32;
+    fn attach(&mut self, cr: ClauseRef);
+    fn remove(&mut self, cr: ClauseRef, just_dealloc: bool);
+    fn simplify(&mut self, cr: ClauseRef) -> bool;
     fn new_var(&mut self) -> i32;
     fn assume(&mut self, p: Lit) -> bool;
     fn cancel_until(&mut self, level: i32);
@@ -48,48 +59,32 @@ struct Dict {
 }
 
 impl NewClause for SolverState {
-    fn reorder_by_level(&mut self, mut _ps: &mut Vec<Lit>) {
+    // Moves the literal assigned last to 'ps[0]' and the one assigned last of the others to
+    // 'ps[1]'. Literals that are not false count as assigned after every false one.
+    fn reorder_by_level(&mut self, _ps: &mut Vec<Lit>) {
         trace!(
             "{}|{}|{}|{:?}",
-            "reorder_by_level".to_string(),
+            "reorder_by_level",
             file!(),
             line!(),
             _ps,
         );
 
-        let mut max: i32 = i32::MIN;
-        let mut max_at: i32 = -1;
-        let mut max2: i32 = i32::MIN;
-        let mut max2_at: i32 = -1;
-
-        for (i, lt) in _ps.iter().enumerate() {
-            let mut lev: i32 = self.level[var(lt) as usize];
-            if lev == -1 || self.value_by_lit(*lt) == Lbool::True {
-                lev = i32::MAX;
+        let key = |lt: &Lit| -> i32 {
+            if self.value_by_lit(*lt) != Lbool::False {
+                i32::MAX
+            } else {
+                self.level[var(lt) as usize]
             }
-
-            if lev >= max {
-                max2_at = max_at;
-                max2 = max;
-                max = lev;
-                max_at = i as i32;
-            } else if lev > max2 {
-                max2 = lev;
-                max2_at = i as i32;
+        };
+        for i in 0..2 {
+            let mut max_at: usize = i;
+            for k in i + 1.._ps.len() {
+                if key(&_ps[k]) > key(&_ps[max_at]) {
+                    max_at = k;
+                }
             }
-        }
-
-        if max_at == 0 {
-            _ps.swap(1_usize, max2_at as usize);
-        } else if max_at == 1 {
-            _ps.swap(0_usize, max2_at as usize);
-        } else if max2_at == 0 {
-            _ps.swap(1_usize, max_at as usize);
-        } else if max2_at == 1 {
-            _ps.swap(0_usize, max_at as usize);
-        } else {
-            _ps.swap(0_usize, max_at as usize);
-            _ps.swap(1_usize, max2_at as usize);
+            _ps.swap(i, max_at);
         }
     }
 
@@ -106,7 +101,7 @@ impl NewClause for SolverState {
     ) {
         trace!(
             "{}|{}|{}|{:?}",
-            "new_clause".to_string(),
+            "new_clause",
             file!(),
             line!(),
             _ps
@@ -116,9 +111,13 @@ impl NewClause for SolverState {
             return;
         };
 
-        let ps: Vec<Lit>;
+        let mut ps: Vec<Lit>;
 
         assert!(!(_learnt && _theory_clause));
+        let lbd: u32 = if _learnt { self.lbd(_ps) } else { 0 };
+        if _learnt {
+            self.export_learnt(_ps, lbd);
+        }
 
         if !_learnt {
             assert!(_theory_clause || self.decision_level() == 0);
@@ -161,95 +160,192 @@ impl NewClause for SolverState {
         if ps.is_empty() {
             self.ok = false;
         } else if ps.len() == 1 {
-            if _theory_clause {
-                self.level_to_backtrack = 0;
-                self.cancel_until(0);
-            }
-            if !self.internal_enqueue(&ps[0]) {
+            let x: usize = var(&ps[0]) as usize;
+            if !_theory_clause || self.decision_level() == 0 {
+                // a learnt unit holds at the root even when chronological backtracking kept
+                // some decisions, cancel_until then leaves it on the trail
+                if !self.enqueue_at(&ps[0], None, 0) {
+                    self.ok = false;
+                }
+            } else if is_undefined(self.value_by_lit(ps[0])) {
+                self.enqueue_at(&ps[0], None, 0);
+            } else if self.value_by_lit(ps[0]) == Lbool::True {
+                self.level[x] = 0;
+                self.reason[x] = None;
+            } else if self.level[x] == 0 {
                 self.ok = false;
+            } else {
+                // the negation was implied at a higher level, undo that level and keep the fact
+                self.cancel_until(self.level[x] - 1);
+                self.enqueue_at(&ps[0], None, 0);
             }
         } else {
             if _theory_clause {
-                self.reorder_by_level(&mut ps.to_vec())
+                self.reorder_by_level(&mut ps)
             }
 
             let mut c: Clause = Clause::new(_learnt || _theory_clause, &ps, self.clause_id_counter);
             self.clause_id_counter += 1;
 
+            if _learnt {
+                c.lbd = lbd;
+                let mut max_i: usize = 1;
+                let mut max: i32 = self.level[var(&ps[1]) as usize];
+                for (y, lt) in ps.iter().enumerate().skip(2) {
+                    if self.level[var(lt) as usize] > max {
+                        max = self.level[var(lt) as usize];
+                        max_i = y;
+                    }
+                }
+                c.data[1] = ps[max_i];
+                c.data[max_i] = ps[1];
+            }
+
+            let size = c.size() as f64;
+            let first: Lit = c.data[0];
+            let second: Lit = c.data[1];
+            let cr: ClauseRef = self.ca.alloc(c);
+
             if !_learnt && !_theory_clause {
-                self.clauses.push(c.clone());
-                self.solver_stats.clauses_literals += c.size() as f64;
+                self.clauses.push(cr);
+                self.solver_stats.clauses_literals += size;
             } else {
                 if _learnt {
-                    let mut max_i: usize = 1;
-                    let mut max: i32 = self.level[var(&ps[1]) as usize];
-                    for (y, lt) in ps.iter().enumerate().skip(2) {
-                        if self.level[var(lt) as usize] > max {
-                            max = self.level[var(lt) as usize];
-                            max_i = y;
-                        }
-                    }
-                    c.data[1] = ps[max_i];
-                    c.data[max_i] = ps[1];
-
-                    assert!(self.enqueue(&c.data[0], Some(c.clone())));
-                } else {
-                    move_back(c.clone().data[0], c.clone().data[1], self);
+                    let from: Reason = if ps.len() == 2 {
+                        Reason::Binary(second)
+                    } else {
+                        Reason::Clause(cr)
+                    };
+                    let lv: i32 = self.level[var(&second) as usize];
+                    assert!(self.enqueue_at(&first, Some(from), lv));
+                } else if is_undefined(self.value_by_lit(first))
+                    && self.value_by_lit(second) == Lbool::False
+                {
+                    let lv: i32 = self.level[var(&second) as usize];
+                    let from: Reason = if ps.len() == 2 {
+                        Reason::Binary(second)
+                    } else {
+                        Reason::Clause(cr)
+                    };
+                    self.enqueue_at(&first, Some(from), lv);
                 }
 
-                self.cla_bump_activity(&mut c);
-                self.learnts.push(c.clone());
-                self.solver_stats.learnts_literals += c.clone().size() as f64;
+                self.cla_bump_activity(cr);
+                self.learnts.push(cr);
+                self.solver_stats.learnts_literals += size;
+            }
+
+            self.attach(cr);
+        }
+    }
+
+    // Adds a clause implied by the formula as a learnt clause while at the root level. Satisfied
+    // clauses are dropped and false literals removed, a clause that ends up unit is enqueued.
+    fn new_root_learnt(&mut self, ps: &mut Vec<Lit>) {
+        trace!(
+            "{}|{}|{}|{:?}",
+            "new_root_learnt",
+            file!(),
+            line!(),
+            ps
+        );
+        assert!(self.decision_level() == 0);
+
+        if !self.ok {
+            return;
+        }
+        let mut qs: Vec<Lit> = match basic_clause_simplification(ps.to_vec(), true) {
+            Some(v) => v,
+            None => return,
+        };
+        if qs.iter().any(|q| self.value_by_lit(*q) == Lbool::True) {
+            return;
+        }
+        qs.retain(|q| self.value_by_lit(*q) != Lbool::False);
+
+        if qs.is_empty() {
+            self.ok = false;
+        } else if qs.len() == 1 {
+            if !self.internal_enqueue(&qs[0]) {
+                self.ok = false;
             }
+        } else {
+            let size = qs.len() as f64;
+            let c: Clause = Clause::new(true, &qs, self.clause_id_counter);
+            self.clause_id_counter += 1;
+            let cr: ClauseRef = self.ca.alloc(c);
+            self.cla_bump_activity(cr);
+            self.learnts.push(cr);
+            self.solver_stats.learnts_literals += size;
+            self.attach(cr);
+        }
+    }
 
-            let watch_position_zero = (!c.data[0]).x as usize;
-            let watch_position_one = (!c.data[1]).x as usize;
+    // The number of decision levels among the literals of a learnt clause, 'ps[0]' is counted as
+    // the level it will be asserted at.
+    fn lbd(&self, ps: &[Lit]) -> u32 {
+        let mut levels: Vec<i32> = ps[1..]
+            .iter()
+            .map(|p| self.level[var(p) as usize])
+            .collect();
+        levels.sort_unstable();
+        levels.dedup();
+        levels.len() as u32 + 1
+    }
 
-            self.watches[watch_position_zero].push(c.clone());
-            self.watches[watch_position_one].push(c.clone());
+    fn attach(&mut self, cr: ClauseRef) {
+        let first: Lit = self.ca[cr].data[0];
+        let second: Lit = self.ca[cr].data[1];
+        if self.ca[cr].size() == 2 {
+            self.bin_watches[(!first).x as usize].push(BinWatcher {
+                cref: cr,
+                implied: second,
+            });
+            self.bin_watches[(!second).x as usize].push(BinWatcher {
+                cref: cr,
+                implied: first,
+            });
+        } else {
+            self.watches[(!first).x as usize].push(Watcher {
+                cref: cr,
+                blocker: second,
+            });
+            self.watches[(!second).x as usize].push(Watcher {
+                cref: cr,
+                blocker: first,
+            });
         }
     }
 
-    fn remove(&mut self, c: Clause, just_dealloc: bool) {
-        trace!("{}|{}|{}|{:?}", "remove".to_string(), file!(), line!(), c);
+    fn remove(&mut self, cr: ClauseRef, just_dealloc: bool) {
+        trace!("{}|{}|{}|{:?}", "remove", file!(), line!(), self.ca[cr]);
 
+        let first: Lit = self.ca[cr].data[0];
+        let second: Lit = self.ca[cr].data[1];
         if !just_dealloc {
-            remove_watch(
-                &mut self.watches[(!c.clone().data[0]).x as usize],
-                c.clone(),
-            );
-            remove_watch(
-                &mut self.watches[(!c.clone().data[1]).x as usize],
-                c.clone(),
-            );
+            if self.ca[cr].size() == 2 {
+                remove_bin_watch(&mut self.bin_watches[(!first).x as usize], cr);
+                remove_bin_watch(&mut self.bin_watches[(!second).x as usize], cr);
+            } else {
+                remove_watch(&mut self.watches[(!first).x as usize], cr);
+                remove_watch(&mut self.watches[(!second).x as usize], cr);
+            }
         }
 
-        if c.learnt() {
-            self.solver_stats.learnts_literals -= c.size() as f64;
+        if self.ca[cr].learnt() {
+            self.solver_stats.learnts_literals -= self.ca[cr].size() as f64;
         } else {
-            self.solver_stats.clauses_literals -= c.size() as f64;
+            self.solver_stats.clauses_literals -= self.ca[cr].size() as f64;
         }
+        self.ca.free(cr);
     }
-    fn simplify(&mut self, k: i32, t: i32) -> bool {
-        trace!(
-            "{}|{}|{}|{}|{}",
-            "simplify".to_string(),
-            file!(),
-            line!(),
-            k,
-            t
-        );
+    fn simplify(&mut self, cr: ClauseRef) -> bool {
+        trace!("{}|{}|{}|{:?}", "simplify", file!(), line!(), cr);
         assert!(self.decision_level() == 0);
 
-        let c = if t != 0 {
-            &self.learnts[k as usize]
-        } else {
-            &self.clauses[k as usize]
-        };
-
+        let c: &Clause = &self.ca[cr];
         for y in 0..c.size() {
-            let f = self.clone().value_by_lit(c.data[y as usize]);
-            if f == Lbool::True {
+            if self.value_by_lit(c.data[y as usize]) == Lbool::True {
                 return true;
             }
         }
@@ -257,23 +353,27 @@ impl NewClause for SolverState {
     }
 
     fn new_var(&mut self) -> i32 {
-        trace!("{}|{}|{}", "new_var".to_string(), file!(), line!(),);
+        trace!("{}|{}|{}", "new_var", file!(), line!(),);
 
         let index: i32 = self.assigns.col.len() as i32;
         self.watches.push(Vec::new());
         self.watches.push(Vec::new());
+        self.bin_watches.push(Vec::new());
+        self.bin_watches.push(Vec::new());
         self.reason.push(None);
         self.add_assigns(Lbool::Undef0);
         self.level.push(-1);
         self.trail_pos.push(-1);
         self.add_activity(0.0);
-        self.order.new_var();
+        self.order.new_var(&self.activity.col);
         self.analyze_seen.push(Lbool::Undef0);
+        self.frozen.push(false);
+        self.eliminated.push(false);
 
         index
     }
     fn assume(&mut self, p: Lit) -> bool {
-        trace!("{}|{}|{}|{:?}", "assume".to_string(), file!(), line!(), p);
+        trace!("{}|{}|{}|{:?}", "assume", file!(), line!(), p);
 
         self.trail_lim.push(self.trail.len() as i32);
         self.i_enqueue(p)
@@ -282,23 +382,51 @@ impl NewClause for SolverState {
     fn cancel_until(&mut self, level: i32) {
         trace!(
             "{}|{}|{}|{}",
-            "cancel_until".to_string(),
+            "cancel_until",
             file!(),
             line!(),
             level
         );
 
         if self.decision_level() > level {
-            for y in (self.trail_lim[level as usize]..=(self.trail.len() as i32 - 1)).rev() {
-                let x = var(&self.trail[y as usize]) as usize;
+            let start: usize = self.trail_lim[level as usize] as usize;
+            let mut kept: bool = false;
+            for y in (start..self.trail.len()).rev() {
+                let x = var(&self.trail[y]) as usize;
+                if self.level[x] <= level {
+                    kept = true;
+                    continue;
+                }
+                self.order.save_phase(x as i32, !sign(&self.trail[y]));
                 self.update_assigns(Lbool::Undef0, x);
                 self.reason[x] = None;
-                self.order.undo(x as i32);
+                self.order.undo(x as i32, &self.activity.col);
+            }
+
+            // literals implied out of order below 'level' stay on the trail, in the same order,
+            // and are propagated again
+            let mut j: usize = start;
+            if kept {
+                for y in start..self.trail.len() {
+                    let p: Lit = self.trail[y];
+                    if !is_undefined(self.value_by_lit(p)) {
+                        self.trail[j] = p;
+                        self.trail_pos[var(&p) as usize] = j as i32;
+                        j += 1;
+                    }
+                }
             }
 
-            self.trail.truncate(self.trail_lim[level as usize] as usize);
+            self.trail.truncate(j);
             self.trail_lim.truncate(level as usize);
-            self.qhead = self.trail.len() as i32;
+            if self.theory_head > start {
+                self.theory_head = start;
+                if let Some(theory) = self.theory.as_mut() {
+                    theory.backtrack(start);
+                }
+            }
+            self.qhead = start as i32;
+            self.bin_qhead = start as i32;
         }
     }
 }
@@ -306,7 +434,7 @@ impl NewClause for SolverState {
 fn basic_clause_simplification(_ps: Vec<Lit>, _copy: bool) -> Option<Vec<Lit>> {
     trace!(
         "{}|{}|{}|{:?}",
-        "basic_clause_simplification".to_string(),
+        "basic_clause_simplification",
         file!(),
         line!(),
         _ps,
@@ -346,28 +474,26 @@ fn basic_clause_simplification(_ps: Vec<Lit>, _copy: bool) -> Option<Vec<Lit>> {
     Some(qs)
 }
 
-fn remove_watch(ws: &mut Vec<Clause>, elem: Clause) -> bool {
-    trace!(
-        "{}|{}|{}|{:?}",
-        "remove_watch".to_string(),
-        file!(),
-        line!(),
-        ws
-    );
+fn remove_watch(ws: &mut Vec<Watcher>, elem: ClauseRef) -> bool {
+    trace!("{}|{}|{}|{:?}", "remove_watch", file!(), line!(), elem);
 
-    if ws.is_empty() {
-        return false;
-    }
-    let mut j: usize = 0;
-    while ws[j] != elem {
-        if j >= ws.len() - 1 {
-            return false;
+    match ws.iter().position(|w| w.cref == elem) {
+        Some(j) => {
+            ws.remove(j);
+            true
         }
-        j += 1;
+        None => false,
     }
-    for y in j..ws.len() - 1 {
-        ws[y] = ws[y + 1].clone();
+}
+
+fn remove_bin_watch(ws: &mut Vec<BinWatcher>, elem: ClauseRef) -> bool {
+    trace!("{}|{}|{}|{:?}", "remove_bin_watch", file!(), line!(), elem);
+
+    match ws.iter().position(|w| w.cref == elem) {
+        Some(j) => {
+            ws.remove(j);
+            true
+        }
+        None => false,
     }
-    ws.pop();
-    true
 }
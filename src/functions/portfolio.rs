@@ -0,0 +1,189 @@
+use crate::functions::solve::*;
+use crate::models::clause_pool::*;
+use crate::models::lit::*;
+use crate::models::solverstate::*;
+use crate::models::varorder::*;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+
+/*_________________________________________________________________________________________________
+|
+|  solve_portfolio
+|
+|  Description:
+|    Solves the same problem with several differently configured solvers, one thread each. Every
+|    state of 'states' must hold the same clauses, the i-th one is run with 'configs[i]'. The
+|    first solver that finishes raises the shared 'interrupt' flag, the others give up at their
+|    next decision or restart. With 'share' set the solvers exchange their short and low LBD
+|    learnt clauses through a common 'ClausePool', see 'export_learnt()'.
+|
+|  Output:
+|    The index of the solver that finished first and its state, with the model or the conflict
+|    as after 'solve()'.
+|________________________________________________________________________________________________@*/
+pub fn solve_portfolio(
+    states: Vec<SolverState>,
+    configs: &[PortfolioConfig],
+    assumptions: &[Lit],
+    share: bool,
+) -> (usize, SolverState) {
+    assert!(!states.is_empty() && states.len() <= configs.len());
+    let stop: Arc<AtomicBool> = Arc::new(AtomicBool::new(false));
+    let pool: Arc<ClausePool> = Arc::new(ClausePool::new());
+
+    let finished: Vec<(bool, SolverState)> = thread::scope(|scope| {
+        let workers: Vec<thread::ScopedJoinHandle<(bool, SolverState)>> = states
+            .into_iter()
+            .zip(configs)
+            .enumerate()
+            .map(|(worker, (mut state, config))| {
+                let stop: Arc<AtomicBool> = Arc::clone(&stop);
+                let pool: Arc<ClausePool> = Arc::clone(&pool);
+                scope.spawn(move || {
+                    config.apply(&mut state);
+                    state.interrupt = Some(Arc::clone(&stop));
+                    if share {
+                        state.sharing = Some(ClauseSharing::new(pool, worker));
+                    }
+                    state.solve(assumptions.to_vec());
+                    let won: bool = stop
+                        .compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst)
+                        .is_ok();
+                    state.interrupt = None;
+                    state.sharing = None;
+                    (won, state)
+                })
+            })
+            .collect();
+        workers.into_iter().map(|w| w.join().unwrap()).collect()
+    });
+
+    let winner: usize = finished.iter().position(|(won, _)| *won).unwrap();
+    info!("portfolio won by {}", configs[winner].name);
+    (winner, finished.into_iter().nth(winner).unwrap().1)
+}
+
+// The settings in which the solvers of a portfolio differ.
+#[derive(Clone, Debug)]
+pub struct PortfolioConfig {
+    pub name: String,
+    pub parms: SearchParams,
+    pub random_seed: f64,
+    pub restart_policy: RestartPolicy,
+    pub polarity_mode: PolarityMode,
+    pub chrono_backtrack: i32,
+}
+
+pub trait IPortfolioConfig {
+    fn diversified(n: usize) -> Vec<PortfolioConfig>;
+    fn apply(&self, state: &mut SolverState);
+}
+
+impl IPortfolioConfig for PortfolioConfig {
+    // 'n' configurations, the first one being the search settings of 'new_simp()'. Past the base
+    // ones the same settings come again with other random seeds.
+    fn diversified(n: usize) -> Vec<PortfolioConfig> {
+        let defaults: SolverState = SolverState::new();
+        let parms: SearchParams = defaults.default_parms;
+        let geometric: RestartPolicy = defaults.restart_policy;
+        let luby: RestartPolicy = RestartPolicy::Luby { unit: 100.0 };
+        let base: Vec<(&str, f64, f64, RestartPolicy, PolarityMode, i32)> = vec![
+            ("default", 0.95, 0.02, geometric, PolarityMode::Saved, 100),
+            ("luby-target", 0.95, 0.02, luby, PolarityMode::Target, 100),
+            ("fast-decay", 0.85, 0.05, luby, PolarityMode::Saved, 0),
+            ("negative", 0.95, 0.02, geometric, PolarityMode::False, -1),
+            ("random", 0.9, 0.1, luby, PolarityMode::Random, 100),
+            ("positive", 0.99, 0.01, geometric, PolarityMode::True, 100),
+        ];
+
+        (0..n)
+            .map(|i| {
+                let (name, var_decay, random_var_freq, restart_policy, polarity_mode, chrono) =
+                    base[i % base.len()];
+                let round: usize = i / base.len();
+                PortfolioConfig {
+                    name: if round == 0 {
+                        name.to_string()
+                    } else {
+                        format!("{}-{}", name, round)
+                    },
+                    parms: SearchParams {
+                        var_decay,
+                        random_var_freq,
+                        ..parms
+                    },
+                    random_seed: defaults.order.random_seed + 7919.0 * i as f64,
+                    restart_policy,
+                    polarity_mode,
+                    chrono_backtrack: chrono,
+                }
+            })
+            .collect()
+    }
+
+    fn apply(&self, state: &mut SolverState) {
+        state.default_parms = self.parms;
+        state.order.random_seed = self.random_seed;
+        state.order.polarity_mode = self.polarity_mode;
+        state.restart_policy = self.restart_policy;
+        state.chrono_backtrack = self.chrono_backtrack;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::functions::new_clause::*;
+    use crate::functions::portfolio::*;
+    use crate::functions::test_support::*;
+    use crate::models::lbool::*;
+
+    #[test]
+    fn luby_sequence() {
+        let luby = RestartPolicy::Luby { unit: 1.0 };
+        let seq: Vec<f64> = (0..15).map(|i| luby.conflicts(i)).collect();
+        assert_eq!(
+            seq,
+            vec![1., 1., 2., 1., 1., 2., 4., 1., 1., 2., 1., 1., 2., 4., 8.]
+        );
+        let geometric = SolverState::new().restart_policy;
+        assert_eq!(geometric.conflicts(2), 225.0);
+    }
+
+    #[test]
+    fn first_finisher_wins() {
+        fn is_send<T: Send>() {}
+        is_send::<SolverState>();
+
+        let configs: Vec<PortfolioConfig> = PortfolioConfig::diversified(8);
+        assert_eq!(configs[0].name, "default");
+        assert_eq!(configs[7].name, "luby-target-1");
+
+        let states: Vec<SolverState> = (0..8).map(|_| pigeons(7, 6)).collect();
+        let (winner, state) = solve_portfolio(states, &configs, &[], false);
+        assert!(winner < 8);
+        assert!(!state.ok && state.interrupt.is_none());
+
+        // whichever configuration wins, the assumption forces the model
+        let mut states: Vec<SolverState> = Vec::new();
+        for _ in 0..4 {
+            let mut state = SolverState::new();
+            for v in 0..6 {
+                state.new_var();
+                state.set_frozen(v, true);
+            }
+            state.add_clause(&mut vec![Lit::new(0, false), Lit::new(1, false)]);
+            state.add_clause(&mut vec![Lit::new(0, true), Lit::new(2, false)]);
+            states.push(state);
+        }
+        let (_, state) = solve_portfolio(states, &configs, &[Lit::new(0, false)], true);
+        assert_eq!(state.model[0], L_TRUE);
+        assert_eq!(state.model[2], L_TRUE);
+
+        // a raised flag stops a solver before its first restart
+        let mut state = pigeons(10, 9);
+        state.interrupt = Some(Arc::new(AtomicBool::new(true)));
+        assert_eq!(state.solve_limited(vec![]), Lbool::Undef0);
+        assert!(state.ok && state.model.is_empty() && state.conflict.is_empty());
+    }
+}
@@ -0,0 +1,588 @@
+use crate::functions::garbage_collect::*;
+use crate::functions::new_clause::*;
+use crate::functions::propagate::*;
+use crate::models::arena::*;
+use crate::models::extension::*;
+use crate::models::lbool::*;
+use crate::models::lit::*;
+use crate::models::solverstate::*;
+use crate::models::varorder::*;
+
+/*_________________________________________________________________________________________________
+|
+|  preprocess
+|
+|  Description:
+|    SatELite style simplification of the original clauses, run at the root level before search.
+|    Backward subsumption and self-subsuming resolution are done with occurrence lists, then
+|    variables are eliminated by clause distribution as long as the number of resolvents does not
+|    grow by more than 'grow' and no resolvent is longer than 'clause_lim'.
+|
+|    Finally clauses that are blocked on one of their literals are removed (BCE). With 'covered'
+|    set, a clause is first extended by covered literal addition: when all the non tautological
+|    resolvents on a literal share some literals, these can be added to the clause, which may
+|    make it blocked (CCE). The blocking and covering variables count as eliminated from then on,
+|    since the extension may flip them.
+|
+|    Frozen variables and the variables of the assumptions are never eliminated, nor used as
+|    blocking or covering literals. The removed clauses are pushed on the model extension stack,
+|    'extend_model' uses them to fix the model of the remaining formula once it has been found.
+|    Since this runs on every 'solve()', callers have to freeze the variables of later assumptions
+|    and clauses, 'solve()' and 'add_clause()' refuse eliminated ones.
+|
+|  Output:
+|    FALSE if the formula was found to be unsatisfiable, TRUE otherwise.
+|________________________________________________________________________________________________@*/
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Subsumption {
+    None,
+    Subsumes,
+    // the clause can be strengthened by removing the negation of this literal
+    Strengthen(Lit),
+}
+
+pub trait Preprocess {
+    fn preprocess(&mut self, assumptions: &[Lit]) -> bool;
+    fn backward_subsumption(
+        &mut self,
+        occs: &mut [Vec<ClauseRef>],
+        queue: &mut Vec<ClauseRef>,
+    ) -> bool;
+    fn eliminate_var(
+        &mut self,
+        v: i32,
+        occs: &mut [Vec<ClauseRef>],
+        queue: &mut Vec<ClauseRef>,
+    ) -> bool;
+    fn add_derived(
+        &mut self,
+        ps: Vec<Lit>,
+        occs: &mut [Vec<ClauseRef>],
+        queue: &mut Vec<ClauseRef>,
+    ) -> bool;
+    fn eliminate_blocked(&mut self, frozen: &[bool], occs: &mut [Vec<ClauseRef>]);
+    fn eliminate_blocked_clause(
+        &mut self,
+        cr: ClauseRef,
+        frozen: &[bool],
+        occs: &mut [Vec<ClauseRef>],
+    ) -> bool;
+    fn remove_eliminated_learnts(&mut self);
+    fn extend_model(&mut self);
+}
+
+impl Preprocess for SolverState {
+    fn preprocess(&mut self, assumptions: &[Lit]) -> bool {
+        trace!("{}|{}|{}|{:?}", "preprocess", file!(), line!(), assumptions);
+
+        if !self.ok {
+            return false;
+        }
+        assert!(self.decision_level() == 0);
+        if self.propagate().is_some() {
+            self.ok = false;
+            return false;
+        }
+
+        let mut frozen: Vec<bool> = self.frozen.clone();
+        for p in assumptions {
+            frozen[var(p) as usize] = true;
+        }
+
+        let mut occs: Vec<Vec<ClauseRef>> = vec![Vec::new(); 2 * self.n_vars() as usize];
+        for &cr in &self.clauses {
+            for p in &self.ca[cr].data {
+                occs[p.x as usize].push(cr);
+            }
+        }
+        let mut queue: Vec<ClauseRef> = self.clauses.clone();
+
+        let mut ok = self.backward_subsumption(&mut occs, &mut queue);
+
+        let mut candidates: Vec<i32> = (0..self.n_vars())
+            .filter(|&v| {
+                !frozen[v as usize]
+                    && !self.eliminated[v as usize]
+                    && is_undefined(self.value_by_var(v))
+            })
+            .collect();
+        candidates.sort_by_key(|&v| {
+            occs[Lit::simple(v).x as usize].len() * occs[(!Lit::simple(v)).x as usize].len()
+        });
+
+        for v in candidates {
+            if !ok || !self.preprocess_parms.elim {
+                break;
+            }
+            if is_undefined(self.value_by_var(v)) {
+                ok = self.eliminate_var(v, &mut occs, &mut queue)
+                    && self.backward_subsumption(&mut occs, &mut queue);
+            }
+        }
+
+        if ok && self.preprocess_parms.blocked {
+            self.eliminate_blocked(&frozen, &mut occs);
+        }
+
+        self.remove_eliminated_learnts();
+        let ca: &ClauseArena = &self.ca;
+        self.clauses.retain(|cr| !ca[*cr].deleted);
+        self.learnts.retain(|cr| !ca[*cr].deleted);
+        self.check_garbage();
+        ok
+    }
+
+    fn backward_subsumption(
+        &mut self,
+        occs: &mut [Vec<ClauseRef>],
+        queue: &mut Vec<ClauseRef>,
+    ) -> bool {
+        while let Some(cr) = queue.pop() {
+            if self.ca[cr].deleted {
+                continue;
+            }
+            let c: Vec<Lit> = self.ca[cr].data.clone();
+            let best: Lit = *c
+                .iter()
+                .min_by_key(|p| occs[p.x as usize].len() + occs[(!**p).x as usize].len())
+                .unwrap();
+            if occs[best.x as usize].len() + occs[(!best).x as usize].len()
+                > self.preprocess_parms.subsumption_lim
+            {
+                continue;
+            }
+
+            for l in [best, !best] {
+                occs[l.x as usize].retain(|d| !self.ca[*d].deleted);
+                let candidates: Vec<ClauseRef> = occs[l.x as usize].clone();
+                for d in candidates {
+                    if d == cr || self.ca[d].deleted || self.ca[d].data.len() < c.len() {
+                        continue;
+                    }
+                    match subsumes(&c, &self.ca[d].data) {
+                        Subsumption::Subsumes => {
+                            self.remove(d, false);
+                            self.solver_stats.subsumed_clauses += 1.0;
+                        }
+                        Subsumption::Strengthen(p) => {
+                            let ps: Vec<Lit> =
+                                self.ca[d].data.iter().copied().filter(|q| *q != !p).collect();
+                            self.remove(d, false);
+                            self.solver_stats.strengthened_clauses += 1.0;
+                            if !self.add_derived(ps, occs, queue) {
+                                return false;
+                            }
+                        }
+                        Subsumption::None => {}
+                    }
+                }
+            }
+        }
+        true
+    }
+
+    fn eliminate_var(
+        &mut self,
+        v: i32,
+        occs: &mut [Vec<ClauseRef>],
+        queue: &mut Vec<ClauseRef>,
+    ) -> bool {
+        let pos_lit: Lit = Lit::simple(v);
+        let neg_lit: Lit = !pos_lit;
+        for l in [pos_lit, neg_lit] {
+            occs[l.x as usize].retain(|d| !self.ca[*d].deleted);
+        }
+        let pos: Vec<ClauseRef> = occs[pos_lit.x as usize].clone();
+        let neg: Vec<ClauseRef> = occs[neg_lit.x as usize].clone();
+        if pos.is_empty() && neg.is_empty() {
+            return true;
+        }
+
+        let limit: usize = pos.len() + neg.len() + self.preprocess_parms.grow;
+        let mut resolvents: Vec<Vec<Lit>> = Vec::new();
+        for &c in &pos {
+            for &d in &neg {
+                if let Some(r) = resolve(&self.ca[c].data, &self.ca[d].data, v) {
+                    if r.len() > self.preprocess_parms.clause_lim || resolvents.len() == limit {
+                        return true;
+                    }
+                    resolvents.push(r);
+                }
+            }
+        }
+
+        trace!(
+            "{}|{}|{}|{}|{}",
+            "eliminate_var",
+            file!(),
+            line!(),
+            v,
+            resolvents.len()
+        );
+
+        for (cs, witness) in [(&pos, pos_lit), (&neg, neg_lit)] {
+            for &cr in cs.iter() {
+                self.extension.push(witness, &self.ca[cr].data);
+                self.remove(cr, false);
+            }
+        }
+        self.eliminated[v as usize] = true;
+        self.order.set_decision_var(v, false, &self.activity.col);
+        self.solver_stats.eliminated_vars += 1.0;
+
+        for r in resolvents {
+            if !self.add_derived(r, occs, queue) {
+                return false;
+            }
+        }
+        true
+    }
+
+    // Adds a clause derived during preprocessing and registers it in the occurrence lists. Units
+    // are propagated right away.
+    fn add_derived(
+        &mut self,
+        mut ps: Vec<Lit>,
+        occs: &mut [Vec<ClauseRef>],
+        queue: &mut Vec<ClauseRef>,
+    ) -> bool {
+        let before: usize = self.clauses.len();
+        self.new_clause(&mut ps, false);
+        if self.clauses.len() > before {
+            let cr: ClauseRef = self.clauses[before];
+            for p in &self.ca[cr].data {
+                occs[p.x as usize].push(cr);
+            }
+            queue.push(cr);
+        }
+        if self.ok && self.propagate().is_some() {
+            self.ok = false;
+        }
+        self.ok
+    }
+
+    fn eliminate_blocked(&mut self, frozen: &[bool], occs: &mut [Vec<ClauseRef>]) {
+        for i in 0..self.clauses.len() {
+            let cr: ClauseRef = self.clauses[i];
+            if !self.ca[cr].deleted && self.eliminate_blocked_clause(cr, frozen, occs) {
+                trace!("{}|{}|{}|{:?}", "blocked", file!(), line!(), cr);
+                self.remove(cr, false);
+            }
+        }
+    }
+
+    // Checks if the clause is blocked, or covered when 'covered' is set, and if so pushes it on
+    // the model extension stack. Every covered literal addition on a literal 'k' is pushed with
+    // 'k' as witness and the clause as it was before the addition, the extended clause is pushed
+    // last with its blocking literal. Replayed in reverse, 'k' is flipped only when the clause
+    // was satisfied by the added literals alone, which every resolution partner on '~k' contains.
+    fn eliminate_blocked_clause(
+        &mut self,
+        cr: ClauseRef,
+        frozen: &[bool],
+        occs: &mut [Vec<ClauseRef>],
+    ) -> bool {
+        let mut c: Vec<Lit> = self.ca[cr].data.clone();
+        if c.iter().any(|p| self.value_by_lit(*p) == L_TRUE) {
+            return false;
+        }
+        let mut steps: Vec<(Lit, Vec<Lit>)> = Vec::new();
+
+        let mut i: usize = 0;
+        while i < c.len() {
+            let l: Lit = c[i];
+            i += 1;
+            if frozen[var(&l) as usize] || !is_undefined(self.value_by_lit(l)) {
+                continue;
+            }
+
+            occs[(!l).x as usize].retain(|d| !self.ca[*d].deleted);
+            let mut blocked: bool = true;
+            let mut common: Option<Vec<Lit>> = None;
+            for &d in &occs[(!l).x as usize] {
+                let ds: &[Lit] = &self.ca[d].data;
+                if ds.iter().any(|q| self.value_by_lit(*q) == L_TRUE)
+                    || ds.iter().any(|q| *q != !l && c.contains(&!*q))
+                {
+                    continue;
+                }
+                blocked = false;
+                if !self.preprocess_parms.covered {
+                    break;
+                }
+                match common.as_mut() {
+                    None => {
+                        common = Some(
+                            ds.iter()
+                                .copied()
+                                .filter(|q| *q != !l && !c.contains(q))
+                                .collect(),
+                        )
+                    }
+                    Some(ls) => ls.retain(|q| ds.contains(q)),
+                }
+                if common.as_ref().is_some_and(|ls| ls.is_empty()) {
+                    break;
+                }
+            }
+
+            if blocked {
+                if !steps.is_empty() {
+                    self.solver_stats.covered_clauses += 1.0;
+                } else {
+                    self.solver_stats.blocked_clauses += 1.0;
+                }
+                // the extension may flip the witnesses, so they can take no new clauses or
+                // assumptions although they still occur in the formula
+                for (k, ks) in &steps {
+                    self.extension.push(*k, ks);
+                    self.eliminated[var(k) as usize] = true;
+                }
+                self.extension.push(l, &c);
+                self.eliminated[var(&l) as usize] = true;
+                return true;
+            }
+
+            if let Some(ls) = common
+                && !ls.is_empty()
+                && c.len() + ls.len() <= self.preprocess_parms.clause_lim
+            {
+                steps.push((l, c.clone()));
+                c.extend(ls);
+                // more literals make more resolvents tautological, check the clause again
+                i = 0;
+            }
+        }
+        false
+    }
+
+    fn remove_eliminated_learnts(&mut self) {
+        for i in 0..self.learnts.len() {
+            let cr: ClauseRef = self.learnts[i];
+            if !self.ca[cr].deleted
+                && self.ca[cr]
+                    .data
+                    .iter()
+                    .any(|p| self.eliminated[var(p) as usize])
+            {
+                self.remove(cr, false);
+            }
+        }
+    }
+
+    fn extend_model(&mut self) {
+        self.extension.extend(&mut self.model);
+    }
+}
+
+// Checks if 'c' subsumes 'd', or if 'd' can be strengthened by self-subsuming resolution with 'c'.
+pub fn subsumes(c: &[Lit], d: &[Lit]) -> Subsumption {
+    let mut ret: Subsumption = Subsumption::Subsumes;
+    for p in c {
+        if d.contains(p) {
+            continue;
+        }
+        if ret == Subsumption::Subsumes && d.contains(&!*p) {
+            ret = Subsumption::Strengthen(*p);
+        } else {
+            return Subsumption::None;
+        }
+    }
+    ret
+}
+
+// Resolvent of 'c' and 'd' on variable 'v', or None if it is a tautology.
+pub fn resolve(c: &[Lit], d: &[Lit], v: i32) -> Option<Vec<Lit>> {
+    let mut r: Vec<Lit> = c.iter().copied().filter(|p| var(p) != v).collect();
+    for q in d {
+        if var(q) == v || r.contains(q) {
+            continue;
+        }
+        if r.contains(&!*q) {
+            return None;
+        }
+        r.push(*q);
+    }
+    Some(r)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::functions::preprocess::*;
+    use crate::functions::solve::*;
+    use crate::models::random::irand;
+
+    #[test]
+    fn subsumption_and_strengthening() {
+        assert_eq!(subsumes(&lits(&[1, 2]), &lits(&[1, 2, 3])), Subsumption::Subsumes);
+        assert_eq!(
+            subsumes(&lits(&[1, 2]), &lits(&[-1, 2, 3])),
+            Subsumption::Strengthen(lits(&[1])[0])
+        );
+        assert_eq!(subsumes(&lits(&[1, 2]), &lits(&[-1, -2, 3])), Subsumption::None);
+        assert_eq!(resolve(&lits(&[1, 2]), &lits(&[-1, 3]), 0), Some(lits(&[2, 3])));
+        assert_eq!(resolve(&lits(&[1, 2]), &lits(&[-1, -2]), 0), None);
+    }
+
+    #[test]
+    fn eliminated_variables_are_extended() {
+        let problem: Vec<Vec<i32>> = vec![
+            vec![1, 2],
+            vec![-2, 3],
+            vec![-3, 4, 5],
+            vec![-1, -4],
+            vec![2, -5, 6],
+            vec![-6, 1],
+        ];
+        let mut state = SolverState::new_simp();
+        for _ in 0..6 {
+            state.new_var();
+        }
+        state.set_frozen(0, true);
+        for c in &problem {
+            state.add_clause(&mut lits(c));
+        }
+        state.solve_no_assumptions();
+
+        assert!(state.solver_stats.eliminated_vars > 0.0);
+        assert!(!state.eliminated[0]);
+        assert!(state.ok && !state.model.is_empty());
+        for c in &problem {
+            assert!(lits(c)
+                .iter()
+                .any(|p| state.model[var(p) as usize] == to_bool(!sign(p))));
+        }
+    }
+
+    fn solve_blocked(problem: &[Vec<i32>], frozen: bool) -> SolverState {
+        let mut state = SolverState::new_simp();
+        for v in 0..5 {
+            state.new_var();
+            state.set_frozen(v, frozen);
+        }
+        state.preprocess_parms.elim = false;
+        state.probe_parms.enabled = false;
+        for c in problem {
+            state.add_clause(&mut lits(c));
+        }
+        state.solve_no_assumptions();
+
+        assert!(state.ok && !state.model.is_empty());
+        for c in problem {
+            assert!(lits(c)
+                .iter()
+                .any(|p| state.model[var(p) as usize] == to_bool(!sign(p))));
+        }
+        state
+    }
+
+    #[test]
+    fn blocked_and_covered_clauses_are_extended() {
+        // (x1 | x2) is not blocked, but all its resolvents on x1 contain x3 and once x3 is added
+        // the clause is blocked on x2
+        let problem: Vec<Vec<i32>> = vec![
+            vec![1, 2],
+            vec![-1, 3, 5],
+            vec![-1, 3, 4],
+            vec![-2, -3],
+            vec![-4, 2, -3],
+        ];
+        let state = solve_blocked(&problem, false);
+        assert_eq!(state.solver_stats.covered_clauses, 1.0);
+        assert!(state.solver_stats.blocked_clauses > 0.0);
+
+        let state = solve_blocked(&problem, true);
+        assert_eq!(state.solver_stats.covered_clauses, 0.0);
+        assert_eq!(state.solver_stats.blocked_clauses, 0.0);
+        assert_eq!(state.n_clauses(), problem.len());
+    }
+
+    // (x1 | x2) is blocked on x1, which the solution may then set to true against later units
+    #[test]
+    fn blocking_variables_take_no_new_clauses() {
+        let problem: Vec<Vec<i32>> = vec![vec![1, 2], vec![2, 3], vec![-2, -3]];
+        let state = solve_blocked(&problem, false);
+        assert!(state.solver_stats.blocked_clauses > 0.0);
+        assert!(state.eliminated[0]);
+
+        let mut state = solve_blocked(&problem, true);
+        state.add_clause(&mut lits(&[-1]));
+        state.add_clause(&mut lits(&[-2]));
+        state.solve_no_assumptions();
+        assert!(!state.ok);
+    }
+
+    #[test]
+    #[should_panic(expected = "clause uses an eliminated variable")]
+    fn clauses_on_blocking_variables_are_refused() {
+        let mut state = solve_blocked(&[vec![1, 2], vec![2, 3], vec![-2, -3]], false);
+        state.add_clause(&mut lits(&[-1]));
+    }
+
+    #[test]
+    #[should_panic(expected = "assumption on an eliminated variable")]
+    fn assuming_an_eliminated_variable_is_refused() {
+        let mut state = SolverState::new_simp();
+        for _ in 0..3 {
+            state.new_var();
+        }
+        state.add_clause(&mut lits(&[1, 2]));
+        state.add_clause(&mut lits(&[-2, 3]));
+        state.solve_no_assumptions();
+        let v: i32 = (0..3).find(|&v| state.eliminated[v as usize]).unwrap();
+        state.solve(vec![Lit::new(v, false)]);
+    }
+
+    // Random formulas solved again and again under changing assumptions and with clauses added
+    // in between, all on frozen variables, must get the answers of a solver without preprocessing.
+    #[test]
+    fn incremental_solving_on_frozen_variables() {
+        let mut seed: f64 = 1234.0;
+        for _ in 0..100 {
+            let mut simp = SolverState::new_simp();
+            let mut plain = SolverState::new();
+            simp.probe_parms.enabled = false;
+            for v in 0..10 {
+                simp.new_var();
+                plain.new_var();
+                simp.set_frozen(v, v < 4);
+            }
+            let random_clause = |vars: i32, seed: &mut f64| -> Vec<Lit> {
+                (0..3)
+                    .map(|_| Lit::new(irand(seed, vars), irand(seed, 2) == 1))
+                    .collect()
+            };
+            let mut problem: Vec<Vec<Lit>> =
+                (0..30).map(|_| random_clause(10, &mut seed)).collect();
+            for c in &problem {
+                simp.add_clause(&mut c.clone());
+                plain.add_clause(&mut c.clone());
+            }
+
+            for _ in 0..6 {
+                let assumps: Vec<Lit> = (0..1 + irand(&mut seed, 2))
+                    .map(|_| Lit::new(irand(&mut seed, 4), irand(&mut seed, 2) == 1))
+                    .collect();
+                if assumps.iter().any(|p| assumps.contains(&!*p)) {
+                    continue;
+                }
+                plain.solve(assumps.clone());
+                simp.solve(assumps.clone());
+                assert_eq!(simp.model.is_empty(), plain.model.is_empty());
+                if !simp.model.is_empty() {
+                    let units: Vec<Vec<Lit>> = assumps.iter().map(|p| vec![*p]).collect();
+                    for c in problem.iter().chain(&units) {
+                        let is_true = |p: &Lit| simp.model[var(p) as usize] == to_bool(!sign(p));
+                        assert!(c.iter().any(is_true));
+                    }
+                }
+
+                let c: Vec<Lit> = random_clause(4, &mut seed);
+                simp.add_clause(&mut c.clone());
+                plain.add_clause(&mut c.clone());
+                problem.push(c);
+            }
+        }
+    }
+}
@@ -0,0 +1,357 @@
+use crate::functions::enqueue::*;
+use crate::functions::garbage_collect::*;
+use crate::functions::new_clause::*;
+use crate::functions::propagate::*;
+use crate::models::arena::*;
+use crate::models::extension::*;
+use crate::models::lbool::*;
+use crate::models::lit::*;
+use crate::models::solverstate::*;
+use crate::models::varorder::*;
+use std::cmp::min;
+
+/*_________________________________________________________________________________________________
+|
+|  probe
+|
+|  Description:
+|    Inprocessing at the root level, called from 'simplifyDB()' once the search has done
+|    'probe_parms.interval' propagations since the last round.
+|
+|    First the strongly connected components of the binary implication graph are computed. All
+|    literals of a component are equivalent, each one is replaced by the component representative
+|    in 'clauses' and 'learnts' and the substituted variable is recorded on the model extension
+|    stack. Then the roots of the implication graph are assumed one by one: a root whose
+|    propagation fails is a failed literal and its negation is a top-level fact, otherwise every
+|    literal implied through a longer clause gives a hyper-binary resolvent (~root | implied). The
+|    probing stops once 'probe_parms.budget' propagations have been used and continues from the
+|    same literal in the next round.
+|
+|    Frozen variables and the variables of the current assumptions are never substituted.
+|
+|  Output:
+|    FALSE if the formula was found to be unsatisfiable, TRUE otherwise.
+|________________________________________________________________________________________________@*/
+pub trait Probe {
+    fn probe(&mut self) -> bool;
+    fn substitute_equivalences(&mut self) -> bool;
+    fn binary_sccs(&self) -> Vec<Vec<Lit>>;
+    fn substitute_clauses(&mut self, repr: &[Lit], learnt: bool);
+    fn probe_failed_literals(&mut self) -> bool;
+}
+
+impl Probe for SolverState {
+    fn probe(&mut self) -> bool {
+        trace!("{}|{}|{}", "probe", file!(), line!());
+        assert!(self.decision_level() == 0);
+
+        if !self.ok || self.propagate().is_some() {
+            self.ok = false;
+            return false;
+        }
+        self.substitute_equivalences() && self.probe_failed_literals()
+    }
+
+    fn substitute_equivalences(&mut self) -> bool {
+        let mut protected: Vec<bool> = self.frozen.clone();
+        for p in &self.assumptions {
+            protected[var(p) as usize] = true;
+        }
+
+        // 'repr[p.x]' is the literal that replaces 'p'
+        let mut repr: Vec<Lit> = (0..2 * self.n_vars()).map(|x| Lit { x }).collect();
+        let mut substituted: bool = false;
+        for scc in self.binary_sccs() {
+            if scc.iter().any(|p| scc.contains(&!*p)) {
+                self.ok = false;
+                return false;
+            }
+            // the dual component, made of the negations, picks the negated representative
+            let r: Lit = *scc
+                .iter()
+                .min_by_key(|p| (!protected[var(p) as usize], var(p)))
+                .unwrap();
+            for &p in &scc {
+                if p != r && !protected[var(&p) as usize] {
+                    repr[p.x as usize] = r;
+                    repr[(!p).x as usize] = !r;
+                    substituted = true;
+                }
+            }
+        }
+        if !substituted {
+            return true;
+        }
+
+        for v in 0..self.n_vars() {
+            let p: Lit = Lit::simple(v);
+            let r: Lit = repr[p.x as usize];
+            if r != p {
+                trace!("{}|{}|{}|{}|{:?}", "substitute", file!(), line!(), v, r);
+                self.extension.push(p, &[p, !r]);
+                self.extension.push(!p, &[!p, r]);
+                self.eliminated[v as usize] = true;
+                self.order.set_decision_var(v, false, &self.activity.col);
+                self.solver_stats.substituted_vars += 1.0;
+            }
+        }
+
+        self.substitute_clauses(&repr, false);
+        self.substitute_clauses(&repr, true);
+        self.check_garbage();
+        if self.ok && self.propagate().is_some() {
+            self.ok = false;
+        }
+        self.ok
+    }
+
+    // Tarjan's algorithm on the literals, following the binary implications between unassigned
+    // and not eliminated variables. Only components with more than one literal are returned.
+    fn binary_sccs(&self) -> Vec<Vec<Lit>> {
+        let n: usize = 2 * self.n_vars() as usize;
+        let active = |x: usize| {
+            let v: i32 = (x >> 1) as i32;
+            !self.eliminated[v as usize] && is_undefined(self.value_by_var(v))
+        };
+
+        let mut index: Vec<usize> = vec![usize::MAX; n];
+        let mut lowlink: Vec<usize> = vec![0; n];
+        let mut on_stack: Vec<bool> = vec![false; n];
+        let mut stack: Vec<usize> = Vec::new();
+        let mut counter: usize = 0;
+        let mut sccs: Vec<Vec<Lit>> = Vec::new();
+
+        for root in 0..n {
+            if index[root] != usize::MAX || !active(root) || self.bin_watches[root].is_empty() {
+                continue;
+            }
+            index[root] = counter;
+            lowlink[root] = counter;
+            counter += 1;
+            stack.push(root);
+            on_stack[root] = true;
+            let mut calls: Vec<(usize, usize)> = vec![(root, 0)];
+
+            while let Some(&(v, edge)) = calls.last() {
+                if edge < self.bin_watches[v].len() {
+                    calls.last_mut().unwrap().1 += 1;
+                    let w: usize = self.bin_watches[v][edge].implied.x as usize;
+                    if !active(w) {
+                        continue;
+                    }
+                    if index[w] == usize::MAX {
+                        index[w] = counter;
+                        lowlink[w] = counter;
+                        counter += 1;
+                        stack.push(w);
+                        on_stack[w] = true;
+                        calls.push((w, 0));
+                    } else if on_stack[w] {
+                        lowlink[v] = min(lowlink[v], index[w]);
+                    }
+                    continue;
+                }
+
+                calls.pop();
+                if let Some(&(u, _)) = calls.last() {
+                    lowlink[u] = min(lowlink[u], lowlink[v]);
+                }
+                if lowlink[v] == index[v] {
+                    let mut scc: Vec<Lit> = Vec::new();
+                    loop {
+                        let w: usize = stack.pop().unwrap();
+                        on_stack[w] = false;
+                        scc.push(Lit { x: w as i32 });
+                        if w == v {
+                            break;
+                        }
+                    }
+                    if scc.len() > 1 {
+                        sccs.push(scc);
+                    }
+                }
+            }
+        }
+        sccs
+    }
+
+    // Rewrites every clause of 'clauses' (or 'learnts') that has a substituted literal. Clauses
+    // satisfied at the root level are dropped instead, their reasons are not needed anymore.
+    fn substitute_clauses(&mut self, repr: &[Lit], learnt: bool) {
+        let cs: Vec<ClauseRef> = if learnt {
+            std::mem::take(&mut self.learnts)
+        } else {
+            std::mem::take(&mut self.clauses)
+        };
+
+        let mut kept: Vec<ClauseRef> = Vec::with_capacity(cs.len());
+        let mut rewritten: Vec<Vec<Lit>> = Vec::new();
+        for cr in cs {
+            let c: &[Lit] = &self.ca[cr].data;
+            if c.iter().all(|p| repr[p.x as usize] == *p) {
+                kept.push(cr);
+                continue;
+            }
+            if !c.iter().any(|p| self.value_by_lit(*p) == L_TRUE) {
+                rewritten.push(c.iter().map(|p| repr[p.x as usize]).collect());
+            }
+            if self.locked(cr) {
+                self.reason[var(&self.ca[cr].data[0]) as usize] = None;
+            }
+            self.remove(cr, false);
+        }
+
+        if learnt {
+            self.learnts = kept;
+        } else {
+            self.clauses = kept;
+        }
+        for mut ps in rewritten {
+            if learnt {
+                self.new_root_learnt(&mut ps);
+            } else {
+                self.new_clause(&mut ps, false);
+            }
+        }
+    }
+
+    fn probe_failed_literals(&mut self) -> bool {
+        let n: usize = 2 * self.n_vars() as usize;
+        let limit: f64 = self.solver_stats.propagations + self.probe_parms.budget;
+        let mut resolvents: Vec<Lit> = Vec::new();
+
+        for _ in 0..n {
+            if self.solver_stats.propagations >= limit {
+                break;
+            }
+            let p: Lit = Lit {
+                x: (self.probe_head % n) as i32,
+            };
+            self.probe_head = (self.probe_head + 1) % n;
+
+            // only the roots of the binary implication graph are probed
+            if !is_undefined(self.value_by_lit(p))
+                || self.eliminated[var(&p) as usize]
+                || self.bin_watches[p.x as usize].is_empty()
+                || !self.bin_watches[(!p).x as usize].is_empty()
+            {
+                continue;
+            }
+
+            self.assume(p);
+            if self.propagate().is_some() {
+                trace!("{}|{}|{}|{:?}", "failed_literal", file!(), line!(), p);
+                self.cancel_until(0);
+                self.solver_stats.failed_literals += 1.0;
+                self.enqueue(&!p, None);
+                if self.propagate().is_some() {
+                    self.ok = false;
+                    return false;
+                }
+                continue;
+            }
+
+            resolvents.clear();
+            let start: usize = self.trail_lim[0] as usize + 1;
+            for &q in &self.trail[start..] {
+                if let Some(Reason::Clause(_)) = self.reason[var(&q) as usize] {
+                    resolvents.push(q);
+                }
+            }
+            self.cancel_until(0);
+
+            for &q in &resolvents {
+                self.solver_stats.hyper_binaries += 1.0;
+                self.new_root_learnt(&mut vec![!p, q]);
+            }
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::functions::probe::*;
+    use crate::functions::solve::*;
+    use crate::functions::test_support::*;
+
+    #[test]
+    fn failed_literal_and_hyper_binary() {
+        // x1 implies x2 and x3, which can not both be true
+        let mut state = state_of(3, &[vec![-1, 2], vec![-1, 3], vec![-2, -3]]);
+        assert!(state.probe());
+        assert_eq!(state.solver_stats.failed_literals, 1.0);
+        assert_eq!(state.value_by_lit(lits(&[-1])[0]), L_TRUE);
+
+        // x1 implies x2 and then x3 through the ternary clause
+        let mut state = state_of(3, &[vec![-1, 2], vec![-1, -2, 3]]);
+        assert!(state.probe());
+        assert_eq!(state.solver_stats.hyper_binaries, 1.0);
+        let cr = *state.learnts.last().unwrap();
+        assert_eq!(state.ca[cr].data, lits(&[-1, 3]));
+    }
+
+    #[test]
+    fn equivalent_literals_are_substituted() {
+        // x1 = x2 = -x3
+        let problem: Vec<Vec<i32>> = vec![
+            vec![-1, 2],
+            vec![-2, 1],
+            vec![2, 3],
+            vec![-3, -1],
+            vec![1, 4, 5],
+            vec![-2, -4, 5],
+            vec![3, -5, 4],
+        ];
+        let mut state = state_of(5, &problem);
+        state.probe_parms.enabled = true;
+        state.solve_no_assumptions();
+
+        assert_eq!(state.solver_stats.substituted_vars, 2.0);
+        assert!(!state.eliminated[0]);
+        assert!(state.ok && !state.model.is_empty());
+        for c in &problem {
+            assert!(lits(c)
+                .iter()
+                .any(|p| state.model[var(p) as usize] == to_bool(!sign(p))));
+        }
+    }
+
+    // x1 = x3, so substitution eliminates one of them unless both are frozen
+    #[test]
+    #[should_panic(expected = "assumption on an eliminated variable")]
+    fn assuming_a_substituted_variable_is_refused() {
+        let mut state = state_of(3, &[vec![3, -1], vec![1, -3], vec![1, 3, 2]]);
+        state.probe_parms.enabled = true;
+        state.solve(vec![]);
+        state.solve(vec![]);
+        state.solve(lits(&[1, -3]));
+    }
+
+    #[test]
+    fn frozen_variables_can_be_assumed_between_substitutions() {
+        let problem: Vec<Vec<i32>> = vec![vec![3, -1], vec![1, -3], vec![1, 3, 2]];
+        let mut state = state_of(3, &problem);
+        state.probe_parms.enabled = true;
+        state.set_frozen(0, true);
+        state.set_frozen(2, true);
+
+        for (assumptions, sat) in [
+            (vec![], true),
+            (vec![1, -3], false),
+            (vec![-1], true),
+            (vec![-3, -2], false),
+            (vec![], true),
+            (vec![3, -2], true),
+        ] {
+            let assumps: Vec<Lit> = lits(&assumptions);
+            assert_eq!(state.solve(assumps.clone()), sat);
+            if sat {
+                let units: Vec<Vec<Lit>> = assumps.iter().map(|p| vec![*p]).collect();
+                for c in problem.iter().map(|c| lits(c)).chain(units) {
+                    assert!(c.iter().any(|p| state.model[var(p) as usize] == to_bool(!sign(p))));
+                }
+            }
+        }
+    }
+}
@@ -1,5 +1,5 @@
 use crate::functions::enqueue::*;
-use crate::models::clause::*;
+use crate::models::arena::*;
 use crate::models::lbool::*;
 use crate::models::lit::*;
 use crate::models::solverstate::*;
@@ -12,89 +12,157 @@ use crate::models::solverstate::*;
 |    Propagates all enqueued facts. If a conflict arises, the conflicting clause is returned,
 |    otherwise null. NOTE! This method has been optimized for speed rather than readability.
 |
+|    Binary implications of every enqueued fact are propagated before any longer clause is
+|    visited, using a separate queue head ('bin_qhead'). Watch lists of longer clauses are taken
+|    out of 'watches' while they are visited, clauses whose blocker literal is true are skipped
+|    without touching the clause arena.
+|
+|    An implied literal is assigned at the highest level of its reason. When the propagated fact
+|    is from a lower level than the current one (chronological backtracking) the clause is
+|    scanned for that level and its literal is made the second watch.
+|
 |    Post-conditions:
 |      * the propagation queue is empty, even if there was a conflict.
 |________________________________________________________________________________________________@*/
 pub trait Prop {
-    fn propagate(&mut self) -> Option<Clause>;
+    fn propagate(&mut self) -> Option<ClauseRef>;
+    fn propagate_binary(&mut self, p: Lit) -> Option<ClauseRef>;
+    fn propagate_long(&mut self, p: Lit) -> Option<ClauseRef>;
 }
 
 impl Prop for SolverState {
-    fn propagate(&mut self) -> Option<Clause> {
-        trace!("{}|{}|{}", "propagate".to_string(), file!(), line!(),);
+    fn propagate(&mut self) -> Option<ClauseRef> {
+        trace!("{}|{}|{}", "propagate", file!(), line!(),);
 
-        let mut confl: Option<Clause> = None;
+        let mut confl: Option<ClauseRef> = None;
+
+        while confl.is_none() && self.qhead < self.trail.len() as i32 {
+            while confl.is_none() && self.bin_qhead < self.trail.len() as i32 {
+                let p: Lit = self.trail[self.bin_qhead as usize];
+                self.bin_qhead += 1;
+                confl = self.propagate_binary(p);
+            }
+            if confl.is_some() {
+                break;
+            }
 
-        while self.qhead < self.trail.len() as i32 {
             self.solver_stats.propagations += 1.0;
             self.simp_db_props -= 1.0;
 
             let p: Lit = self.trail[self.qhead as usize];
             self.qhead += 1;
-            let mut ws: Vec<Clause> = self.watches[p.x as usize].clone();
+            confl = self.propagate_long(p);
+        }
+
+        if confl.is_some() {
+            if self.decision_level() == 0 {
+                self.ok = false;
+            }
+            self.qhead = self.trail.len() as i32;
+            self.bin_qhead = self.trail.len() as i32;
+        }
+        confl
+    }
 
-            //log p
-            let mut i: usize = 0;
-            let mut j: usize = 0;
-            let end: usize = i + ws.len();
-            while i != end {
-                let mut c: Clause = ws[i].clone();
+    fn propagate_binary(&mut self, p: Lit) -> Option<ClauseRef> {
+        for i in 0..self.bin_watches[p.x as usize].len() {
+            let w: BinWatcher = self.bin_watches[p.x as usize][i];
+            let val: Lbool = self.value_by_lit(w.implied);
+            if val == L_FALSE {
+                return Some(w.cref);
+            } else if val != L_TRUE {
+                self.enqueue_at(
+                    &w.implied,
+                    Some(Reason::Binary(!p)),
+                    self.level[var(&p) as usize],
+                );
+            }
+        }
+        None
+    }
 
+    fn propagate_long(&mut self, p: Lit) -> Option<ClauseRef> {
+        let mut confl: Option<ClauseRef> = None;
+        let mut ws: Vec<Watcher> = std::mem::take(&mut self.watches[p.x as usize]);
+        let false_lit: Lit = !p;
+
+        let mut i: usize = 0;
+        let mut j: usize = 0;
+        let end: usize = ws.len();
+        'watches: while i != end {
+            let blocker: Lit = ws[i].blocker;
+            if self.value_by_lit(blocker) == L_TRUE {
+                ws[j] = ws[i];
+                j += 1;
                 i += 1;
-                let false_lit: Lit = !p;
+                continue;
+            }
+
+            let cr: ClauseRef = ws[i].cref;
+            i += 1;
+
+            if self.ca[cr].data[0] == false_lit {
+                self.ca[cr].data.swap(0, 1);
+            }
+            assert!(self.ca[cr].data[1] == false_lit);
+
+            let first: Lit = self.ca[cr].data[0];
+            let w: Watcher = Watcher {
+                cref: cr,
+                blocker: first,
+            };
+            if first != blocker && self.value_by_lit(first) == L_TRUE {
+                ws[j] = w;
+                j += 1;
+                continue;
+            }
 
-                if c.data[0] == false_lit {
-                    c.data[0] = c.data[1];
-                    c.data[1] = false_lit;
+            for k in 2..self.ca[cr].data.len() {
+                let lit: Lit = self.ca[cr].data[k];
+                if self.value_by_lit(lit) != L_FALSE {
+                    self.ca[cr].data[1] = lit;
+                    self.ca[cr].data[k] = false_lit;
+                    self.watches[(!lit).x as usize].push(w);
+                    continue 'watches;
                 }
+            }
 
-                assert!(c.data[1] == false_lit);
+            if self.value_by_lit(first) == L_FALSE {
+                ws[j] = w;
+                j += 1;
+                confl = Some(cr);
 
-                let first: Lit = c.data[0];
-                let val: Lbool = self.value_by_lit(first);
-                if val == L_TRUE {
-                    ws[j] = c.clone();
+                while i < end {
+                    ws[j] = ws[i];
                     j += 1;
-                } else {
-                    let mut foundwatch: bool = false;
-                    for k in 2..c.data.len() {
-                        if self.value_by_lit(c.data[k]) != L_FALSE {
-                            c.data[1] = c.data[k];
-                            c.data[k] = false_lit;
-                            self.watches[(!c.data[1]).x as usize].push(c.clone());
-                            let other_ws = &mut self.watches[(!first).x as usize];
-                            for entry in other_ws.iter_mut() {
-                                if entry.id == c.id {
-                                    *entry = c.clone();
-                                    break;
-                                }
-                            }
-                            foundwatch = true;
-                            break;
-                        }
-                    }
-                    if !foundwatch {
-                        ws[j] = c.clone();
-                        j += 1;
-                        if !self.enqueue(&first, Some(c.clone())) {
-                            if self.decision_level() == 0 {
-                                self.ok = false;
-                            }
-                            confl = Some(c.clone());
-                            self.qhead = self.trail.len() as i32;
-
-                            while i < end {
-                                ws[j] = ws[i].clone();
-                                j += 1;
-                                i += 1;
-                            }
-                        }
+                    i += 1;
+                }
+                break;
+            }
+
+            let mut lv: i32 = self.level[var(&p) as usize];
+            let mut max_k: usize = 1;
+            if lv != self.decision_level() {
+                for k in 2..self.ca[cr].data.len() {
+                    let l: i32 = self.level[var(&self.ca[cr].data[k]) as usize];
+                    if l > lv {
+                        lv = l;
+                        max_k = k;
                     }
                 }
             }
-            ws.truncate(ws.len() - (i - j));
-            self.watches[p.x as usize] = ws;
+            if max_k != 1 {
+                self.ca[cr].data.swap(1, max_k);
+                let lit: Lit = self.ca[cr].data[1];
+                self.watches[(!lit).x as usize].push(w);
+            } else {
+                ws[j] = w;
+                j += 1;
+            }
+            self.enqueue_at(&first, Some(Reason::Clause(cr)), lv);
         }
+        ws.truncate(j);
+        self.watches[p.x as usize] = ws;
         confl
     }
 }
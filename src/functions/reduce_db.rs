@@ -1,4 +1,8 @@
 use crate::functions::new_clause::*;
+use crate::functions::garbage_collect::*;
+use crate::functions::search::*;
+use crate::models::arena::*;
+use crate::models::events::*;
 use crate::models::clause::*;
 use crate::models::solverstate::*;
 use std::cmp::Ordering;
@@ -17,42 +21,79 @@ pub trait Reduce {
 
 impl Reduce for SolverState {
     fn reduce_db(&mut self) {
-        trace!("{}|{}|{}", "reduce_db".to_string(), file!(), line!());
+        trace!("{}|{}|{}", "reduce_db", file!(), line!());
 
-        let mut i: i32 = 0;
-        let mut j: i32 = 0;
+        let mut i: usize = 0;
+        let mut j: usize = 0;
 
         let extra_lim: f64 = self.cla_inc / self.learnts.len() as f64;
 
+        let ca: &ClauseArena = &self.ca;
         self.learnts.sort_by(|x, y| {
-            if x.size() > 2 && (y.size() == 2 || x.activity < y.activity) {
-                Ordering::Less
-            } else {
-                Ordering::Greater
-            }
+            let (x, y) = (&ca[*x], &ca[*y]);
+            (x.size() == 2)
+                .cmp(&(y.size() == 2))
+                .then(x.activity.partial_cmp(&y.activity).unwrap_or(Ordering::Equal))
         });
 
-        for y in 0..self.learnts.len() / 2 {
-            if self.learnts[y].data.len() > 2 && !self.locked(&self.learnts[y].clone()) {
-                self.remove(self.learnts[y].clone(), false);
+        while i < self.learnts.len() / 2 {
+            let cr: ClauseRef = self.learnts[i];
+            if self.ca[cr].size() > 2 && !self.locked(cr) {
+                self.remove(cr, false);
             } else {
-                self.learnts[j as usize] = self.learnts[y].clone();
+                self.learnts[j] = cr;
                 j += 1;
             }
-            i = y as i32;
+            i += 1;
         }
 
-        for y in i..self.learnts.len() as i32 {
-            if self.learnts[y as usize].data.len() > 2
-                && !self.locked(&self.learnts[y as usize].clone())
-                && self.learnts[y as usize].activity < extra_lim
-            {
-                self.remove(self.learnts[y as usize].clone(), false);
+        while i < self.learnts.len() {
+            let cr: ClauseRef = self.learnts[i];
+            if self.ca[cr].size() > 2 && !self.locked(cr) && self.ca[cr].activity < extra_lim {
+                self.remove(cr, false);
             } else {
-                self.learnts[j as usize] = self.learnts[i as usize].clone();
+                self.learnts[j] = cr;
                 j += 1;
             }
+            i += 1;
+        }
+        self.emit(SearchEvent::Reduce {
+            kept: j as u32,
+            deleted: (self.learnts.len() - j) as u32,
+        });
+        self.solver_stats.reductions += 1;
+        self.solver_stats.deleted_clauses += (self.learnts.len() - j) as u64;
+        self.learnts.truncate(j);
+        self.check_garbage();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::functions::new_clause::*;
+    use crate::functions::reduce_db::*;
+    use crate::models::lit::*;
+
+    #[test]
+    fn binary_learnts_survive_every_reduction() {
+        let mut state = SolverState::new();
+        for _ in 0..10 {
+            state.new_var();
+        }
+        for v in 1..=5 {
+            state.new_root_learnt(&mut lits(&[v, v + 5]));
         }
-        self.learnts.truncate(self.learnts.len() - (i - j) as usize)
+        for v in 1..=4 {
+            state.new_root_learnt(&mut lits(&[v, v + 1, v + 2]));
+        }
+
+        state.reduce_db();
+        assert_eq!(state.learnts.len(), 5);
+        assert!(state.learnts.iter().all(|cr| state.ca[*cr].size() == 2));
+
+        // Keeping more clauses than the first half scanned must not underflow.
+        state.reduce_db();
+        assert_eq!(state.learnts.len(), 5);
+        assert_eq!(state.solver_stats.deleted_clauses, 4);
     }
 }
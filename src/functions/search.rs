@@ -2,14 +2,19 @@ use crate::functions::analyse::*;
 use crate::functions::analyse_final::*;
 use crate::functions::dpll::*;
 use crate::functions::new_clause::*;
-use crate::functions::propagate::*;
 use crate::functions::reduce_db::*;
+use crate::functions::share::*;
 use crate::functions::simplify_db::*;
 use crate::functions::solve::*;
+use crate::functions::vivify::*;
+use crate::models::arena::*;
+use crate::models::events::*;
 use crate::models::lbool::*;
 use crate::models::lit::*;
+use crate::models::progress::*;
 use crate::models::solverstate::*;
 use crate::models::varorder::*;
+use crate::parsers::icnf::*;
 use std::cmp::max;
 
 /*_________________________________________________________________________________________________
@@ -21,22 +26,35 @@ use std::cmp::max;
 |    below the provided limit. NOTE! Use negative value for 'nof_conflicts' or 'nof_learnts' to
 |    indicate infinity.
 |
+|    After a conflict the solver backjumps to the level of the learnt clause, unless that skips
+|    more than 'chrono_backtrack' levels. Then it only undoes the conflict level (chronological
+|    backtracking, Nadel & Ryvchin) and the asserting literal is put on the trail at its lower
+|    level.
+|
+|    With a 'Theory' attached, propagation includes the theory and a full assignment is only a
+|    model once the final check of the theory ('model_found()') accepts it.
+|
+|    With an 'events' sink set, every decision, round of propagation, conflict, backjump and
+|    learnt clause is passed to it as a 'SearchEvent' ('emit()').
+|
 |  Output:
 |    'l_True' if a partial assigment that is consistent with respect to the clauseset is found. If
 |    all variables are decision variables, this means that the clause set is satisfiable. 'l_False'
-|    if the clause set is unsatisfiable. 'l_Undef' if the bound on number of conflicts is reached.
+|    if the clause set is unsatisfiable. 'l_Undef' if the bound on number of conflicts is reached
+|    or 'interrupt' is raised.
 |________________________________________________________________________________________________@*/
 pub trait Search {
     fn search(&mut self, nof_conflicts: i32, nof_learnts: i32, parms: SearchParams) -> Lbool;
     fn var_rescale_activity(&mut self);
     fn cla_rescale_activity(&mut self);
+    fn emit(&mut self, e: SearchEvent);
 }
 
 impl Search for SolverState {
     fn search(&mut self, nof_conflicts: i32, nof_learnts: i32, parms: SearchParams) -> Lbool {
         trace!(
             "{}|{}|{}|{}|{}",
-            "search".to_string(),
+            "search",
             file!(),
             line!(),
             nof_conflicts,
@@ -47,37 +65,98 @@ impl Search for SolverState {
             return Lbool::False;
         }
         assert!(self.root_level == self.decision_level());
+        if !self.import_shared() {
+            return L_FALSE;
+        }
 
         self.solver_stats.starts += 1.0;
         let mut conflict_c: i32 = 0;
         self.var_decay = 1.0 / parms.var_decay;
         self.cla_decay = 1.0 / parms.clause_decay;
         self.model.clear();
+        self.order.reset_target();
 
         loop {
-            match self.propagate() {
+            let before: usize = self.trail.len();
+            let confl: Option<ClauseRef> = self.theory_propagate();
+            if self.events.is_some() && self.trail.len() > before {
+                self.emit(SearchEvent::Propagation {
+                    level: self.decision_level() as u32,
+                    assigned: (self.trail.len() - before) as u32,
+                });
+            }
+            match confl {
                 Some(_c) => {
                     self.solver_stats.conflicts += 1.0;
                     conflict_c += 1;
+                    let conflicts: u64 = self.solver_stats.conflicts as u64;
+                    if self.progress_interval > 0 && conflicts.is_multiple_of(self.progress_interval)
+                    {
+                        self.progress_estimate = self.progress_estimate();
+                        self.report_progress(ProgressEvent::Conflicts);
+                    }
                     let mut learnt_clause: Vec<Lit> = Vec::new();
 
-                    if self.decision_level() == self.root_level {
-                        self.analyse_final(&_c, false);
+                    // with chronological backtracking the conflict can be below the current level
+                    let conflict_level: i32 = self.ca[_c]
+                        .data
+                        .iter()
+                        .map(|q| self.level[var(q) as usize])
+                        .max()
+                        .unwrap();
+                    self.emit(SearchEvent::Conflict {
+                        level: conflict_level as u32,
+                        clause: self.ca[_c].id,
+                    });
+                    self.cancel_until(conflict_level);
+
+                    if conflict_level == 0 {
+                        self.ok = false;
+                        self.conflict.clear();
+                        return L_FALSE;
+                    }
+                    if conflict_level <= self.root_level {
+                        self.analyse_final(Reason::Clause(_c), false);
                         return L_FALSE;
                     }
 
-                    let backtrack_level: i32 = self.analyze(Some(_c.clone()), &mut learnt_clause);
+                    let backtrack_level: i32 = self.analyze(Some(_c), &mut learnt_clause);
 
-                    self.cancel_until(max(backtrack_level, self.root_level));
-                    self.new_clause(&mut learnt_clause, true);
-                    if learnt_clause.len() == 1 {
-                        self.level[var(&learnt_clause[0]) as usize] = 0;
+                    let target: i32 = if self.chrono_backtrack >= 0
+                        && self.decision_level() - backtrack_level > self.chrono_backtrack
+                    {
+                        self.decision_level() - 1
+                    } else {
+                        backtrack_level
+                    };
+                    self.emit(SearchEvent::Backjump {
+                        from: self.decision_level() as u32,
+                        to: max(target, self.root_level) as u32,
+                    });
+                    self.cancel_until(max(target, self.root_level));
+                    if self.events.is_some() {
+                        self.emit(SearchEvent::Learnt {
+                            lits: learnt_clause.iter().map(dimacs_lit).collect(),
+                            lbd: self.lbd(&learnt_clause),
+                        });
                     }
+                    self.new_clause(&mut learnt_clause, true);
                     self.var_decay_activity();
                     self.cla_decay_activity();
                 }
                 None => {
-                    if nof_conflicts >= 0 && conflict_c >= nof_conflicts {
+                    if !self.ok {
+                        return L_FALSE;
+                    }
+                    // a unit theory lemma can undo assumption levels
+                    if self.decision_level() < self.root_level {
+                        if !self.assume_assumptions() {
+                            return L_FALSE;
+                        }
+                        continue;
+                    }
+
+                    if (nof_conflicts >= 0 && conflict_c >= nof_conflicts) || self.interrupted() {
                         self.progress_estimate = self.progress_estimate();
                         self.cancel_until(self.root_level);
                         return Lbool::Undef0;
@@ -88,6 +167,15 @@ impl Search for SolverState {
                         if !self.ok {
                             return L_FALSE;
                         }
+                        if self.vivify_parms.enabled
+                            && self.solver_stats.propagations >= self.next_vivify
+                        {
+                            self.next_vivify =
+                                self.solver_stats.propagations + self.vivify_parms.interval;
+                            if !self.vivify() {
+                                return L_FALSE;
+                            }
+                        }
                     }
 
                     if nof_learnts >= 0
@@ -96,17 +184,23 @@ impl Search for SolverState {
                         self.reduce_db();
                     }
 
+                    if self.order.polarity_mode == PolarityMode::Target {
+                        self.order.update_target(&self.trail);
+                    }
                     self.solver_stats.decisions += 1.0;
-                    let next: Lit = self.order.select(parms.random_var_freq);
+                    let next: Lit = self.order.select(
+                        parms.random_var_freq,
+                        &self.assigns.col,
+                        &self.activity.col,
+                    );
 
                     if next == Lit::undefined() {
                         if self.model_found() {
                             continue;
                         }
-                        self.model
-                            .resize(self.clone().n_vars() as usize, Lbool::Undef0);
+                        self.model.resize(self.n_vars() as usize, Lbool::Undef0);
 
-                        for y in 0..self.clone().n_vars() {
+                        for y in 0..self.n_vars() {
                             self.model[y as usize] = self.value_by_var(y);
                         }
                         self.cancel_until(self.root_level);
@@ -114,6 +208,14 @@ impl Search for SolverState {
                     }
 
                     assert!(self.assume(next));
+                    self.emit(SearchEvent::Decision {
+                        level: self.decision_level() as u32,
+                        lit: dimacs_lit(&next),
+                    });
+                    self.solver_stats.max_decision_level = self
+                        .solver_stats
+                        .max_decision_level
+                        .max(self.decision_level() as u64);
                 }
             }
         }
@@ -122,12 +224,12 @@ impl Search for SolverState {
     fn var_rescale_activity(&mut self) {
         trace!(
             "{}|{}|{}",
-            "var_rescale_activity".to_string(),
+            "var_rescale_activity",
             file!(),
             line!(),
         );
 
-        for y in 0..self.clone().n_vars() {
+        for y in 0..self.n_vars() {
             self.update_activity(self.activity.col[y as usize] * 1e-100, y as usize);
         }
         self.var_inc *= 1e-100;
@@ -136,14 +238,89 @@ impl Search for SolverState {
     fn cla_rescale_activity(&mut self) {
         trace!(
             "{}|{}|{}",
-            "cla_rescale_activity".to_string(),
+            "cla_rescale_activity",
             file!(),
             line!()
         );
 
         for y in 0..self.learnts.len() {
-            self.learnts[y].activity *= 1e-20;
+            self.ca[self.learnts[y]].activity *= 1e-20;
         }
         self.cla_inc *= 1e-20;
     }
+
+    fn emit(&mut self, e: SearchEvent) {
+        if let Some(sink) = self.events.as_mut() {
+            sink.event(&e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::functions::propagate::*;
+    use crate::functions::search::*;
+
+    // x1 and x4 imply x5 and x6, which can not both be true. The learnt clause (-x4 | -x1) is
+    // asserting at level 1 but only the conflict level 3 is undone.
+    #[test]
+    fn chronological_backtrack_keeps_lower_levels() {
+        let mut state = SolverState::new();
+        for _ in 0..7 {
+            state.new_var();
+        }
+        for c in [vec![-1, -4, 5], vec![-1, -4, 6], vec![-5, -6], vec![4, 7]] {
+            state.add_clause(&mut lits(&c));
+        }
+
+        for d in [1, 2, 4] {
+            state.assume(lits(&[d])[0]);
+        }
+        let confl = state.propagate();
+        assert!(confl.is_some());
+
+        let mut learnt: Vec<Lit> = Vec::new();
+        assert_eq!(state.analyze(confl, &mut learnt), 1);
+        state.cancel_until(2);
+        state.new_clause(&mut learnt, true);
+        assert!(state.propagate().is_none());
+
+        // -x4 and x7 belong to level 1 although they are on the trail of level 2
+        assert_eq!(state.decision_level(), 2);
+        assert_eq!(state.level[3], 1);
+        assert_eq!(state.level[6], 1);
+
+        state.cancel_until(1);
+        assert_eq!(state.trail, lits(&[1, -4, 7]));
+        assert!(is_undefined(state.value_by_lit(lits(&[2])[0])));
+    }
+
+    // x1 implies x3 and -x3. The learnt unit -x1 is added while level 1 is still kept, it must
+    // be a root fact that survives the backtrack to level 0.
+    #[test]
+    fn learnt_unit_is_enqueued_at_the_root() {
+        let mut state = SolverState::new();
+        for _ in 0..3 {
+            state.new_var();
+        }
+        for c in [vec![-1, 3], vec![-1, -3]] {
+            state.add_clause(&mut lits(&c));
+        }
+
+        for d in [2, 1] {
+            state.assume(lits(&[d])[0]);
+        }
+        let confl = state.propagate();
+        assert!(confl.is_some());
+
+        let mut learnt: Vec<Lit> = Vec::new();
+        assert_eq!(state.analyze(confl, &mut learnt), 0);
+        state.cancel_until(1);
+        state.new_clause(&mut learnt, true);
+        assert_eq!(state.level[0], 0);
+        assert!(state.reason[0].is_none());
+
+        state.cancel_until(0);
+        assert_eq!(state.trail, lits(&[-1]));
+    }
 }
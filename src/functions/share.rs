@@ -0,0 +1,174 @@
+use crate::functions::new_clause::*;
+use crate::functions::propagate::*;
+use crate::functions::solve::*;
+use crate::models::arena::*;
+use crate::models::clause_pool::*;
+use crate::models::lit::*;
+use crate::models::solverstate::*;
+
+/*_________________________________________________________________________________________________
+|
+|  export_learnt / import_shared
+|
+|  Description:
+|    Exchange of learnt clauses between the solvers of a portfolio through the 'ClausePool' of
+|    'sharing'. A learnt clause, 'ps[0]' being its asserting literal, is exported if it has at most
+|    'max_size' literals or if its literals belong to at most 'max_lbd' decision levels (LBD,
+|    Audemard & Simon). Learnt clauses are implied by the clauses of the solver and preprocessing
+|    only adds implied clauses, so the solvers must start from the same formula. Nothing is
+|    exported while a 'Theory' is attached, as learnt clauses can depend on its lemmas.
+|
+|    'search()' imports the clauses of the other solvers when it starts, that is at every restart.
+|    They are added at level 0 as learnt clauses, the assumptions are put back on the trail
+|    afterwards. Clauses that were seen before and clauses over variables that were eliminated
+|    are skipped.
+|
+|  Output:
+|    'import_shared()' returns FALSE if the clauses made the formula or the assumptions
+|    inconsistent, with 'ok' or 'conflict' set as by 'solve()'.
+|________________________________________________________________________________________________@*/
+pub trait Share {
+    fn export_learnt(&mut self, ps: &[Lit], lbd: u32);
+    fn import_shared(&mut self) -> bool;
+    fn shared_used(&mut self, r: Reason, p: Lit);
+}
+
+impl Share for SolverState {
+    fn export_learnt(&mut self, ps: &[Lit], lbd: u32) {
+        if self.theory.is_some() {
+            return;
+        }
+        let Some(sharing) = self.sharing.as_mut() else {
+            return;
+        };
+        if (ps.len() <= sharing.max_size || lbd <= sharing.max_lbd) && sharing.first_time(ps) {
+            trace!("{}|{}|{}|{:?}", "export_learnt", file!(), line!(), ps);
+            let c: SharedClause = SharedClause {
+                lits: ps.to_vec(),
+                lbd,
+                worker: sharing.worker,
+            };
+            if sharing.pool.push(c) {
+                self.solver_stats.shared_exported += 1.0;
+            }
+        }
+    }
+
+    fn import_shared(&mut self) -> bool {
+        let n_vars: i32 = self.n_vars();
+        let Some(sharing) = self.sharing.as_mut() else {
+            return true;
+        };
+        let eliminated: &Vec<bool> = &self.eliminated;
+        let worker: usize = sharing.worker;
+        let mut cursor: usize = sharing.cursor;
+        let clauses: Vec<SharedClause> = sharing
+            .pool
+            .read(&mut cursor)
+            .into_iter()
+            .filter(|c| {
+                c.worker != worker
+                    && c.lits
+                        .iter()
+                        .all(|p| var(p) < n_vars && !eliminated[var(p) as usize])
+            })
+            .filter(|c| sharing.first_time(&c.lits))
+            .collect();
+        sharing.cursor = cursor;
+        if clauses.is_empty() {
+            return true;
+        }
+
+        trace!(
+            "{}|{}|{}|{}",
+            "import_shared",
+            file!(),
+            line!(),
+            clauses.len()
+        );
+        self.cancel_until(0);
+        for c in clauses {
+            let n_learnts: usize = self.learnts.len();
+            self.new_root_learnt(&mut c.lits.clone());
+            self.solver_stats.shared_imported += 1.0;
+            if self.learnts.len() > n_learnts {
+                let cr: ClauseRef = self.learnts[n_learnts];
+                self.ca[cr].imported = true;
+                if let [p, q] = self.ca[cr].data[..] {
+                    let sharing = self.sharing.as_mut().unwrap();
+                    sharing.unused_binaries.insert((p.x.min(q.x), p.x.max(q.x)));
+                }
+            }
+            if !self.ok {
+                return false;
+            }
+        }
+        if self.propagate().is_some() {
+            self.ok = false;
+            return false;
+        }
+        self.assume_assumptions()
+    }
+
+    // Counts an imported clause the first time it is the reason 'r' of 'p' in conflict analysis.
+    fn shared_used(&mut self, r: Reason, p: Lit) {
+        match r {
+            Reason::Clause(cr) => {
+                if self.ca[cr].imported {
+                    self.ca[cr].imported = false;
+                    self.solver_stats.shared_used += 1.0;
+                }
+            }
+            Reason::Binary(q) => {
+                let sharing = self.sharing.as_mut().unwrap();
+                if sharing
+                    .unused_binaries
+                    .remove(&(p.x.min(q.x), p.x.max(q.x)))
+                {
+                    self.solver_stats.shared_used += 1.0;
+                }
+            }
+            Reason::Theory => (),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::functions::share::*;
+    use crate::functions::test_support::*;
+    use std::sync::Arc;
+
+    #[test]
+    fn clauses_of_one_solver_reach_another() {
+        let pool: Arc<ClausePool> = Arc::new(ClausePool::new());
+        let mut a = pigeons(7, 6);
+        a.sharing = Some(ClauseSharing::new(Arc::clone(&pool), 0));
+        a.solve_no_assumptions();
+        assert!(!a.ok);
+        let exported: f64 = a.solver_stats.shared_exported;
+        assert!(exported > 0.0);
+        assert_eq!(pool.len() as f64, exported);
+
+        // with all of them another solver has nothing left to do
+        let mut b = pigeons(7, 6);
+        b.sharing = Some(ClauseSharing::new(Arc::clone(&pool), 1));
+        b.solve_no_assumptions();
+        assert!(!b.ok);
+        assert_eq!(b.solver_stats.shared_imported, exported);
+        assert_eq!(b.solver_stats.conflicts, 0.0);
+
+        // a few of them still save the search some work, under assumptions as well
+        let some: Arc<ClausePool> = Arc::new(ClausePool::new());
+        for c in pool.read(&mut 0).into_iter().take(40) {
+            some.push(c);
+        }
+        let mut b = pigeons(7, 6);
+        b.new_var();
+        b.sharing = Some(ClauseSharing::new(some, 1));
+        b.solve(vec![Lit::new(42, true)]);
+        assert!(!b.ok);
+        assert_eq!(b.solver_stats.shared_imported, 40.0);
+        assert!(b.solver_stats.shared_used > 0.0);
+    }
+}
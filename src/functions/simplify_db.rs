@@ -1,6 +1,8 @@
 use crate::functions::new_clause::*;
 use crate::functions::propagate::*;
-use crate::models::clause::*;
+use crate::functions::garbage_collect::*;
+use crate::functions::probe::*;
+use crate::models::arena::*;
 use crate::models::lit::*;
 use crate::models::solverstate::*;
 
@@ -9,8 +11,9 @@ use crate::models::solverstate::*;
 |  simplifyDB
 |
 |  Description:
-|    Simplify the clause database according to the current top-level assigment. Currently, the only
-|    thing done here is the removal of satisfied clauses, but more things can be put here.
+|    Simplify the clause database according to the current top-level assigment. Satisfied clauses
+|    are removed, and every 'probe_parms.interval' propagations a round of probing and equivalent
+|    literal substitution is run first (see 'probe()').
 |________________________________________________________________________________________________@*/
 pub trait Simplify {
     fn simplify_db(&mut self);
@@ -18,74 +21,81 @@ pub trait Simplify {
 
 impl Simplify for SolverState {
     fn simplify_db(&mut self) {
-        trace!("{}|{}|{}", "simplify_db".to_string(), file!(), line!());
+        trace!("{}|{}|{}", "simplify_db", file!(), line!());
         if !self.ok {
             return;
         }
 
         assert!(self.decision_level() == 0);
+        self.solver_stats.simplify_calls += 1;
         match self.propagate() {
             None => {
                 trace!(
                     "{}|{}|{}",
-                    "propagate match none".to_string(),
+                    "propagate match none",
                     file!(),
                     line!()
                 );
-                if self.clone().n_assigns() == self.simp_db_assigns as usize
+                if self.probe_parms.enabled
+                    && self.solver_stats.propagations >= self.next_probe
+                {
+                    self.next_probe =
+                        self.solver_stats.propagations + self.probe_parms.interval;
+                    if !self.probe() {
+                        return;
+                    }
+                }
+
+                if self.n_assigns() == self.simp_db_assigns as usize
                     || self.simp_db_props > 0.0
                 {
                     return;
                 }
 
-                for y in self.simp_db_assigns..self.clone().n_assigns() as i32 {
+                for y in self.simp_db_assigns..self.n_assigns() as i32 {
                     let _p: Lit = self.trail[y as usize];
                     self.watches[_p.x as usize].clear();
                     self.watches[(!_p).x as usize].clear();
+                    self.bin_watches[_p.x as usize].clear();
+                    self.bin_watches[(!_p).x as usize].clear();
                 }
 
                 for t in 0..2 {
-                    let clause_size: usize = if t != 0 {
-                        self.learnts.len()
-                    } else {
-                        self.clauses.len()
-                    };
-
-                    let mut cs: Vec<Clause> = if t != 0 {
-                        self.learnts.clone()
+                    let mut cs: Vec<ClauseRef> = if t != 0 {
+                        std::mem::take(&mut self.learnts)
                     } else {
-                        self.clauses.clone()
+                        std::mem::take(&mut self.clauses)
                     };
 
                     let mut j: i32 = 0;
-                    for k in 0..clause_size {
-                        let a = !self.locked(&cs[k]);
-                        let b = self.simplify(k as i32, t);
+                    for k in 0..cs.len() {
+                        let a = !self.locked(cs[k]);
+                        let b = self.simplify(cs[k]);
 
                         if a && b {
-                            self.remove(cs[k].clone(), false);
+                            self.remove(cs[k], false);
                         } else {
-                            cs[j as usize] = cs[k].clone();
+                            cs[j as usize] = cs[k];
                             j += 1;
                         }
                     }
+                    cs.truncate(j as usize);
                     if t != 0 {
-                        self.learnts
-                            .truncate(self.learnts.len() - (clause_size - j as usize))
+                        self.learnts = cs;
                     } else {
-                        self.clauses
-                            .truncate(self.clauses.len() - (clause_size - j as usize))
+                        self.clauses = cs;
                     }
                 }
 
-                self.simp_db_assigns = self.clone().n_assigns() as i32;
+                self.check_garbage();
+                self.simp_db_assigns = self.n_assigns() as i32;
                 self.simp_db_props =
                     self.solver_stats.clauses_literals + self.solver_stats.learnts_literals;
             }
             _ => {
                 trace!(
                     "{}|{}|{}",
-                    "solver state false".to_string(),
+                    "solver state false",
                     file!(),
                     line!(),
                 );
@@ -1,11 +1,19 @@
 use crate::functions::analyse_final::*;
+use crate::functions::checkpoint::*;
+use crate::functions::dpll::*;
 use crate::functions::new_clause::*;
+use crate::functions::preprocess::*;
 use crate::functions::propagate::*;
 use crate::functions::search::*;
 use crate::functions::simplify_db::*;
+use crate::models::arena::*;
+use crate::models::events::*;
 use crate::models::lbool::*;
 use crate::models::lit::*;
+use crate::models::progress::*;
 use crate::models::solverstate::*;
+use std::sync::atomic::Ordering;
+use std::time::Instant;
 
 /*_________________________________________________________________________________________________
 |
@@ -16,47 +24,145 @@ use crate::models::solverstate::*;
 |    'simplifyDB()' first to see that no top-level conflict is present (which would put the solver
 |    in an undefined state).
 |
+|    The calls to 'search()' are limited to the number of conflicts of 'restart_policy'. Once
+|    'interrupt' is raised the solver stops at the next restart or decision, without a model or a
+|    conflict. With a 'checkpoint_path' the state is written there every 'checkpoint_interval'
+|    restarts, a state restored from it continues from that restart. The 'progress' listeners get
+|    a snapshot of the counters at every restart and every 'progress_interval' conflicts.
+|
 |  Input:
 |    A list of assumptions (unit clauses coded as literals). Pre-condition: The assumptions must
-|    not contain both 'x' and '~x' for any variable 'x'.
+|    not contain both 'x' and '~x' for any variable 'x', nor variables that preprocessing or
+|    probing eliminated in an earlier call (freeze them with 'set_frozen' before).
+|
+|  Output:
+|    'solve_limited()' returns TRUE with a 'model' if the clauses are satisfiable under the
+|    assumptions, FALSE if not ('conflict' then holds the failed assumptions, it is empty when the
+|    clauses alone are unsatisfiable) and UNDEF if it was interrupted. 'solve()' returns TRUE only
+|    in the first case, so it can not tell an interrupted call from an unsatisfiable one.
 |________________________________________________________________________________________________@*/
 
 pub trait Solver {
     fn solve(&mut self, assumptions: Vec<Lit>) -> bool;
+    fn solve_limited(&mut self, assumptions: Vec<Lit>) -> Lbool;
     fn solve_no_assumptions(&mut self) -> bool;
+    fn assume_assumptions(&mut self) -> bool;
+    fn interrupted(&self) -> bool;
     fn progress_estimate(&mut self) -> f64;
+    fn snapshot(&self, event: ProgressEvent) -> ProgressSnapshot;
+    fn report_progress(&mut self, event: ProgressEvent);
+    fn finish_progress(&mut self);
 }
 
 impl Solver for SolverState {
     fn solve(&mut self, assumptions: Vec<Lit>) -> bool {
+        self.solve_limited(assumptions) == L_TRUE
+    }
+
+    fn solve_limited(&mut self, assumptions: Vec<Lit>) -> Lbool {
         trace!(
             "{}|{}|{}|{:?}",
-            "solve".to_string(),
+            "solve",
             file!(),
             line!(),
             assumptions
         );
+        assert!(
+            assumptions.iter().all(|p| !self.eliminated[var(p) as usize]),
+            "assumption on an eliminated variable, freeze it before solving"
+        );
 
+        self.solve_started = Instant::now();
+        self.model.clear();
+        self.conflict.clear();
+        self.assumptions.clone_from(&assumptions);
         self.simplify_db();
         if !self.ok {
-            return false;
+            return L_FALSE;
         };
+        // a restored state was preprocessed before its checkpoint
+        let resumed: bool = std::mem::take(&mut self.resumed);
+        if self.preprocess_parms.enabled && !resumed && !self.preprocess(&assumptions) {
+            return L_FALSE;
+        }
 
         let parms: SearchParams = self.default_parms;
 
-        let mut nof_conflicts: f64 = 100.0;
-        let mut nof_learnts: f64 = self.clauses.len() as f64 / 3.0;
+        if !resumed {
+            self.restarts = 0;
+            self.nof_learnts = self.clauses.len() as f64 / 3.0;
+        }
         let mut status: Lbool = Lbool::Undef0;
 
         self.root_level = assumptions.len() as i32;
-        for assume in &assumptions {
-            let p: Lit = *assume;
+        if !self.assume_assumptions() {
+            return L_FALSE;
+        }
+        assert!(self.root_level == self.decision_level());
+
+        for listener in self.progress.iter_mut() {
+            listener.started();
+        }
+
+        while is_undefined(status) {
+            if self.interrupted() {
+                info!("interrupted");
+                self.cancel_until(0);
+                self.finish_progress();
+                return Lbool::Undef0;
+            }
+            if self.restarts > 0
+                && self.restarts.is_multiple_of(self.checkpoint_interval)
+                && let Some(path) = self.checkpoint_path.clone()
+                && let Err(msg) = self.write_checkpoint(&path)
+            {
+                info!("checkpoint failed: {}", msg);
+            }
+            self.report_progress(ProgressEvent::Restart);
+
+            let nof_conflicts: f64 = self.restart_policy.conflicts(self.restarts);
+            status = self.search(nof_conflicts as i32, self.nof_learnts as i32, parms);
+            self.restarts += 1;
+            self.nof_learnts *= 1.1;
+            if is_undefined(status) {
+                self.solver_stats.restarts += 1;
+                self.emit(SearchEvent::Restart {
+                    conflicts: self.solver_stats.conflicts as u64,
+                });
+            }
+        }
+
+        self.finish_progress();
+        if status == L_TRUE {
+            self.extend_model();
+        }
+        self.cancel_until(0);
+        status
+    }
+
+    fn solve_no_assumptions(&mut self) -> bool {
+        trace!(
+            "{}|{}|{}",
+            "solve_no_assumptions",
+            file!(),
+            line!(),
+        );
+        let assumptions: Vec<Lit> = Vec::new();
+        self.solve(assumptions)
+    }
+
+    // Puts the assumptions of the current call on the trail, from the current decision level up to
+    // 'root_level'. Returns FALSE, with the failed assumptions in 'conflict', if they are
+    // inconsistent.
+    fn assume_assumptions(&mut self) -> bool {
+        while self.decision_level() < self.root_level {
+            let p: Lit = self.assumptions[self.decision_level() as usize];
             assert!(var(&p) < self.n_vars());
 
             if !self.assume(p) {
-                match &self.reason[var(&p) as usize] {
+                match self.reason_of(var(&p) as usize) {
                     Some(r) => {
-                        self.clone().analyse_final(&(r.clone()), true);
+                        self.analyse_final(r, true);
                         self.conflict.push(!p);
                     }
                     None => {
@@ -69,68 +175,76 @@ impl Solver for SolverState {
                 return false;
             }
             if let Some(confl) = self.propagate() {
-                self.analyse_final(&confl, false);
-                assert!(!self.conflict.is_empty());
+                self.analyse_final(Reason::Clause(confl), false);
+                // the conflict can be between top-level facts found during the search
+                if self.conflict.is_empty() {
+                    self.ok = false;
+                }
                 self.cancel_until(0);
                 return false;
             }
         }
-        assert!(self.root_level == self.decision_level());
+        true
+    }
 
-        info!("==================================[MINISAT]=======================================");
-        info!(
-            "| Conflicts |       ORIGINAL        |              LEARNT              | Progress |"
-        );
-        info!(
-            "|           | Clauses      Literals |   Limit Clauses Literals  Lit/Cl |          |"
-        );
-        info!("==================================================================================");
+    fn interrupted(&self) -> bool {
+        self.interrupt
+            .as_ref()
+            .is_some_and(|flag| flag.load(Ordering::Relaxed))
+    }
 
-        while is_undefined(status) {
-            info!(
-                    "|      {0}    |     {1}        {2}    |   {3}      {4}       {5}       {6}   |   {7} %   |",
-                    self.solver_stats.conflicts,
-                    self.clone().n_clauses(),
-                    self.solver_stats.clauses_literals,
-            nof_learnts.floor(),
-                    self.clone().n_learnts(),
-                    self.solver_stats.learnts_literals,
-                    (self.solver_stats.learnts_literals
-                        / self.clone().n_learnts() as f64).floor(),
-                        self.progress_estimate * 100.0
-            );
-
-            status = self.search(nof_conflicts as i32, nof_learnts as i32, parms);
-            nof_conflicts *= 1.5;
-            nof_learnts *= 1.1;
+    // The share of the search space that is done: the assignments of level 'i' count with weight
+    // F^i, F being one over the number of variables.
+    fn progress_estimate(&mut self) -> f64 {
+        if self.n_vars() == 0 {
+            return 0.0;
+        }
+        let f: f64 = 1.0 / self.n_vars() as f64;
+        let mut progress: f64 = 0.0;
+
+        for i in 0..=self.decision_level() {
+            let beg: usize = if i == 0 { 0 } else { self.trail_lim[i as usize - 1] as usize };
+            let end: usize = if i == self.decision_level() {
+                self.trail.len()
+            } else {
+                self.trail_lim[i as usize] as usize
+            };
+            progress += f.powi(i) * (end - beg) as f64;
         }
 
-        info!("==================================================================================");
-        self.cancel_until(0);
-        true
+        progress / self.n_vars() as f64
     }
 
-    fn solve_no_assumptions(&mut self) -> bool {
-        trace!(
-            "{}|{}|{}",
-            "solve_no_assumptions".to_string(),
-            file!(),
-            line!(),
-        );
-        let assumptions: Vec<Lit> = Vec::new();
-        self.solve(assumptions)
+    fn snapshot(&self, event: ProgressEvent) -> ProgressSnapshot {
+        ProgressSnapshot {
+            event,
+            conflicts: self.solver_stats.conflicts as u64,
+            decisions: self.solver_stats.decisions as u64,
+            propagations: self.solver_stats.propagations as u64,
+            clauses: self.n_clauses(),
+            clauses_literals: self.solver_stats.clauses_literals as u64,
+            learnts: self.n_learnts(),
+            learnts_literals: self.solver_stats.learnts_literals as u64,
+            learnt_limit: self.nof_learnts as u64,
+            progress: self.progress_estimate,
+            elapsed: self.solve_started.elapsed(),
+        }
     }
 
-    fn progress_estimate(&mut self) -> f64 {
-        let mut progress = 0.0;
-        let f = 1.0 / self.n_vars() as f64;
-
-        for i in 0..self.n_vars() {
-            if is_undefined(self.value_by_var(i)) {
-                progress += f.powf(self.level[i as usize] as f64);
-            }
+    fn report_progress(&mut self, event: ProgressEvent) {
+        let s: ProgressSnapshot = self.snapshot(event);
+        for listener in self.progress.iter_mut() {
+            listener.progress(&s);
         }
+    }
 
-        progress / self.n_vars() as f64
+    fn finish_progress(&mut self) {
+        let s: ProgressSnapshot = self.snapshot(ProgressEvent::Finish);
+        for listener in self.progress.iter_mut() {
+            listener.finished(&s);
+        }
+        if let Some(sink) = self.events.as_mut() {
+            sink.flush();
+        }
     }
 }
@@ -0,0 +1,37 @@
+use crate::functions::new_clause::*;
+use crate::models::lit::*;
+use crate::models::solverstate::*;
+
+// Problems shared by the unit tests of the functions.
+
+// 'm' pigeons in 'n' holes, unsatisfiable when 'm > n'
+pub fn pigeons(m: i32, n: i32) -> SolverState {
+    let mut state = SolverState::new();
+    let x = |p: i32, h: i32| Lit::new(p * n + h, false);
+    for _ in 0..m * n {
+        state.new_var();
+    }
+    for p in 0..m {
+        state.add_clause(&mut (0..n).map(|h| x(p, h)).collect());
+    }
+    for h in 0..n {
+        for p in 0..m {
+            for q in p + 1..m {
+                state.add_clause(&mut vec![!x(p, h), !x(q, h)]);
+            }
+        }
+    }
+    state
+}
+
+// 'n' variables and the clauses of 'problem' in DIMACS numbering
+pub fn state_of(n: i32, problem: &[Vec<i32>]) -> SolverState {
+    let mut state = SolverState::new();
+    for _ in 0..n {
+        state.new_var();
+    }
+    for c in problem {
+        state.add_clause(&mut lits(c));
+    }
+    state
+}
@@ -0,0 +1,155 @@
+use crate::functions::garbage_collect::*;
+use crate::functions::new_clause::*;
+use crate::functions::propagate::*;
+use crate::models::arena::*;
+use crate::models::clause::*;
+use crate::models::lbool::*;
+use crate::models::lit::*;
+use crate::models::solverstate::*;
+use std::cmp::Ordering;
+
+/*_________________________________________________________________________________________________
+|
+|  vivify
+|
+|  Description:
+|    Tries to shorten the most active learnt clauses, called from 'search()' at level 0 once
+|    'vivify_parms.interval' propagations have been done since the last round. For a clause
+|    (l1 | ... | ln) the negations ~l1, ~l2, ... are assumed one by one:
+|      * a literal that is already false is implied false by the previous ones and is dropped,
+|      * a literal that is already true ends the clause, (l1 | ... | li) is implied,
+|      * a conflict after assuming ~li also shows that (l1 | ... | li) is implied.
+|    The shortened clause replaces the original one. Every clause is vivified at most once and the
+|    round stops once 'vivify_parms.budget' propagations have been used.
+|
+|  Output:
+|    FALSE if the formula was found to be unsatisfiable, TRUE otherwise.
+|________________________________________________________________________________________________@*/
+pub trait Vivify {
+    fn vivify(&mut self) -> bool;
+    fn vivify_clause(&mut self, cr: ClauseRef) -> Option<Vec<Lit>>;
+}
+
+impl Vivify for SolverState {
+    fn vivify(&mut self) -> bool {
+        trace!("{}|{}|{}", "vivify", file!(), line!());
+        assert!(self.decision_level() == 0);
+
+        let limit: f64 = self.solver_stats.propagations + self.vivify_parms.budget;
+        let ca: &ClauseArena = &self.ca;
+        let mut candidates: Vec<ClauseRef> = self
+            .learnts
+            .iter()
+            .copied()
+            .filter(|cr| ca[*cr].size() > 2 && !ca[*cr].vivified)
+            .collect();
+        candidates.sort_by(|x, y| {
+            ca[*y]
+                .activity
+                .partial_cmp(&ca[*x].activity)
+                .unwrap_or(Ordering::Equal)
+        });
+
+        for cr in candidates {
+            if !self.ok || self.solver_stats.propagations >= limit {
+                break;
+            }
+            if self.ca[cr]
+                .data
+                .iter()
+                .any(|p| self.value_by_lit(*p) == L_TRUE)
+            {
+                continue;
+            }
+
+            self.ca[cr].vivified = true;
+            self.solver_stats.vivified_clauses += 1.0;
+            if let Some(mut ps) = self.vivify_clause(cr) {
+                trace!("{}|{}|{}|{:?}", "vivified", file!(), line!(), ps);
+                let (activity, lbd): (f64, u32) = (self.ca[cr].activity, self.ca[cr].lbd);
+                self.solver_stats.vivify_strengthened += 1.0;
+                self.solver_stats.vivify_literals +=
+                    (self.ca[cr].size() as usize - ps.len()) as f64;
+                self.remove(cr, false);
+
+                let before: usize = self.learnts.len();
+                self.new_root_learnt(&mut ps);
+                if self.learnts.len() > before {
+                    let new: ClauseRef = self.learnts[before];
+                    self.ca[new].activity = activity;
+                    self.ca[new].lbd = lbd.min(ps.len() as u32);
+                }
+                if self.ok && self.propagate().is_some() {
+                    self.ok = false;
+                }
+            }
+        }
+
+        let ca: &ClauseArena = &self.ca;
+        self.learnts.retain(|cr| !ca[*cr].deleted);
+        self.check_garbage();
+        self.ok
+    }
+
+    // Returns the shortened clause, or None if no literal could be removed.
+    fn vivify_clause(&mut self, cr: ClauseRef) -> Option<Vec<Lit>> {
+        // propagation moves the watched literals around, so work on a copy
+        let c: Vec<Lit> = self.ca[cr].data.clone();
+        let mut kept: Vec<Lit> = Vec::with_capacity(c.len());
+
+        for &l in &c {
+            let val: Lbool = self.value_by_lit(l);
+            if val == L_TRUE {
+                kept.push(l);
+                break;
+            } else if val != L_FALSE {
+                kept.push(l);
+                self.assume(!l);
+                if self.propagate().is_some() {
+                    break;
+                }
+            }
+        }
+        self.cancel_until(0);
+
+        if kept.len() < c.len() {
+            Some(kept)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::functions::vivify::*;
+
+    #[test]
+    fn learnt_clauses_are_shortened() {
+        let mut state = SolverState::new();
+        for _ in 0..5 {
+            state.new_var();
+        }
+        // -x1 implies -x2 and x3
+        state.add_clause(&mut lits(&[1, -2]));
+        state.add_clause(&mut lits(&[1, 3]));
+        state.new_root_learnt(&mut lits(&[1, 2, 4, 5]));
+        state.new_root_learnt(&mut lits(&[1, 3, 4, 5]));
+
+        assert!(state.vivify());
+        assert_eq!(state.solver_stats.vivified_clauses, 2.0);
+        assert_eq!(state.solver_stats.vivify_strengthened, 2.0);
+        assert_eq!(state.solver_stats.vivify_literals, 3.0);
+
+        let shortened: Vec<Vec<Lit>> = state
+            .learnts
+            .iter()
+            .map(|cr| {
+                let mut c: Vec<Lit> = state.ca[*cr].data.clone();
+                c.sort_by_key(|p| p.x);
+                c
+            })
+            .collect();
+        assert_eq!(shortened, vec![lits(&[1, 4, 5]), lits(&[1, 3])]);
+    }
+}
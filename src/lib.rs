@@ -0,0 +1,7 @@
+pub mod functions;
+pub mod models;
+pub mod parsers;
+pub mod theories;
+
+#[macro_use]
+extern crate log;
@@ -1,26 +1,142 @@
-mod functions;
-mod models;
-use crate::functions::new_clause::*;
-use crate::functions::solve::*;
-use crate::models::lit::*;
-use crate::models::solverstate::*;
+use sat_rs::functions::bmc::*;
+use sat_rs::functions::checkpoint::*;
+use sat_rs::functions::cube::*;
+use sat_rs::functions::hints::*;
+use sat_rs::functions::portfolio::*;
+use sat_rs::functions::solve::*;
+use sat_rs::models::events::*;
+use sat_rs::models::lit::*;
+use sat_rs::models::progress::*;
+use sat_rs::models::solverstate::*;
+use sat_rs::models::statsparams::*;
+use sat_rs::parsers::aiger::*;
+use sat_rs::parsers::dimacs::*;
+use sat_rs::parsers::icnf::*;
+use sat_rs::parsers::learnts::*;
+use sat_rs::parsers::smtlib::*;
 use simplelog::*;
-use std::cmp::Ordering;
 use std::env;
 use std::fs::File;
+use std::io::BufWriter;
 use std::io::prelude::*;
 
 #[macro_use]
 extern crate log;
 
 fn main() {
-    let _args: Vec<String> = env::args().collect();
+    let mut args: Vec<String> = env::args().collect();
+    // '--threads N' runs a portfolio of N differently configured solvers
+    let mut threads: usize = 1;
+    if let Some(i) = args.iter().position(|a| a == "--threads") {
+        threads = args[i + 1].parse().unwrap();
+        args.drain(i..i + 2);
+    }
+    // '--cubes D' splits the problem into cubes of D decisions and solves them one by one,
+    // '--cubes-out FILE' also writes the formula and the cubes to FILE as iCNF
+    let mut cube_depth: Option<usize> = None;
+    if let Some(i) = args.iter().position(|a| a == "--cubes") {
+        cube_depth = Some(args[i + 1].parse().unwrap());
+        args.drain(i..i + 2);
+    }
+    let mut cubes_out: Option<String> = None;
+    if let Some(i) = args.iter().position(|a| a == "--cubes-out") {
+        cubes_out = Some(args[i + 1].clone());
+        args.drain(i..i + 2);
+    }
+    // '--checkpoint FILE' saves the solver there every few restarts, '--resume FILE' continues
+    // from such a file instead of reading a problem
+    let mut options: Options = Options::default();
+    if let Some(i) = args.iter().position(|a| a == "--checkpoint") {
+        options.checkpoint = Some(args[i + 1].clone());
+        args.drain(i..i + 2);
+    }
+    let mut resume: Option<String> = None;
+    if let Some(i) = args.iter().position(|a| a == "--resume") {
+        resume = Some(args[i + 1].clone());
+        args.drain(i..i + 2);
+    }
+    // '--export-learnts FILE' writes the learnt clauses there after solving, '--import-learnts
+    // FILE' starts from the clauses of such a file that are implied by the problem
+    let mut export_learnts: Option<String> = None;
+    if let Some(i) = args.iter().position(|a| a == "--export-learnts") {
+        export_learnts = Some(args[i + 1].clone());
+        args.drain(i..i + 2);
+    }
+    if let Some(i) = args.iter().position(|a| a == "--import-learnts") {
+        options.hints = Some(std::fs::read_to_string(&args[i + 1]).unwrap());
+        args.drain(i..i + 2);
+    }
+    // '--progress-json FILE' writes the progress of the search to FILE, one JSON object per line
+    if let Some(i) = args.iter().position(|a| a == "--progress-json") {
+        options.progress_json = Some(args[i + 1].clone());
+        args.drain(i..i + 2);
+    }
+    // '--trace-events FILE' writes the decisions, conflicts, learnt clauses, backjumps, restarts
+    // and reductions of the search to FILE as JSON lines, 'trace_svg' draws charts of them
+    if let Some(i) = args.iter().position(|a| a == "--trace-events") {
+        options.trace_events = Some(args[i + 1].clone());
+        args.drain(i..i + 2);
+    }
+    // '--stats-json' prints the statistics as one JSON object instead of logging them, the log
+    // then goes to stderr so that the object is the only output on stdout
+    let mut stats_json: bool = false;
+    if let Some(i) = args.iter().position(|a| a == "--stats-json") {
+        stats_json = true;
+        args.remove(i);
+    }
+    let path: &str = args.get(1).map_or("./input.txt", |p| p.as_str());
+
+    let mut data: Vec<u8> = Vec::new();
+    if resume.is_none() {
+        let mut file = File::open(path).unwrap();
+        file.read_to_end(&mut data).unwrap();
+    }
+    let buffer = String::from_utf8_lossy(&data).to_string();
+
+    // the responses of SMT-LIB and iCNF scripts and AIGER witnesses are the only output on stdout
+    if path.ends_with(".smt2")
+        || path.ends_with(".icnf")
+        || path.ends_with(".aag")
+        || path.ends_with(".aig")
+    {
+        WriteLogger::init(
+            LevelFilter::Info,
+            Config::default(),
+            File::create("sat.log").unwrap(),
+        )
+        .unwrap();
+    }
+    if path.ends_with(".smt2") {
+        let mut smt = SmtLib::new();
+        print!("{}", parse_smtlib(&buffer, &mut smt));
+        return;
+    }
+    if path.ends_with(".icnf") {
+        let mut state: SolverState = SolverState::new_simp();
+        print!("{}", parse_icnf(&buffer, &mut state));
+        return;
+    }
+    if path.ends_with(".aag") || path.ends_with(".aig") {
+        let aig: Aiger = parse_aiger(&data).unwrap();
+        let bound: usize = args.get(2).map_or(20, |b| b.parse().unwrap());
+        let mut state: SolverState = SolverState::new_simp();
+        match state.bmc(&aig, bound) {
+            BmcResult::Counterexample(witness) => print!("{}", witness),
+            BmcResult::Safe => print!("0\nb0\n.\n"),
+            BmcResult::Unknown => print!("2\nb0\n.\n"),
+        }
+        return;
+    }
 
     CombinedLogger::init(vec![
         TermLogger::new(
             LevelFilter::Info,
             Config::default(),
-            TerminalMode::Mixed,
+            if stats_json {
+                TerminalMode::Stderr
+            } else {
+                TerminalMode::Mixed
+            },
             ColorChoice::Auto,
         ),
         WriteLogger::new(
@@ -31,11 +147,21 @@ fn main() {
     ])
     .unwrap();
 
-    let mut file = File::open("./input.txt").unwrap();
-    let mut buffer = String::new();
-    file.read_to_string(&mut buffer).unwrap();
-
-    let state = process_problem(&buffer);
+    let state = if let Some(saved) = resume {
+        resume_problem(&saved, &options)
+    } else if let Some(depth) = cube_depth {
+        process_cubes(&buffer, depth, threads, cubes_out.as_deref())
+    } else if threads > 1 {
+        process_portfolio(&buffer, threads)
+    } else {
+        process_problem(&buffer, &options)
+    };
+    if let Some(out) = export_learnts {
+        File::create(out)
+            .unwrap()
+            .write_all(write_learnts(&state).as_bytes())
+            .unwrap();
+    }
 
     let mut result: String = String::new();
     if state.ok {
@@ -44,54 +170,113 @@ fn main() {
         result.push_str("UNSATISFIABLE");
     }
     info!("{}|{}|{}|{}", result, file!(), line!(), 2);
-    println!("{}", state.solver_stats);
+    if stats_json {
+        println!("{}", state.solver_stats.to_json());
+    } else {
+        println!("{}", state.solver_stats);
+    }
 }
 
-fn process_problem(buffer: &str) -> SolverState {
-    let mut state: SolverState = SolverState::new();
-
-    let mut lits: Vec<Lit> = Vec::new();
-    let mut last_var_zero = false;
-    for part in buffer.lines() {
-        if last_var_zero {
-            lits = Vec::new();
-            last_var_zero = false;
-        }
-        if !part.is_empty() && !part.starts_with('c') && !part.starts_with('p') {
-            for var in part.split_whitespace() {
-                if !var.is_empty() {
-                    let parsed_lit: i32 = var.parse::<i32>().unwrap();
-                    let zero_based_abs_var = parsed_lit.abs() - 1;
-                    while zero_based_abs_var >= state.n_vars() {
-                        state.new_var();
-                    }
-                    let solver_lit;
+// Settings of the command line for a single solver.
+#[derive(Default)]
+struct Options {
+    checkpoint: Option<String>,
+    hints: Option<String>,
+    progress_json: Option<String>,
+    trace_events: Option<String>,
+}
 
-                    match parsed_lit.cmp(&0) {
-                        Ordering::Greater => {
-                            solver_lit = Lit::simple(zero_based_abs_var);
-                            lits.push(solver_lit);
-                        }
-                        Ordering::Less => {
-                            solver_lit = !Lit::simple(zero_based_abs_var);
-                            lits.push(solver_lit);
-                        }
-                        Ordering::Equal => {
-                            last_var_zero = true;
-                        }
-                    }
-                }
-            }
-        }
-        if last_var_zero && !lits.is_empty() {
-            state.add_clause(&mut lits);
-        }
+fn process_problem(buffer: &str, options: &Options) -> SolverState {
+    let mut state: SolverState = SolverState::new_simp();
+    parse_dimacs(buffer, &mut state);
+    if let Some(hints) = &options.hints {
+        state.import_hints(&parse_learnts(hints).unwrap());
     }
 
+    configure(&mut state, options);
     state.solve_no_assumptions();
     state
 }
 
+fn resume_problem(saved: &str, options: &Options) -> SolverState {
+    let data: Vec<u8> = std::fs::read(saved).unwrap();
+    let mut state: SolverState = SolverState::restore(&data).unwrap();
+    configure(&mut state, options);
+    state.solve(state.assumptions.clone());
+    state
+}
+
+fn configure(state: &mut SolverState, options: &Options) {
+    state.checkpoint_path.clone_from(&options.checkpoint);
+    if let Some(path) = &options.progress_json {
+        let out: File = File::create(path).unwrap();
+        state.progress.push(Box::new(JsonLines { out }));
+    }
+    if let Some(path) = &options.trace_events {
+        let out: BufWriter<File> = BufWriter::new(File::create(path).unwrap());
+        state.events = Some(Box::new(JsonlTrace::new(out)));
+    }
+}
+
+fn process_portfolio(buffer: &str, threads: usize) -> SolverState {
+    let states: Vec<SolverState> = (0..threads)
+        .map(|_| {
+            let mut state: SolverState = SolverState::new_simp();
+            parse_dimacs(buffer, &mut state);
+            state
+        })
+        .collect();
+    let configs: Vec<PortfolioConfig> = PortfolioConfig::diversified(threads);
+    let (winner, state) = solve_portfolio(states, &configs, &[], true);
+    info!("winning configuration: {}", configs[winner].name);
+    state
+}
+
+// The cubes are written to 'out' when given, then solved by 'threads' solvers.
+fn process_cubes(buffer: &str, depth: usize, threads: usize, out: Option<&str>) -> SolverState {
+    let mut state: SolverState = SolverState::new_simp();
+    parse_dimacs(buffer, &mut state);
+    state.cube_parms.depth = depth;
+    let cubes: Vec<Vec<Lit>> = state.cube();
+    if let Some(path) = out
+        && let Err(e) = std::fs::write(path, write_icnf(&state, &cubes))
+    {
+        error!("cannot write the cubes to {}: {}", path, e);
+    }
+
+    let (results, state) = if threads > 1 {
+        let states: Vec<SolverState> = (0..threads)
+            .map(|_| {
+                let mut state: SolverState = SolverState::new_simp();
+                parse_dimacs(buffer, &mut state);
+                state
+            })
+            .collect();
+        conquer_parallel(states, &cubes)
+    } else {
+        let results: Vec<CubeResult> = state.conquer(&cubes);
+        (results, state)
+    };
+
+    for (k, (cube, result)) in cubes.iter().zip(&results).enumerate() {
+        let lits: Vec<String> = cube.iter().map(|p| dimacs_lit(p).to_string()).collect();
+        let result: &str = match result {
+            CubeResult::Sat => "SAT",
+            CubeResult::Unsat => "UNSAT",
+            CubeResult::Unknown => "UNKNOWN",
+        };
+        info!("cube {} [{}]: {}", k, lits.join(" "), result);
+    }
+    let count = |r: CubeResult| results.iter().filter(|&&x| x == r).count();
+    info!(
+        "cubes: {} sat, {} unsat, {} unknown",
+        count(CubeResult::Sat),
+        count(CubeResult::Unsat),
+        count(CubeResult::Unknown)
+    );
+    state
+}
+
 #[test]
 fn control_problem() {
     let problem = r#"
@@ -100,12 +285,12 @@ p cnf 3 2
 1 -3 0
 2 3 -1 0
 "#;
-    let state = process_problem(problem);
+    let state = process_problem(problem, &Options::default());
 
     assert_eq!(state.solver_stats.starts, 1.);
     assert_eq!(state.solver_stats.conflicts, 0.);
-    assert_eq!(state.solver_stats.decisions, 3.);
-    assert_eq!(state.solver_stats.propagations, 3.);
+    assert_eq!(state.solver_stats.decisions, 2.);
+    assert_eq!(state.solver_stats.propagations, 5.);
     assert_eq!(state.solver_stats.tot_literals, 0.);
 }
 
@@ -202,13 +387,13 @@ p cnf 50 80
 -3 -13 46 0
 -13 -41 43 0
 "#;
-    let state = process_problem(problem);
+    let state = process_problem(problem, &Options::default());
 
     assert_eq!(state.solver_stats.starts, 1.);
-    assert_eq!(state.solver_stats.conflicts, 11.);
-    assert_eq!(state.solver_stats.decisions, 27.);
-    assert_eq!(state.solver_stats.propagations, 168.);
-    assert_eq!(state.solver_stats.tot_literals, 31.);
+    assert_eq!(state.solver_stats.conflicts, 1.);
+    assert_eq!(state.solver_stats.decisions, 4.);
+    assert_eq!(state.solver_stats.propagations, 47.);
+    assert_eq!(state.solver_stats.tot_literals, 1.);
 }
 
 #[test]
@@ -235,13 +420,13 @@ p cnf 16 18
 14 -15  0
 15 16  0
 "#;
-    let state = process_problem(problem);
+    let state = process_problem(problem, &Options::default());
 
     assert_eq!(state.solver_stats.starts, 1.);
-    assert_eq!(state.solver_stats.conflicts, 1.);
-    assert_eq!(state.solver_stats.decisions, 6.);
-    assert_eq!(state.solver_stats.propagations, 25.);
-    assert_eq!(state.solver_stats.tot_literals, 1.);
+    assert_eq!(state.solver_stats.conflicts, 0.);
+    assert_eq!(state.solver_stats.decisions, 1.);
+    assert_eq!(state.solver_stats.propagations, 16.);
+    assert_eq!(state.solver_stats.tot_literals, 0.);
 }
 
 #[test]
@@ -417,11 +602,11 @@ p cnf 100 160
 34 -45 -69 0
 63 -86 -98 0
 "#;
-    let state = process_problem(problem);
+    let state = process_problem(problem, &Options::default());
 
     assert_eq!(state.solver_stats.starts, 1.);
-    assert_eq!(state.solver_stats.conflicts, 30.);
-    assert_eq!(state.solver_stats.decisions, 147.);
-    assert_eq!(state.solver_stats.propagations, 402.);
-    assert_eq!(state.solver_stats.tot_literals, 77.);
+    assert_eq!(state.solver_stats.conflicts, 7.);
+    assert_eq!(state.solver_stats.decisions, 19.);
+    assert_eq!(state.solver_stats.propagations, 71.);
+    assert_eq!(state.solver_stats.tot_literals, 13.);
 }
@@ -0,0 +1,123 @@
+use crate::models::clause::*;
+use crate::models::lit::*;
+use std::ops::{Index, IndexMut};
+
+// Handle to a clause stored in the 'ClauseArena'. Handles stay valid until the next garbage
+// collection, which hands out a relocation table for every live clause.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct ClauseRef(pub u32);
+
+// Watch list entry. If the 'blocker' literal is true the clause is satisfied and does not have to
+// be visited during propagation.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Watcher {
+    pub cref: ClauseRef,
+    pub blocker: Lit,
+}
+
+// Binary clauses are watched on both literals in their own implication lists, the literal that
+// is implied when the watched one becomes false is stored inline.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct BinWatcher {
+    pub cref: ClauseRef,
+    pub implied: Lit,
+}
+
+// Reason of an implied assignment. Binary clauses keep their other (false) literal inline so
+// conflict analysis does not have to visit the arena for them. Literals propagated by a theory
+// get their clause from 'Theory::explain()' when it is first needed.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Reason {
+    Clause(ClauseRef),
+    Binary(Lit),
+    Theory,
+}
+
+#[derive(Clone, Default)]
+pub struct ClauseArena {
+    pub clauses: Vec<Clause>,
+    pub wasted: usize,
+}
+
+pub trait IClauseArena {
+    fn alloc(&mut self, c: Clause) -> ClauseRef;
+    fn free(&mut self, cr: ClauseRef);
+    fn size(&self) -> usize;
+    fn needs_collection(&self) -> bool;
+    fn collect(&mut self) -> Vec<Option<ClauseRef>>;
+}
+
+impl IClauseArena for ClauseArena {
+    fn alloc(&mut self, c: Clause) -> ClauseRef {
+        self.clauses.push(c);
+        ClauseRef(self.clauses.len() as u32 - 1)
+    }
+    fn free(&mut self, cr: ClauseRef) {
+        let c = &mut self.clauses[cr.0 as usize];
+        assert!(!c.deleted);
+        c.deleted = true;
+        c.data = Vec::new();
+        self.wasted += 1;
+    }
+    fn size(&self) -> usize {
+        self.clauses.len()
+    }
+    fn needs_collection(&self) -> bool {
+        self.wasted > 0 && self.wasted * 2 > self.clauses.len()
+    }
+    // Compacts the arena, the returned table maps every old handle to its new position or to
+    // None if the clause was freed.
+    fn collect(&mut self) -> Vec<Option<ClauseRef>> {
+        let mut relocation: Vec<Option<ClauseRef>> = Vec::with_capacity(self.clauses.len());
+        let mut j: usize = 0;
+        for i in 0..self.clauses.len() {
+            if self.clauses[i].deleted {
+                relocation.push(None);
+            } else {
+                self.clauses.swap(i, j);
+                relocation.push(Some(ClauseRef(j as u32)));
+                j += 1;
+            }
+        }
+        self.clauses.truncate(j);
+        self.wasted = 0;
+        relocation
+    }
+}
+
+impl Index<ClauseRef> for ClauseArena {
+    type Output = Clause;
+    fn index(&self, cr: ClauseRef) -> &Clause {
+        &self.clauses[cr.0 as usize]
+    }
+}
+
+impl IndexMut<ClauseRef> for ClauseArena {
+    fn index_mut(&mut self, cr: ClauseRef) -> &mut Clause {
+        &mut self.clauses[cr.0 as usize]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::arena::*;
+
+    #[test]
+    fn collect_relocates_live_clauses() {
+        let mut ca = ClauseArena::default();
+        let a = ca.alloc(Clause::new(false, &[Lit::simple(0), Lit::simple(1)], 0));
+        let b = ca.alloc(Clause::new(true, &[Lit::simple(1), Lit::simple(2)], 1));
+        let c = ca.alloc(Clause::new(true, &[Lit::simple(2), Lit::simple(3)], 2));
+
+        ca.free(b);
+        assert!(!ca.needs_collection());
+        ca.free(a);
+        assert!(ca.needs_collection());
+
+        let relocation = ca.collect();
+        assert_eq!(relocation, vec![None, None, Some(ClauseRef(0))]);
+        assert_eq!(ca.size(), 1);
+        assert_eq!(ca[relocation[c.0 as usize].unwrap()].id, 2);
+        assert_eq!(ca.wasted, 0);
+    }
+}
@@ -0,0 +1,438 @@
+use crate::functions::new_clause::*;
+use crate::models::lbool::*;
+use crate::models::lit::*;
+use crate::models::solverstate::*;
+use std::collections::HashMap;
+use std::ops::Not;
+
+// A Boolean circuit over named inputs, built from AND, XOR and ITE gates with complemented edges.
+// Gates are hashed structurally, building the same gate twice returns the same node, and
+// constants are propagated while building, so no gate ever has a constant input.
+//
+// A node is encoded into the solver on demand. 'assert()' uses the polarity-aware encoding of
+// Plaisted and Greenbaum, a gate only gets the clauses for the polarities it occurs with, while
+// 'lit()' encodes both, its literal can be assumed either way. Polarities that are missing are
+// added when a node is reused. Every variable of the circuit is frozen, later assertions can
+// share gates with earlier ones.
+#[derive(Clone, Debug, Default)]
+pub struct Circuit {
+    gates: Vec<Gate>,
+    hash: HashMap<Gate, u32>,
+    inputs: HashMap<String, u32>,
+    // solver variable of every gate, and the polarities that are encoded
+    vars: Vec<Option<i32>>,
+    encoded: Vec<[bool; 2]>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+enum Gate {
+    False,
+    Input(String),
+    And(Node, Node),
+    Xor(Node, Node),
+    Ite(Node, Node, Node),
+}
+
+// A gate and a complement bit, 'x = 2 * gate + complemented' as for 'Lit'.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Node {
+    pub x: u32,
+}
+
+pub static C_FALSE: Node = Node { x: 0 };
+pub static C_TRUE: Node = Node { x: 1 };
+
+impl Not for Node {
+    type Output = Node;
+    fn not(self) -> Node {
+        Node { x: self.x ^ 1 }
+    }
+}
+
+impl Node {
+    fn gate(self) -> usize {
+        (self.x >> 1) as usize
+    }
+    fn complemented(self) -> bool {
+        self.x & 1 == 1
+    }
+    fn positive(self) -> Node {
+        Node { x: self.x & !1 }
+    }
+    fn flip(self, c: bool) -> Node {
+        Node {
+            x: self.x ^ c as u32,
+        }
+    }
+}
+
+pub trait ICircuit {
+    fn new() -> Self;
+    fn input(&mut self, name: &str) -> Node;
+    fn find(&self, name: &str) -> Option<Node>;
+    fn and(&mut self, a: Node, b: Node) -> Node;
+    fn or(&mut self, a: Node, b: Node) -> Node;
+    fn xor(&mut self, a: Node, b: Node) -> Node;
+    fn implies(&mut self, a: Node, b: Node) -> Node;
+    fn iff(&mut self, a: Node, b: Node) -> Node;
+    fn ite(&mut self, c: Node, t: Node, e: Node) -> Node;
+    fn and_all(&mut self, fs: &[Node]) -> Node;
+    fn or_all(&mut self, fs: &[Node]) -> Node;
+    fn n_gates(&self) -> usize;
+    fn assert(&mut self, state: &mut SolverState, f: Node);
+    fn lit(&mut self, state: &mut SolverState, f: Node) -> Lit;
+    fn encode(&mut self, state: &mut SolverState, f: Node, polarities: &[bool]) -> Lit;
+    fn var_of(&mut self, state: &mut SolverState, g: usize) -> i32;
+    fn value(&self, model: &[Lbool], f: Node) -> bool;
+    fn named_model(&self, model: &[Lbool]) -> Vec<(String, bool)>;
+}
+
+impl ICircuit for Circuit {
+    fn new() -> Self {
+        let mut circuit: Circuit = Circuit::default();
+        circuit.gates.push(Gate::False);
+        circuit.vars.push(None);
+        circuit.encoded.push([false; 2]);
+        circuit
+    }
+
+    fn input(&mut self, name: &str) -> Node {
+        if let Some(node) = self.find(name) {
+            return node;
+        }
+        let node: Node = gate_node(self, Gate::Input(name.to_string()));
+        self.inputs.insert(name.to_string(), node.gate() as u32);
+        node
+    }
+
+    fn find(&self, name: &str) -> Option<Node> {
+        self.inputs.get(name).map(|&g| Node { x: 2 * g })
+    }
+
+    fn and(&mut self, a: Node, b: Node) -> Node {
+        let (a, b) = (a.min(b), a.max(b));
+        if a == C_FALSE || a == !b {
+            return C_FALSE;
+        }
+        if a == C_TRUE || a == b {
+            return b;
+        }
+        gate_node(self, Gate::And(a, b))
+    }
+
+    fn or(&mut self, a: Node, b: Node) -> Node {
+        !self.and(!a, !b)
+    }
+
+    // The output is complemented instead of the inputs, only uncomplemented inputs are hashed.
+    fn xor(&mut self, a: Node, b: Node) -> Node {
+        let c: bool = a.complemented() != b.complemented();
+        let (a, b) = (
+            a.positive().min(b.positive()),
+            a.positive().max(b.positive()),
+        );
+        if a == b {
+            return C_FALSE.flip(c);
+        }
+        if a == C_FALSE {
+            return b.flip(c);
+        }
+        gate_node(self, Gate::Xor(a, b)).flip(c)
+    }
+
+    fn implies(&mut self, a: Node, b: Node) -> Node {
+        self.or(!a, b)
+    }
+
+    fn iff(&mut self, a: Node, b: Node) -> Node {
+        !self.xor(a, b)
+    }
+
+    fn ite(&mut self, c: Node, t: Node, e: Node) -> Node {
+        if c.positive() == C_FALSE {
+            return if c == C_TRUE { t } else { e };
+        }
+        if c.complemented() {
+            return self.ite(!c, e, t);
+        }
+        if t == e {
+            return t;
+        }
+        if t == !e {
+            return self.xor(c, e);
+        }
+        if t.positive() == C_FALSE || t.positive() == c {
+            // c ? 1 : e = c | e and c ? 0 : e = ~c & e
+            return if t == C_TRUE || t == c {
+                self.or(c, e)
+            } else {
+                self.and(!c, e)
+            };
+        }
+        if e.positive() == C_FALSE || e.positive() == c {
+            // c ? t : 0 = c & t and c ? t : 1 = ~c | t
+            return if e == C_FALSE || e == c {
+                self.and(c, t)
+            } else {
+                self.or(!c, t)
+            };
+        }
+        if t.complemented() {
+            return !self.ite(c, !t, !e);
+        }
+        gate_node(self, Gate::Ite(c, t, e))
+    }
+
+    fn and_all(&mut self, fs: &[Node]) -> Node {
+        match fs.len() {
+            0 => C_TRUE,
+            1 => fs[0],
+            n => {
+                let a: Node = self.and_all(&fs[..n / 2]);
+                let b: Node = self.and_all(&fs[n / 2..]);
+                self.and(a, b)
+            }
+        }
+    }
+
+    fn or_all(&mut self, fs: &[Node]) -> Node {
+        let negated: Vec<Node> = fs.iter().map(|f| !*f).collect();
+        !self.and_all(&negated)
+    }
+
+    fn n_gates(&self) -> usize {
+        self.gates.len()
+    }
+
+    // Adds 'f' as a unit clause, encoding it only for the polarity it is asserted with.
+    fn assert(&mut self, state: &mut SolverState, f: Node) {
+        if f == C_TRUE {
+            return;
+        }
+        if f == C_FALSE {
+            state.add_clause(&mut Vec::new());
+            return;
+        }
+        let p: Lit = self.encode(state, f, &[true]);
+        state.add_clause(&mut vec![p]);
+    }
+
+    // The literal of 'f', equivalent to it in both polarities.
+    fn lit(&mut self, state: &mut SolverState, f: Node) -> Lit {
+        self.encode(state, f, &[true, false])
+    }
+
+    // Encodes 'f' for each polarity in 'polarities', TRUE being the clauses that hold when the
+    // literal of 'f' is true.
+    fn encode(&mut self, state: &mut SolverState, f: Node, polarities: &[bool]) -> Lit {
+        trace!("{}|{}|{}|{:?}", "encode", file!(), line!(), f);
+
+        let mut stack: Vec<(Node, bool)> = polarities.iter().map(|&pol| (f, pol)).collect();
+        while let Some((n, pol)) = stack.pop() {
+            let g: usize = n.gate();
+            let pol: bool = pol != n.complemented();
+            if self.encoded[g][pol as usize] {
+                continue;
+            }
+            self.encoded[g][pol as usize] = true;
+
+            let y: Lit = Lit::simple(self.var_of(state, g));
+            let out: Lit = if pol { !y } else { y };
+            match self.gates[g].clone() {
+                Gate::False => {
+                    state.add_clause(&mut vec![!y]);
+                }
+                Gate::Input(_) => {}
+                Gate::And(a, b) => {
+                    let (la, lb) = (self.lit_of(state, a), self.lit_of(state, b));
+                    if pol {
+                        state.add_clause(&mut vec![out, la]);
+                        state.add_clause(&mut vec![out, lb]);
+                    } else {
+                        state.add_clause(&mut vec![out, !la, !lb]);
+                    }
+                    stack.push((a, pol));
+                    stack.push((b, pol));
+                }
+                Gate::Xor(a, b) => {
+                    let (la, lb) = (self.lit_of(state, a), self.lit_of(state, b));
+                    if pol {
+                        state.add_clause(&mut vec![out, la, lb]);
+                        state.add_clause(&mut vec![out, !la, !lb]);
+                    } else {
+                        state.add_clause(&mut vec![out, !la, lb]);
+                        state.add_clause(&mut vec![out, la, !lb]);
+                    }
+                    stack.extend([(a, true), (a, false), (b, true), (b, false)]);
+                }
+                Gate::Ite(c, t, e) => {
+                    let lc: Lit = self.lit_of(state, c);
+                    let (lt, le) = (self.lit_of(state, t), self.lit_of(state, e));
+                    let (lt, le) = if pol { (lt, le) } else { (!lt, !le) };
+                    state.add_clause(&mut vec![out, !lc, lt]);
+                    state.add_clause(&mut vec![out, lc, le]);
+                    stack.extend([(c, true), (c, false), (t, pol), (e, pol)]);
+                }
+            }
+        }
+        self.lit_of(state, f)
+    }
+
+    // The variable of gate 'g', created frozen on first use.
+    fn var_of(&mut self, state: &mut SolverState, g: usize) -> i32 {
+        if let Some(v) = self.vars[g] {
+            return v;
+        }
+        let v: i32 = state.new_var();
+        state.set_frozen(v, true);
+        self.vars[g] = Some(v);
+        v
+    }
+
+    // The value of 'f' under the values of the inputs in 'model', inputs without a value being
+    // false. Gates are not read from the model, with a one-sided encoding they can be off.
+    fn value(&self, model: &[Lbool], f: Node) -> bool {
+        let mut values: Vec<bool> = Vec::with_capacity(f.gate() + 1);
+        let of = |values: &Vec<bool>, n: Node| values[n.gate()] != n.complemented();
+        for g in 0..=f.gate() {
+            let value: bool = match &self.gates[g] {
+                Gate::False => false,
+                Gate::Input(_) => match self.vars[g] {
+                    Some(v) => model.get(v as usize) == Some(&Lbool::True),
+                    None => false,
+                },
+                Gate::And(a, b) => of(&values, *a) && of(&values, *b),
+                Gate::Xor(a, b) => of(&values, *a) != of(&values, *b),
+                Gate::Ite(c, t, e) => {
+                    if of(&values, *c) {
+                        of(&values, *t)
+                    } else {
+                        of(&values, *e)
+                    }
+                }
+            };
+            values.push(value);
+        }
+        of(&values, f)
+    }
+
+    // The values of the inputs that are encoded, sorted by name.
+    fn named_model(&self, model: &[Lbool]) -> Vec<(String, bool)> {
+        let mut named: Vec<(String, bool)> = self
+            .inputs
+            .iter()
+            .filter(|(_, g)| self.vars[**g as usize].is_some())
+            .map(|(name, &g)| (name.clone(), self.value(model, Node { x: 2 * g })))
+            .collect();
+        named.sort();
+        named
+    }
+}
+
+impl Circuit {
+    fn lit_of(&mut self, state: &mut SolverState, n: Node) -> Lit {
+        Lit::new(self.var_of(state, n.gate()), n.complemented())
+    }
+}
+
+// The node of 'gate', a new one unless it was built before.
+fn gate_node(circuit: &mut Circuit, gate: Gate) -> Node {
+    if let Some(&g) = circuit.hash.get(&gate) {
+        return Node { x: 2 * g };
+    }
+    let g: u32 = circuit.gates.len() as u32;
+    circuit.gates.push(gate.clone());
+    circuit.hash.insert(gate, g);
+    circuit.vars.push(None);
+    circuit.encoded.push([false; 2]);
+    Node { x: 2 * g }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::functions::solve::*;
+    use crate::models::circuit::*;
+
+    #[test]
+    fn structural_hashing_and_constants() {
+        let mut c = Circuit::new();
+        let a: Node = c.input("a");
+        let b: Node = c.input("b");
+        assert_eq!(c.input("a"), a);
+
+        let ab: Node = c.and(a, b);
+        assert_eq!(c.and(b, a), ab);
+        assert_eq!(c.or(!a, !b), !ab);
+        assert_eq!(c.xor(!a, b), !c.xor(a, b));
+        assert_eq!(c.and(a, C_TRUE), a);
+        assert_eq!(c.and(a, !a), C_FALSE);
+        assert_eq!(c.xor(a, a), C_FALSE);
+        assert_eq!(c.ite(C_TRUE, a, b), a);
+        assert_eq!(c.ite(a, C_TRUE, C_FALSE), a);
+        assert_eq!(c.ite(a, b, C_FALSE), ab);
+        assert_eq!(c.ite(!a, b, !b), c.xor(a, b));
+        assert_eq!(c.ite(a, !b, a), c.and(a, !b));
+        // the constant, the inputs, two ANDs and a XOR
+        assert_eq!(c.n_gates(), 6);
+    }
+
+    // Two ripple-carry adders written differently, the miter is unsatisfiable.
+    #[test]
+    fn adder_equivalence() {
+        let mut c = Circuit::new();
+        let xs: Vec<Node> = (0..4).map(|i| c.input(&format!("x{}", i))).collect();
+        let ys: Vec<Node> = (0..4).map(|i| c.input(&format!("y{}", i))).collect();
+
+        let mut carry: Node = C_FALSE;
+        let mut carry2: Node = C_FALSE;
+        let mut sums: Vec<Node> = Vec::new();
+        let mut diff: Vec<Node> = Vec::new();
+        for i in 0..4 {
+            let half: Node = c.xor(xs[i], ys[i]);
+            let sum: Node = c.xor(half, carry);
+            let generate: Node = c.and(xs[i], ys[i]);
+            let propagate: Node = c.and(half, carry);
+            carry = c.or(generate, propagate);
+
+            let same: Node = c.iff(xs[i], ys[i]);
+            let sum2: Node = c.ite(carry2, same, half);
+            let either: Node = c.or(ys[i], carry2);
+            let both: Node = c.and(ys[i], carry2);
+            carry2 = c.ite(xs[i], either, both);
+            diff.push(c.xor(sum, sum2));
+            sums.push(sum);
+        }
+        diff.push(c.xor(carry, carry2));
+        let miter: Node = c.or_all(&diff);
+
+        let mut state = SolverState::new();
+        let m: Lit = c.lit(&mut state, miter);
+        state.solve(vec![m]);
+        assert!(state.ok && state.model.is_empty());
+
+        // the sum of 5 and 6 is 11, read back through the input names
+        let mut assumptions: Vec<Lit> = Vec::new();
+        for i in 0..4 {
+            let x: Lit = c.lit(&mut state, xs[i]);
+            let y: Lit = c.lit(&mut state, ys[i]);
+            assumptions.push(if 5 >> i & 1 == 1 { x } else { !x });
+            assumptions.push(if 6 >> i & 1 == 1 { y } else { !y });
+        }
+        let bits: Vec<Lit> = sums.iter().map(|s| c.lit(&mut state, *s)).collect();
+        state.solve(assumptions);
+        assert!(!state.model.is_empty());
+        assert!(!c.value(&state.model, carry));
+        for i in 0..4 {
+            assert_eq!(c.value(&state.model, sums[i]), 11 >> i & 1 == 1);
+            let p: Lit = bits[i];
+            assert_eq!(
+                state.model[var(&p) as usize] == to_bool(!sign(&p)),
+                11 >> i & 1 == 1
+            );
+        }
+        let named: Vec<(String, bool)> = c.named_model(&state.model);
+        assert_eq!(named.len(), 8);
+        assert_eq!(named[0], ("x0".to_string(), true));
+        assert_eq!(named[4], ("y0".to_string(), false));
+    }
+}
@@ -6,6 +6,13 @@ pub struct Clause {
     pub is_learnt: bool,
     pub id: u32,
     pub activity: f64,
+    pub deleted: bool,
+    // set once vivification has tried to shorten the clause
+    pub vivified: bool,
+    // imported from another solver and not used by conflict analysis yet
+    pub imported: bool,
+    // literal blocks distance when the clause was learnt (Audemard & Simon), the size otherwise
+    pub lbd: u32,
 }
 
 pub trait IClause {
@@ -20,6 +27,10 @@ impl IClause for Clause {
             data: _ps.to_vec(),
             is_learnt: _learnt,
             activity: 0.0,
+            deleted: false,
+            vivified: false,
+            imported: false,
+            lbd: _ps.len() as u32,
             id,
         }
     }
@@ -30,27 +41,3 @@ impl IClause for Clause {
         self.is_learnt
     }
 }
-
-impl PartialEq for Clause {
-    fn eq(&self, other: &Self) -> bool {
-        if self.activity == other.activity
-            && self.data.len() == other.data.len()
-            && self.is_learnt == other.is_learnt
-        {
-            let mut f: String = String::new();
-            for y in &self.data {
-                f.push_str(&y.x.to_string())
-            }
-
-            let mut s: String = String::new();
-            for y in &other.data {
-                s.push_str(&y.x.to_string())
-            }
-
-            if f == s {
-                return true;
-            }
-        }
-        false
-    }
-}
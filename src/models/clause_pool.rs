@@ -0,0 +1,161 @@
+use crate::models::lit::*;
+use std::collections::HashSet;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, OnceLock};
+
+const SEGMENT_SIZE: usize = 4096;
+const SEGMENTS: usize = 1024;
+
+// A learnt clause published by one of the workers of a portfolio.
+#[derive(Clone, Debug)]
+pub struct SharedClause {
+    pub lits: Vec<Lit>,
+    pub lbd: u32,
+    pub worker: usize,
+}
+
+// Append-only pool of shared clauses. Writers claim a slot with an atomic counter and fill it
+// once, readers never wait: a slot that is claimed but not written yet ends the read and is
+// picked up by the next one. The slots come in segments that are allocated on first use, once
+// the last one is full further clauses are dropped.
+pub struct ClausePool {
+    segments: Vec<OnceLock<Box<[OnceLock<SharedClause>]>>>,
+    next: AtomicUsize,
+}
+
+pub trait IClausePool {
+    fn new() -> Self;
+    fn push(&self, c: SharedClause) -> bool;
+    fn read(&self, cursor: &mut usize) -> Vec<SharedClause>;
+    fn len(&self) -> usize;
+    fn is_empty(&self) -> bool;
+}
+
+impl IClausePool for ClausePool {
+    fn new() -> Self {
+        Self {
+            segments: (0..SEGMENTS).map(|_| OnceLock::new()).collect(),
+            next: AtomicUsize::new(0),
+        }
+    }
+
+    fn push(&self, c: SharedClause) -> bool {
+        let k: usize = self.next.fetch_add(1, Ordering::Relaxed);
+        if k >= SEGMENT_SIZE * SEGMENTS {
+            return false;
+        }
+        let segment = self.segments[k / SEGMENT_SIZE]
+            .get_or_init(|| (0..SEGMENT_SIZE).map(|_| OnceLock::new()).collect());
+        segment[k % SEGMENT_SIZE].set(c).is_ok()
+    }
+
+    // The clauses from slot 'cursor' on, 'cursor' is moved past them.
+    fn read(&self, cursor: &mut usize) -> Vec<SharedClause> {
+        let mut out: Vec<SharedClause> = Vec::new();
+        while *cursor < SEGMENT_SIZE * SEGMENTS {
+            let slot = self.segments[*cursor / SEGMENT_SIZE]
+                .get()
+                .and_then(|segment| segment[*cursor % SEGMENT_SIZE].get());
+            match slot {
+                Some(c) => out.push(c.clone()),
+                None => break,
+            }
+            *cursor += 1;
+        }
+        out
+    }
+
+    fn len(&self) -> usize {
+        self.next
+            .load(Ordering::Relaxed)
+            .min(SEGMENT_SIZE * SEGMENTS)
+    }
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+// The link of a solver to the pool of its portfolio. Learnt clauses with at most 'max_size'
+// literals or an LBD of at most 'max_lbd' are exported.
+pub struct ClauseSharing {
+    pub pool: Arc<ClausePool>,
+    pub worker: usize,
+    pub max_size: usize,
+    pub max_lbd: u32,
+    // next slot of the pool to import
+    pub cursor: usize,
+    // hashes of the clauses exported or imported so far
+    pub seen: HashSet<u64>,
+    // imported binary clauses that have not been used by conflict analysis yet
+    pub unused_binaries: HashSet<(i32, i32)>,
+}
+
+pub trait IClauseSharing {
+    fn new(pool: Arc<ClausePool>, worker: usize) -> Self;
+    fn first_time(&mut self, lits: &[Lit]) -> bool;
+}
+
+impl IClauseSharing for ClauseSharing {
+    fn new(pool: Arc<ClausePool>, worker: usize) -> Self {
+        Self {
+            pool,
+            worker,
+            max_size: 4,
+            max_lbd: 3,
+            cursor: 0,
+            seen: HashSet::new(),
+            unused_binaries: HashSet::new(),
+        }
+    }
+
+    // Records the clause, FALSE if it was seen before in any order of its literals.
+    fn first_time(&mut self, lits: &[Lit]) -> bool {
+        let mut xs: Vec<i32> = lits.iter().map(|p| p.x).collect();
+        xs.sort_unstable();
+        xs.dedup();
+        let mut hasher = DefaultHasher::new();
+        xs.hash(&mut hasher);
+        self.seen.insert(hasher.finish())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::clause_pool::*;
+    use std::thread;
+
+    #[test]
+    fn concurrent_pushes_are_all_read() {
+        let pool: Arc<ClausePool> = Arc::new(ClausePool::new());
+        thread::scope(|scope| {
+            for worker in 0..4 {
+                let pool = Arc::clone(&pool);
+                scope.spawn(move || {
+                    for v in 0..3000 {
+                        assert!(pool.push(SharedClause {
+                            lits: vec![Lit::new(v, false), Lit::new(v + 1, true)],
+                            lbd: 2,
+                            worker,
+                        }));
+                    }
+                });
+            }
+        });
+        assert_eq!(pool.len(), 12000);
+
+        let mut cursor: usize = 0;
+        let read: Vec<SharedClause> = pool.read(&mut cursor);
+        assert_eq!((read.len(), cursor), (12000, 12000));
+        for worker in 0..4 {
+            assert_eq!(read.iter().filter(|c| c.worker == worker).count(), 3000);
+        }
+        assert!(pool.read(&mut cursor).is_empty());
+
+        let mut sharing: ClauseSharing = ClauseSharing::new(pool, 0);
+        assert!(sharing.first_time(&[Lit::new(1, false), Lit::new(2, true)]));
+        assert!(!sharing.first_time(&[Lit::new(2, true), Lit::new(1, false)]));
+    }
+}
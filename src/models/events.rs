@@ -0,0 +1,242 @@
+use std::io::Write;
+use std::time::Instant;
+
+// One step of the search. Literals are DIMACS numbers, levels are decision levels.
+#[derive(Clone, Debug, PartialEq)]
+pub enum SearchEvent {
+    // a decision opened 'level'
+    Decision { level: u32, lit: i32 },
+    // one round of propagation assigned 'assigned' literals at 'level'
+    Propagation { level: u32, assigned: u32 },
+    // the clause with id 'clause' is false, 'level' is the highest level of its literals
+    Conflict { level: u32, clause: u32 },
+    Learnt { lits: Vec<i32>, lbd: u32 },
+    Backjump { from: u32, to: u32 },
+    // the search restarts after 'conflicts' conflicts in total
+    Restart { conflicts: u64 },
+    Reduce { kept: u32, deleted: u32 },
+}
+
+// Receives the events of 'search()' while it is set as 'events' of a solver.
+pub trait EventSink: Send {
+    fn event(&mut self, e: &SearchEvent);
+    fn flush(&mut self) {}
+}
+
+/*_________________________________________________________________________________________________
+|
+|  JsonlTrace
+|
+|  Description:
+|    Writes every event as a JSON object on a line of its own, with the microseconds since the
+|    trace was created as "t" and the kind of event as "e", for example
+|      {"t":1042,"e":"conflict","level":7,"clause":1290}
+|      {"t":1043,"e":"learnt","lits":[-4,17,9],"lbd":2}
+|    The other kinds are "decision" (level, lit), "propagate" (level, assigned), "backjump" (from,
+|    to), "restart" (conflicts) and "reduce" (kept, deleted). 'parse_event()' reads the lines back.
+|________________________________________________________________________________________________@*/
+pub struct JsonlTrace<W: Write + Send> {
+    pub out: W,
+    pub start: Instant,
+}
+
+impl<W: Write + Send> JsonlTrace<W> {
+    pub fn new(out: W) -> Self {
+        Self {
+            out,
+            start: Instant::now(),
+        }
+    }
+}
+
+impl<W: Write + Send> EventSink for JsonlTrace<W> {
+    fn event(&mut self, e: &SearchEvent) {
+        let fields: String = match e {
+            SearchEvent::Decision { level, lit } => {
+                format!("\"decision\",\"level\":{},\"lit\":{}", level, lit)
+            }
+            SearchEvent::Propagation { level, assigned } => {
+                format!(
+                    "\"propagate\",\"level\":{},\"assigned\":{}",
+                    level, assigned
+                )
+            }
+            SearchEvent::Conflict { level, clause } => {
+                format!("\"conflict\",\"level\":{},\"clause\":{}", level, clause)
+            }
+            SearchEvent::Learnt { lits, lbd } => {
+                let lits: Vec<String> = lits.iter().map(|p| p.to_string()).collect();
+                format!("\"learnt\",\"lits\":[{}],\"lbd\":{}", lits.join(","), lbd)
+            }
+            SearchEvent::Backjump { from, to } => {
+                format!("\"backjump\",\"from\":{},\"to\":{}", from, to)
+            }
+            SearchEvent::Restart { conflicts } => {
+                format!("\"restart\",\"conflicts\":{}", conflicts)
+            }
+            SearchEvent::Reduce { kept, deleted } => {
+                format!("\"reduce\",\"kept\":{},\"deleted\":{}", kept, deleted)
+            }
+        };
+        // a sink has no way to report errors, a closed output just ends the trace
+        let _ = writeln!(
+            self.out,
+            "{{\"t\":{},\"e\":{}}}",
+            self.start.elapsed().as_micros(),
+            fields
+        );
+    }
+
+    fn flush(&mut self) {
+        let _ = self.out.flush();
+    }
+}
+
+/*_________________________________________________________________________________________________
+|
+|  parse_event
+|
+|  Description:
+|    Reads a line written by 'JsonlTrace'.
+|
+|  Output:
+|    The microseconds of the event and the event, None for a line of another shape.
+|________________________________________________________________________________________________@*/
+pub fn parse_event(line: &str) -> Option<(u64, SearchEvent)> {
+    let t: u64 = field(line, "t")?.parse().ok()?;
+    let num = |key: &str| field(line, key).and_then(|v| v.parse::<u32>().ok());
+    let e: SearchEvent = match field(line, "e")? {
+        "\"decision\"" => SearchEvent::Decision {
+            level: num("level")?,
+            lit: field(line, "lit")?.parse().ok()?,
+        },
+        "\"propagate\"" => SearchEvent::Propagation {
+            level: num("level")?,
+            assigned: num("assigned")?,
+        },
+        "\"conflict\"" => SearchEvent::Conflict {
+            level: num("level")?,
+            clause: num("clause")?,
+        },
+        "\"learnt\"" => {
+            let lits: &str = field(line, "lits")?.strip_prefix('[')?.strip_suffix(']')?;
+            SearchEvent::Learnt {
+                lits: lits
+                    .split(',')
+                    .filter(|p| !p.is_empty())
+                    .map(|p| p.parse().ok())
+                    .collect::<Option<Vec<i32>>>()?,
+                lbd: num("lbd")?,
+            }
+        }
+        "\"backjump\"" => SearchEvent::Backjump {
+            from: num("from")?,
+            to: num("to")?,
+        },
+        "\"restart\"" => SearchEvent::Restart {
+            conflicts: field(line, "conflicts")?.parse().ok()?,
+        },
+        "\"reduce\"" => SearchEvent::Reduce {
+            kept: num("kept")?,
+            deleted: num("deleted")?,
+        },
+        _ => return None,
+    };
+    Some((t, e))
+}
+
+// The text of the value of "key" in a flat JSON object, arrays included.
+fn field<'a>(line: &'a str, key: &str) -> Option<&'a str> {
+    let pattern: String = format!("\"{}\":", key);
+    let start: usize = line.find(&pattern)? + pattern.len();
+    let rest: &str = &line[start..];
+    let end: usize = if rest.starts_with('[') {
+        rest.find(']')? + 1
+    } else {
+        rest.find([',', '}'])?
+    };
+    Some(&rest[..end])
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::functions::new_clause::*;
+    use crate::functions::solve::*;
+    use crate::models::events::*;
+    use crate::models::lit::*;
+    use crate::models::solverstate::*;
+    use std::sync::{Arc, Mutex};
+
+    struct Shared(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Shared {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn trace_of_a_search_reads_back() {
+        // 8 pigeons in 7 holes
+        let mut state = SolverState::new();
+        let x = |p: i32, h: i32| Lit::new(p * 7 + h, false);
+        for _ in 0..8 * 7 {
+            state.new_var();
+        }
+        for p in 0..8 {
+            state.add_clause(&mut (0..7).map(|h| x(p, h)).collect());
+        }
+        for h in 0..7 {
+            for p in 0..8 {
+                for q in p + 1..8 {
+                    state.add_clause(&mut vec![!x(p, h), !x(q, h)]);
+                }
+            }
+        }
+        let out: Arc<Mutex<Vec<u8>>> = Arc::new(Mutex::new(Vec::new()));
+        state.events = Some(Box::new(JsonlTrace::new(Shared(Arc::clone(&out)))));
+        state.solve_no_assumptions();
+        assert!(!state.ok);
+
+        let text: String = String::from_utf8(out.lock().unwrap().clone()).unwrap();
+        let events: Vec<(u64, SearchEvent)> =
+            text.lines().map(|l| parse_event(l).unwrap()).collect();
+        assert!(events.windows(2).all(|w| w[0].0 <= w[1].0));
+        let count = |kind: fn(&SearchEvent) -> bool| events.iter().filter(|(_, e)| kind(e)).count();
+        let stats = &state.solver_stats;
+        assert_eq!(
+            count(|e| matches!(e, SearchEvent::Decision { .. })) as f64,
+            stats.decisions
+        );
+        assert_eq!(
+            count(|e| matches!(e, SearchEvent::Conflict { .. })) as f64,
+            stats.conflicts
+        );
+        assert_eq!(
+            count(|e| matches!(e, SearchEvent::Restart { .. })) as u64,
+            stats.restarts
+        );
+        assert_eq!(
+            count(|e| matches!(e, SearchEvent::Reduce { .. })) as u64,
+            stats.reductions
+        );
+        // every conflict but the last one is followed by a backjump and a learnt clause
+        assert_eq!(
+            count(|e| matches!(e, SearchEvent::Learnt { .. })) as f64,
+            stats.conflicts - 1.0
+        );
+        for (_, e) in &events {
+            match e {
+                SearchEvent::Backjump { from, to } => assert!(to < from),
+                SearchEvent::Learnt { lits, lbd } => {
+                    assert!(*lbd >= 1 && *lbd as usize <= lits.len())
+                }
+                _ => (),
+            }
+        }
+        assert!(parse_event("{\"t\":3,\"e\":\"unknown\"}").is_none());
+    }
+}
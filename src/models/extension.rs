@@ -0,0 +1,69 @@
+use crate::models::lbool::*;
+use crate::models::lit::*;
+
+// Clauses taken out of the formula by preprocessing, each one together with the literal that has
+// to be made true when the clause is not satisfied by the model of the remaining formula. The
+// entries are replayed in reverse order, so later eliminations are undone first.
+#[derive(Clone, Default)]
+pub struct ModelExtension {
+    pub witnesses: Vec<Lit>,
+    pub clauses: Vec<Vec<Lit>>,
+}
+
+pub trait IModelExtension {
+    fn push(&mut self, witness: Lit, clause: &[Lit]);
+    fn len(&self) -> usize;
+    fn is_empty(&self) -> bool;
+    fn extend(&self, model: &mut [Lbool]);
+}
+
+impl IModelExtension for ModelExtension {
+    fn push(&mut self, witness: Lit, clause: &[Lit]) {
+        self.witnesses.push(witness);
+        self.clauses.push(clause.to_vec());
+    }
+    fn len(&self) -> usize {
+        self.witnesses.len()
+    }
+    fn is_empty(&self) -> bool {
+        self.witnesses.is_empty()
+    }
+    fn extend(&self, model: &mut [Lbool]) {
+        for value in model.iter_mut() {
+            if is_undefined(*value) {
+                *value = Lbool::False;
+            }
+        }
+
+        for i in (0..self.witnesses.len()).rev() {
+            let satisfied = self.clauses[i]
+                .iter()
+                .any(|p| model[var(p) as usize] == to_bool(!sign(p)));
+            if !satisfied {
+                let w: Lit = self.witnesses[i];
+                model[var(&w) as usize] = to_bool(!sign(&w));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::extension::*;
+
+    #[test]
+    fn extend_eliminated_variable() {
+        // x1 was eliminated from (x0 | x1) & (-x1 | x2)
+        let mut ext = ModelExtension::default();
+        ext.push(Lit::simple(1), &[Lit::simple(0), Lit::simple(1)]);
+        ext.push(!Lit::simple(1), &[!Lit::simple(1), Lit::simple(2)]);
+
+        let mut model = vec![Lbool::False, Lbool::Undef0, Lbool::True];
+        ext.extend(&mut model);
+        assert_eq!(model, vec![Lbool::False, Lbool::True, Lbool::True]);
+
+        let mut model = vec![Lbool::True, Lbool::Undef0, Lbool::False];
+        ext.extend(&mut model);
+        assert_eq!(model[1], Lbool::False);
+    }
+}
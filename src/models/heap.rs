@@ -8,12 +8,12 @@ pub trait IHeap {
     fn new() -> Self;
     fn set_bounds(&mut self, n: i32);
     fn in_heap(&self, n: i32) -> bool;
-    fn increase(&mut self, n: i32, act: Vec<f64>);
-    fn insert(&mut self, n: i32, act: Vec<f64>);
-    fn percolate_up(&mut self, i: i32, act: Vec<f64>);
-    fn percolate_down(&mut self, i: i32, act: Vec<f64>);
+    fn increase(&mut self, n: i32, act: &[f64]);
+    fn insert(&mut self, n: i32, act: &[f64]);
+    fn percolate_up(&mut self, i: i32, act: &[f64]);
+    fn percolate_down(&mut self, i: i32, act: &[f64]);
     fn empty(&self) -> bool;
-    fn getmin(&mut self, act: Vec<f64>) -> i32;
+    fn getmin(&mut self, act: &[f64]) -> i32;
     fn compare(x: f64, y: f64) -> bool;
 }
 
@@ -31,24 +31,17 @@ impl IHeap for Heap {
     fn in_heap(&self, n: i32) -> bool {
         self.indices[n as usize] != 0
     }
-    fn increase(&mut self, n: i32, act: Vec<f64>) {
+    fn increase(&mut self, n: i32, act: &[f64]) {
         assert!(self.indices[n as usize] != 0);
-        <Heap as IHeap>::percolate_up(self, self.indices[n as usize], act.to_vec());
+        <Heap as IHeap>::percolate_up(self, self.indices[n as usize], act);
     }
-    fn insert(&mut self, n: i32, act: Vec<f64>) {
+    fn insert(&mut self, n: i32, act: &[f64]) {
         self.indices[n as usize] = self.heap.len() as i32;
         self.heap.push(n);
-        <Heap as IHeap>::percolate_up(self, self.indices[n as usize], act.to_vec());
+        <Heap as IHeap>::percolate_up(self, self.indices[n as usize], act);
     }
-    fn percolate_up(&mut self, mut _i: i32, act: Vec<f64>) {
-        trace!(
-            "{}|{}|{}|{}|{:?}",
-            "percolate_up".to_string(),
-            file!(),
-            line!(),
-            _i,
-            act
-        );
+    fn percolate_up(&mut self, mut _i: i32, act: &[f64]) {
+        trace!("{}|{}|{}|{}", "percolate_up", file!(), line!(), _i);
 
         let x = self.heap[_i as usize];
         while (_i >> 1) != 0
@@ -65,15 +58,8 @@ impl IHeap for Heap {
         self.heap[_i as usize] = x;
         self.indices[x as usize] = _i;
     }
-    fn percolate_down(&mut self, mut _i: i32, act: Vec<f64>) {
-        trace!(
-            "{}|{}|{}|{}|{:?}",
-            "percolate_down".to_string(),
-            file!(),
-            line!(),
-            _i,
-            act
-        );
+    fn percolate_down(&mut self, mut _i: i32, act: &[f64]) {
+        trace!("{}|{}|{}|{}", "percolate_down", file!(), line!(), _i);
 
         let x = self.heap[_i as usize];
         while _i + _i < self.heap.len() as i32 {
@@ -103,14 +89,14 @@ impl IHeap for Heap {
     fn empty(&self) -> bool {
         self.heap.len() == 1_usize
     }
-    fn getmin(&mut self, act: Vec<f64>) -> i32 {
+    fn getmin(&mut self, act: &[f64]) -> i32 {
         let r = self.heap[1];
         self.heap[1] = *self.heap.last().unwrap();
         self.indices[self.heap[1] as usize] = 1;
         self.indices[r as usize] = 0;
         self.heap.pop();
         if self.heap.len() > 1 {
-            <Heap as IHeap>::percolate_down(self, 1, act.to_vec());
+            <Heap as IHeap>::percolate_down(self, 1, act);
         }
         r
     }
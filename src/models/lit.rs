@@ -66,6 +66,12 @@ pub fn var(lit: &Lit) -> i32 {
     lit.x >> 1
 }
 
+// The literals of DIMACS numbers, for writing clauses in tests.
+#[cfg(test)]
+pub fn lits(xs: &[i32]) -> Vec<Lit> {
+    xs.iter().map(|&x| Lit::new(x.abs() - 1, x < 0)).collect()
+}
+
 /*pub fn swap(i: i32, j: i32, data: &mut Vec<Lit>) {
     assert!((i as usize) < data.len() && (j as usize) < data.len());
     data.swap(i as usize, j as usize);
@@ -1,8 +1,15 @@
+pub mod arena;
+pub mod circuit;
 pub mod clause;
+pub mod clause_pool;
+pub mod events;
+pub mod extension;
 pub mod heap;
 pub mod lbool;
 pub mod lit;
+pub mod progress;
 pub mod random;
 pub mod solverstate;
 pub mod statsparams;
+pub mod theory;
 pub mod varorder;
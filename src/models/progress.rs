@@ -0,0 +1,197 @@
+use std::io::Write;
+use std::time::Duration;
+
+// What made the solver report its progress.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ProgressEvent {
+    Restart,
+    Conflicts,
+    Finish,
+}
+
+// The counters of the solver at one report. 'learnt_limit' is the number of learnt clauses
+// 'reduce_db()' lets the search keep, 'progress' the estimate of 'progress_estimate()' in [0, 1].
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ProgressSnapshot {
+    pub event: ProgressEvent,
+    pub conflicts: u64,
+    pub decisions: u64,
+    pub propagations: u64,
+    pub clauses: usize,
+    pub clauses_literals: u64,
+    pub learnts: usize,
+    pub learnts_literals: u64,
+    pub learnt_limit: u64,
+    pub progress: f64,
+    pub elapsed: Duration,
+}
+
+// Receives the progress of 'solve()': 'started()' once per call, 'progress()' at every restart
+// and every 'progress_interval' conflicts, 'finished()' with a 'Finish' snapshot when the search
+// of the call ends.
+pub trait ProgressListener: Send {
+    fn started(&mut self) {}
+    fn progress(&mut self, s: &ProgressSnapshot);
+    fn finished(&mut self, _s: &ProgressSnapshot) {}
+}
+
+// The MiniSat table of the log, one row per report.
+pub struct TablePrinter;
+
+impl ProgressListener for TablePrinter {
+    fn started(&mut self) {
+        info!(
+            "==================================[MINISAT]========================================"
+        );
+        info!(
+            "| Conflicts |       ORIGINAL        |              LEARNT              | Progress |"
+        );
+        info!(
+            "|           | Clauses      Literals |   Limit Clauses Literals  Lit/Cl |          |"
+        );
+        info!(
+            "==================================================================================="
+        );
+    }
+
+    fn progress(&mut self, s: &ProgressSnapshot) {
+        let lits_per_clause: f64 = if s.learnts > 0 {
+            s.learnts_literals as f64 / s.learnts as f64
+        } else {
+            0.0
+        };
+        info!(
+            "| {:>9} | {:>7} {:>13} | {:>7} {:>7} {:>8} {:>7.1} | {:>6.3} % |",
+            s.conflicts,
+            s.clauses,
+            s.clauses_literals,
+            s.learnt_limit,
+            s.learnts,
+            s.learnts_literals,
+            lits_per_clause,
+            s.progress * 100.0
+        );
+    }
+
+    fn finished(&mut self, _s: &ProgressSnapshot) {
+        info!(
+            "==================================================================================="
+        );
+    }
+}
+
+// One JSON object per report, on a line of its own.
+pub struct JsonLines<W: Write + Send> {
+    pub out: W,
+}
+
+impl<W: Write + Send> ProgressListener for JsonLines<W> {
+    fn progress(&mut self, s: &ProgressSnapshot) {
+        let event: &str = match s.event {
+            ProgressEvent::Restart => "restart",
+            ProgressEvent::Conflicts => "conflicts",
+            ProgressEvent::Finish => "finish",
+        };
+        // NaN and infinity are not JSON numbers
+        let progress: String = if s.progress.is_finite() {
+            s.progress.to_string()
+        } else {
+            "null".to_string()
+        };
+        // a listener has no way to report errors, a closed output just ends the lines
+        let _ = writeln!(
+            self.out,
+            "{{\"event\":\"{}\",\"conflicts\":{},\"decisions\":{},\"propagations\":{},\
+             \"clauses\":{},\"clauses_literals\":{},\"learnts\":{},\"learnts_literals\":{},\
+             \"learnt_limit\":{},\"progress\":{},\"elapsed\":{}}}",
+            event,
+            s.conflicts,
+            s.decisions,
+            s.propagations,
+            s.clauses,
+            s.clauses_literals,
+            s.learnts,
+            s.learnts_literals,
+            s.learnt_limit,
+            progress,
+            s.elapsed.as_secs_f64()
+        );
+    }
+
+    fn finished(&mut self, s: &ProgressSnapshot) {
+        self.progress(s);
+        let _ = self.out.flush();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::functions::new_clause::*;
+    use crate::functions::solve::*;
+    use crate::models::lit::*;
+    use crate::models::progress::*;
+    use crate::models::solverstate::*;
+    use std::sync::{Arc, Mutex};
+
+    struct Recorder(Arc<Mutex<Vec<ProgressSnapshot>>>);
+
+    impl ProgressListener for Recorder {
+        fn progress(&mut self, s: &ProgressSnapshot) {
+            self.0.lock().unwrap().push(*s);
+        }
+        fn finished(&mut self, s: &ProgressSnapshot) {
+            self.0.lock().unwrap().push(*s);
+        }
+    }
+
+    #[test]
+    fn listeners_see_restarts_and_conflicts() {
+        // 8 pigeons in 7 holes
+        let mut state = SolverState::new();
+        let x = |p: i32, h: i32| Lit::new(p * 7 + h, false);
+        for _ in 0..8 * 7 {
+            state.new_var();
+        }
+        for p in 0..8 {
+            state.add_clause(&mut (0..7).map(|h| x(p, h)).collect());
+        }
+        for h in 0..7 {
+            for p in 0..8 {
+                for q in p + 1..8 {
+                    state.add_clause(&mut vec![!x(p, h), !x(q, h)]);
+                }
+            }
+        }
+        let seen: Arc<Mutex<Vec<ProgressSnapshot>>> = Arc::new(Mutex::new(Vec::new()));
+        state.progress = vec![Box::new(Recorder(Arc::clone(&seen)))];
+        state.progress_interval = 100;
+        state.solve_no_assumptions();
+        assert!(!state.ok);
+
+        let seen = seen.lock().unwrap();
+        let count = |e: ProgressEvent| seen.iter().filter(|s| s.event == e).count();
+        assert_eq!(
+            count(ProgressEvent::Restart) as f64,
+            state.solver_stats.starts
+        );
+        assert_eq!(
+            count(ProgressEvent::Conflicts) as f64,
+            (state.solver_stats.conflicts / 100.0).floor()
+        );
+        assert_eq!(seen.last().unwrap().event, ProgressEvent::Finish);
+        assert_eq!(count(ProgressEvent::Finish), 1);
+        assert!(
+            seen.windows(2)
+                .all(|w| w[0].conflicts <= w[1].conflicts && w[0].elapsed <= w[1].elapsed)
+        );
+
+        let mut json = JsonLines { out: Vec::new() };
+        json.progress(&ProgressSnapshot {
+            progress: f64::NAN,
+            ..seen[0]
+        });
+        let line: String = String::from_utf8(json.out).unwrap();
+        assert!(line.starts_with("{\"event\":\"restart\",\"conflicts\":0,"));
+        assert!(line.contains("\"progress\":null,") && line.ends_with("}\n"));
+    }
+}
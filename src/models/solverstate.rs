@@ -2,13 +2,20 @@ use crate::functions::enqueue::*;
 use crate::functions::new_clause::*;
 use crate::functions::search::*;
 
-use crate::models::clause::*;
+use crate::models::arena::*;
+use crate::models::clause_pool::*;
+use crate::models::events::*;
+use crate::models::extension::*;
 use crate::models::lbool::*;
 use crate::models::lit::*;
+use crate::models::progress::*;
 use crate::models::statsparams::*;
+use crate::models::theory::*;
 use crate::models::varorder::*;
+use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
+use std::time::Instant;
 
-use std::cmp;
 
 #[derive(Clone)]
 pub struct Activity {
@@ -20,26 +27,28 @@ pub struct Assigns {
     pub col: Vec<Lbool>,
 }
 
-#[derive(Clone)]
 pub struct SolverState {
     pub ok: bool,
-    pub clauses: Vec<Clause>,
-    pub learnts: Vec<Clause>,
+    pub ca: ClauseArena,
+    pub clauses: Vec<ClauseRef>,
+    pub learnts: Vec<ClauseRef>,
     pub cla_inc: f64,
     pub cla_decay: f64,
     pub activity: Activity,
     pub var_inc: f64,
     pub var_decay: f64,
     pub order: VarOrder,
-    pub watches: Vec<Vec<Clause>>,
+    pub watches: Vec<Vec<Watcher>>,
+    pub bin_watches: Vec<Vec<BinWatcher>>,
     pub assigns: Assigns,
     pub trail: Vec<Lit>,
     pub trail_lim: Vec<i32>,
-    pub reason: Vec<Option<Clause>>,
+    pub reason: Vec<Option<Reason>>,
     pub level: Vec<i32>,
     pub trail_pos: Vec<i32>,
     pub root_level: i32,
     pub qhead: i32,
+    pub bin_qhead: i32,
     pub simp_db_assigns: i32,
     pub simp_db_props: f64,
     pub analyze_seen: Vec<Lbool>,
@@ -49,29 +58,73 @@ pub struct SolverState {
     pub add_binary_tmp: Vec<Lit>,
     pub add_ternary_tmp: Vec<Lit>,
     // DLPP(T)
-    pub level_to_backtrack: i32,
+    pub theory: Option<Box<dyn Theory>>,
+    pub theory_head: usize,
+    pub theory_out: TheoryOutput,
     pub solver_stats: SolverStats,
     // SOLVING
     pub progress_estimate: f64,
+    // told about the progress at every restart and every 'progress_interval' conflicts, 0 for
+    // restarts only
+    pub progress: Vec<Box<dyn ProgressListener>>,
+    pub progress_interval: u64,
+    pub solve_started: Instant,
+    // gets every decision, propagation, conflict, backjump, restart and reduction when set
+    pub events: Option<Box<dyn EventSink>>,
     pub model: Vec<Lbool>,
     pub conflict: Vec<Lit>,
     //MOO
     pub default_parms: SearchParams,
     pub expensive_ccmin: bool,
+    pub shrink_ccmin: bool,
+    pub binary_ccmin: bool,
+    // backjumps over more than this many levels backtrack chronologically instead, negative disables
+    pub chrono_backtrack: i32,
+    pub restart_policy: RestartPolicy,
+    // restarts of the current 'solve()' call and its limit on learnt clauses
+    pub restarts: u32,
+    pub nof_learnts: f64,
+    // raised by another thread to make 'solve()' give up at the next restart or decision
+    pub interrupt: Option<Arc<AtomicBool>>,
+    // learnt clauses exchanged with the other solvers of a portfolio
+    pub sharing: Option<ClauseSharing>,
+    // PREPROCESSING
+    pub preprocess_parms: PreprocessParams,
+    pub frozen: Vec<bool>,
+    pub eliminated: Vec<bool>,
+    pub extension: ModelExtension,
+    // INPROCESSING
+    pub probe_parms: ProbeParams,
+    pub next_probe: f64,
+    pub probe_head: usize,
+    pub assumptions: Vec<Lit>,
+    pub vivify_parms: VivifyParams,
+    pub next_vivify: f64,
     pub clause_id_counter: u32,
+    // CUBE AND CONQUER
+    pub cube_parms: CubeParams,
+    // CHECKPOINTS
+    // written every 'checkpoint_interval' restarts when set
+    pub checkpoint_path: Option<String>,
+    pub checkpoint_interval: u32,
+    // set by 'restore()', the next 'solve()' continues with the restored counters
+    pub resumed: bool,
 }
 
 pub trait NewState {
     fn new() -> Self;
+    fn new_simp() -> Self;
 }
 
 impl NewState for SolverState {
     fn new() -> Self {
         let mut solver = Self {
+            ca: ClauseArena::default(),
             clauses: Vec::new(),
             learnts: Vec::new(),
             activity: Activity { col: Vec::new() },
             watches: Vec::new(),
+            bin_watches: Vec::new(),
             assigns: Assigns { col: Vec::new() },
             trail_pos: Vec::new(),
             trail: Vec::new(),
@@ -94,6 +147,7 @@ impl NewState for SolverState {
             var_decay: 1.0,
             order: VarOrder::default(),
             qhead: 0,
+            bin_qhead: 0,
             simp_db_assigns: 0,
             simp_db_props: 0.0,
             default_parms: SearchParams {
@@ -102,10 +156,60 @@ impl NewState for SolverState {
                 random_var_freq: 0.02,
             },
             expensive_ccmin: true,
+            shrink_ccmin: true,
+            binary_ccmin: true,
+            chrono_backtrack: -1,
+            restart_policy: RestartPolicy::Geometric {
+                first: 100.0,
+                factor: 1.5,
+            },
+            restarts: 0,
+            nof_learnts: 0.0,
+            interrupt: None,
+            sharing: None,
+            preprocess_parms: PreprocessParams {
+                enabled: false,
+                elim: true,
+                blocked: true,
+                covered: true,
+                grow: 0,
+                clause_lim: 20,
+                subsumption_lim: 1000,
+            },
+            frozen: Vec::new(),
+            eliminated: Vec::new(),
+            extension: ModelExtension::default(),
+            probe_parms: ProbeParams {
+                enabled: false,
+                budget: 10000.0,
+                interval: 100000.0,
+            },
+            next_probe: 0.0,
+            probe_head: 0,
+            assumptions: Vec::new(),
+            vivify_parms: VivifyParams {
+                enabled: false,
+                budget: 20000.0,
+                interval: 50000.0,
+            },
+            next_vivify: 0.0,
             progress_estimate: 0.0,
+            progress: vec![Box::new(TablePrinter)],
+            progress_interval: 10000,
+            solve_started: Instant::now(),
+            events: None,
             root_level: 0,
-            level_to_backtrack: 0,
+            theory: None,
+            theory_head: 0,
+            theory_out: TheoryOutput::default(),
             clause_id_counter: 0,
+            cube_parms: CubeParams {
+                depth: 8,
+                candidates: 64,
+            },
+            checkpoint_path: None,
+            checkpoint_interval: 10,
+            resumed: false,
         };
 
         solver.add_unit_tmp.resize(2, Lit::new(-1, false));
@@ -113,6 +217,19 @@ impl NewState for SolverState {
         solver.add_ternary_tmp.resize(3, Lit::new(-1, false));
         solver
     }
+
+    // A solver that also simplifies the formula, the way the command line runs it: preprocessing
+    // before every 'solve()', probing and vivification during the search and chronological
+    // backtracking. Variables that are assumed or get clauses after the first 'solve()' have to
+    // be frozen with 'set_frozen' first, the others may be eliminated.
+    fn new_simp() -> Self {
+        let mut solver: SolverState = SolverState::new();
+        solver.chrono_backtrack = 100;
+        solver.preprocess_parms.enabled = true;
+        solver.probe_parms.enabled = true;
+        solver.vivify_parms.enabled = true;
+        solver
+    }
 }
 
 #[derive(Copy, Clone, Debug)]
@@ -122,30 +239,115 @@ pub struct SearchParams {
     pub random_var_freq: f64,
 }
 
+// Number of conflicts of each call to 'search()', a geometric series or the Luby sequence
+// (1, 1, 2, 1, 1, 2, 4, ...) times 'unit'.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum RestartPolicy {
+    Geometric { first: f64, factor: f64 },
+    Luby { unit: f64 },
+}
+
+pub trait IRestartPolicy {
+    fn conflicts(&self, restart: u32) -> f64;
+}
+
+impl IRestartPolicy for RestartPolicy {
+    fn conflicts(&self, restart: u32) -> f64 {
+        match *self {
+            RestartPolicy::Geometric { first, factor } => first * factor.powi(restart as i32),
+            RestartPolicy::Luby { unit } => unit * luby(restart),
+        }
+    }
+}
+
+// The 'i'-th element of the Luby sequence, counting from 0.
+fn luby(i: u32) -> f64 {
+    let mut i: u64 = i as u64;
+    // the sub-sequence of length 2^k - 1 that contains 'i'
+    let mut size: u64 = 1;
+    let mut k: i32 = 0;
+    while size < i + 1 {
+        k += 1;
+        size = 2 * size + 1;
+    }
+    while size - 1 != i {
+        size = (size - 1) / 2;
+        k -= 1;
+        i %= size;
+    }
+    2f64.powi(k)
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct PreprocessParams {
+    pub enabled: bool,
+    // bounded variable elimination
+    pub elim: bool,
+    // blocked clause elimination, 'covered' extends it with covered literal addition (CCE)
+    pub blocked: bool,
+    pub covered: bool,
+    // number of clauses an elimination may add on top of the ones it removes
+    pub grow: usize,
+    // resolvents longer than this block the elimination of a variable
+    pub clause_lim: usize,
+    // occurrence lists longer than this are not used for subsumption
+    pub subsumption_lim: usize,
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct ProbeParams {
+    pub enabled: bool,
+    // propagations a single round of failed literal probing may use
+    pub budget: f64,
+    // propagations of the search between two rounds
+    pub interval: f64,
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct VivifyParams {
+    pub enabled: bool,
+    // propagations a single round of vivification may use
+    pub budget: f64,
+    // propagations of the search between two rounds
+    pub interval: f64,
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct CubeParams {
+    // number of decisions of a cube, failed literals come on top
+    pub depth: usize,
+    // variables looked ahead at every split, the ones with most occurrences
+    pub candidates: usize,
+}
+
 pub trait Internal {
     fn i_enqueue(&mut self, fact: Lit) -> bool;
     fn var_bump_activity(&mut self, p: Lit);
     fn var_decay_activity(&mut self);
     fn cla_decay_activity(&mut self);
-    fn cla_bump_activity(&mut self, c: &mut Clause);
-    fn locked(&mut self, _c: &Clause) -> bool;
-    fn decision_level(&mut self) -> i32;
+    fn cla_bump_activity(&mut self, cr: ClauseRef);
+    fn locked(&mut self, cr: ClauseRef) -> bool;
+    fn decision_level(&self) -> i32;
+    fn reason_size(&self, r: Reason) -> usize;
+    fn reason_lit(&self, r: Reason, i: usize) -> Lit;
 }
 
 pub trait NewVar {
-    fn n_vars(&mut self) -> i32;
+    fn n_vars(&self) -> i32;
     fn add_clause(&mut self, ps: &mut Vec<Lit>);
+    fn set_polarity(&mut self, v: i32, value: Option<bool>);
+    fn set_frozen(&mut self, v: i32, frozen: bool);
 }
 
 pub trait SemiInternal {
-    fn n_assigns(self) -> usize;
-    fn n_clauses(self) -> usize;
-    fn n_learnts(self) -> usize;
+    fn n_assigns(&self) -> usize;
+    fn n_clauses(&self) -> usize;
+    fn n_learnts(&self) -> usize;
 }
 
 pub trait Setters {
-    fn value_by_var(&mut self, x: i32) -> Lbool;
-    fn value_by_lit(&mut self, x: Lit) -> Lbool;
+    fn value_by_var(&self, x: i32) -> Lbool;
+    fn value_by_lit(&self, x: Lit) -> Lbool;
     fn add_activity(&mut self, val: f64);
     fn add_assigns(&mut self, val: Lbool);
     fn update_activity(&mut self, val: f64, i: usize);
@@ -153,11 +355,11 @@ pub trait Setters {
 }
 
 impl Setters for SolverState {
-    fn value_by_var(&mut self, x: i32) -> Lbool {
+    fn value_by_var(&self, x: i32) -> Lbool {
         self.assigns.col[x as usize]
     }
 
-    fn value_by_lit(&mut self, x: Lit) -> Lbool {
+    fn value_by_lit(&self, x: Lit) -> Lbool {
         let mut assign = self.assigns.col[var(&x) as usize];
         if sign(&x) {
             assign = bit_not(assign);
@@ -166,19 +368,15 @@ impl Setters for SolverState {
     }
     fn add_activity(&mut self, val: f64) {
         self.activity.col.push(val);
-        self.order.activity.col.push(val);
     }
     fn add_assigns(&mut self, val: Lbool) {
         self.assigns.col.push(val);
-        self.order.assigns.col.push(val);
     }
     fn update_activity(&mut self, val: f64, i: usize) {
         self.activity.col[i] = val;
-        self.order.activity.col[i] = val;
     }
     fn update_assigns(&mut self, val: Lbool, i: usize) {
         self.assigns.col[i] = val;
-        self.order.assigns.col[i] = val;
     }
 }
 
@@ -198,7 +396,7 @@ impl Internal for SolverState {
         if self.activity.col[index as usize] > 1e100 {
             self.var_rescale_activity();
         }
-        self.order.update(var(&p));
+        self.order.update(var(&p), &self.activity.col);
     }
     fn var_decay_activity(&mut self) {
         if self.var_decay >= 0.0 {
@@ -208,86 +406,74 @@ impl Internal for SolverState {
     fn cla_decay_activity(&mut self) {
         self.cla_inc *= self.cla_decay;
     }
-    fn cla_bump_activity(&mut self, c: &mut Clause) {
-        c.activity += self.cla_inc;
-        if c.activity > 1e20 {
+    fn cla_bump_activity(&mut self, cr: ClauseRef) {
+        self.ca[cr].activity += self.cla_inc;
+        if self.ca[cr].activity > 1e20 {
             self.cla_rescale_activity();
         }
     }
-    fn locked(&mut self, _c: &Clause) -> bool {
-        match &self.reason[var(&_c.data[0]) as usize] {
-            Some(x) => _c == x,
-            _ => false,
-        }
+    fn locked(&mut self, cr: ClauseRef) -> bool {
+        self.reason[var(&self.ca[cr].data[0]) as usize] == Some(Reason::Clause(cr))
     }
-    fn decision_level(&mut self) -> i32 {
+    fn decision_level(&self) -> i32 {
         self.trail_lim.len() as i32
     }
+    // A reason is read like a clause whose first literal is the implied one.
+    fn reason_size(&self, r: Reason) -> usize {
+        match r {
+            Reason::Clause(cr) => self.ca[cr].data.len(),
+            Reason::Binary(_) => 2,
+            Reason::Theory => panic!("theory reasons are explained before they are read"),
+        }
+    }
+    fn reason_lit(&self, r: Reason, i: usize) -> Lit {
+        match r {
+            Reason::Clause(cr) => self.ca[cr].data[i],
+            Reason::Binary(q) => {
+                assert!(i == 1);
+                q
+            }
+            Reason::Theory => panic!("theory reasons are explained before they are read"),
+        }
+    }
 }
 
 impl SemiInternal for SolverState {
-    fn n_assigns(self) -> usize {
+    fn n_assigns(&self) -> usize {
         self.trail.len()
     }
-    fn n_clauses(self) -> usize {
+    fn n_clauses(&self) -> usize {
         self.clauses.len()
     }
-    fn n_learnts(self) -> usize {
+    fn n_learnts(&self) -> usize {
         self.learnts.len()
     }
 }
 
 impl NewVar for SolverState {
-    fn n_vars(&mut self) -> i32 {
+    fn n_vars(&self) -> i32 {
         self.assigns.col.len() as i32
     }
     fn add_clause(&mut self, ps: &mut Vec<Lit>) {
         trace!(
             "{}|{}|{}|{:?}",
-            "add_clause".to_string(),
+            "add_clause",
             file!(),
             line!(),
             ps
         );
+        assert!(
+            ps.iter().all(|p| !self.eliminated[var(p) as usize]),
+            "clause uses an eliminated variable, freeze it before solving"
+        );
         self.new_clause(ps, false);
     }
-}
-
-pub fn move_back(_l1: Lit, _l2: Lit, solver_state: &mut SolverState) {
-    trace!(
-        "{}|{}|{}|{:?}|{:?}",
-        "move_back".to_string(),
-        file!(),
-        line!(),
-        _l1,
-        _l2
-    );
-
-    let mut lev1: i32 = solver_state.level[var(&_l1) as usize];
-    let mut lev2: i32 = solver_state.level[var(&_l2) as usize];
-    if lev1 == -1 {
-        lev1 = i32::MAX;
-    }
-    if lev2 == -1 {
-        lev2 = i32::MAX;
+    fn set_polarity(&mut self, v: i32, value: Option<bool>) {
+        self.order.set_polarity(v, value);
     }
-
-    if lev1 < solver_state.level_to_backtrack || lev2 < solver_state.level_to_backtrack {
-        if solver_state.value_by_lit(_l1) == Lbool::True {
-            if solver_state.value_by_lit(_l2) == Lbool::True
-                || lev1 <= lev2
-                || solver_state.level_to_backtrack <= lev2
-            {
-            } else {
-                solver_state.level_to_backtrack = lev2;
-            }
-        } else if solver_state.value_by_lit(_l2) == Lbool::True {
-            if lev2 <= lev1 || solver_state.level_to_backtrack <= lev1 {
-            } else {
-                solver_state.level_to_backtrack = lev1;
-            }
-        } else {
-            solver_state.level_to_backtrack = cmp::min(lev1, lev2);
-        }
+    // Frozen variables are never eliminated by preprocessing, incremental users should freeze the
+    // variables they will use in later clauses or assumptions.
+    fn set_frozen(&mut self, v: i32, frozen: bool) {
+        self.frozen[v as usize] = frozen;
     }
 }
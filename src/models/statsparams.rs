@@ -1,7 +1,5 @@
 use std::fmt::Display;
-use std::time::Instant;
-use sys_info::mem_info;
-use sys_info::MemInfo;
+use std::time::{Duration, Instant};
 
 #[derive(Copy, Clone)]
 pub struct SolverStats {
@@ -14,10 +12,35 @@ pub struct SolverStats {
     pub learnts_literals: f64,
     pub max_literals: f64,
     pub tot_literals: f64,
+    pub subsumed_clauses: f64,
+    pub strengthened_clauses: f64,
+    pub eliminated_vars: f64,
+    pub blocked_clauses: f64,
+    pub covered_clauses: f64,
+    pub failed_literals: f64,
+    pub hyper_binaries: f64,
+    pub substituted_vars: f64,
+    pub vivified_clauses: f64,
+    pub vivify_strengthened: f64,
+    pub vivify_literals: f64,
+    pub theory_lemmas: f64,
+    pub theory_propagations: f64,
+    pub shared_exported: f64,
+    pub shared_imported: f64,
+    pub shared_used: f64,
+    pub restarts: u64,
+    // calls to 'reduce_db()' and the learnt clauses they deleted
+    pub reductions: u64,
+    pub deleted_clauses: u64,
+    // literals removed from learnt clauses by minimisation
+    pub minimized_literals: u64,
+    pub simplify_calls: u64,
+    pub max_decision_level: u64,
 }
 
 pub trait ISolverStats {
     fn new() -> Self;
+    fn to_json(&self) -> String;
 }
 
 impl ISolverStats for SolverStats {
@@ -32,45 +55,223 @@ impl ISolverStats for SolverStats {
             learnts_literals: 0.0,
             max_literals: 0.0,
             tot_literals: 0.0,
+            subsumed_clauses: 0.0,
+            strengthened_clauses: 0.0,
+            eliminated_vars: 0.0,
+            blocked_clauses: 0.0,
+            covered_clauses: 0.0,
+            failed_literals: 0.0,
+            hyper_binaries: 0.0,
+            substituted_vars: 0.0,
+            vivified_clauses: 0.0,
+            vivify_strengthened: 0.0,
+            vivify_literals: 0.0,
+            theory_lemmas: 0.0,
+            theory_propagations: 0.0,
+            shared_exported: 0.0,
+            shared_imported: 0.0,
+            shared_used: 0.0,
+            restarts: 0,
+            reductions: 0,
+            deleted_clauses: 0,
+            minimized_literals: 0,
+            simplify_calls: 0,
+            max_decision_level: 0,
         }
     }
+
+    // The counters as one JSON object, with the elapsed and CPU seconds and the peak resident
+    // set size in KB ('null' where the platform does not tell).
+    fn to_json(&self) -> String {
+        let usage: Option<ResourceUsage> = resource_usage();
+        let counters: [(&str, u64); 30] = [
+            ("starts", self.starts as u64),
+            ("restarts", self.restarts),
+            ("decisions", self.decisions as u64),
+            ("propagations", self.propagations as u64),
+            ("conflicts", self.conflicts as u64),
+            ("max_decision_level", self.max_decision_level),
+            ("clauses_literals", self.clauses_literals as u64),
+            ("learnts_literals", self.learnts_literals as u64),
+            ("max_literals", self.max_literals as u64),
+            ("tot_literals", self.tot_literals as u64),
+            ("minimized_literals", self.minimized_literals),
+            ("reductions", self.reductions),
+            ("deleted_clauses", self.deleted_clauses),
+            ("simplify_calls", self.simplify_calls),
+            ("subsumed_clauses", self.subsumed_clauses as u64),
+            ("strengthened_clauses", self.strengthened_clauses as u64),
+            ("eliminated_vars", self.eliminated_vars as u64),
+            ("blocked_clauses", self.blocked_clauses as u64),
+            ("covered_clauses", self.covered_clauses as u64),
+            ("failed_literals", self.failed_literals as u64),
+            ("hyper_binaries", self.hyper_binaries as u64),
+            ("substituted_vars", self.substituted_vars as u64),
+            ("vivified_clauses", self.vivified_clauses as u64),
+            ("vivify_strengthened", self.vivify_strengthened as u64),
+            ("vivify_literals", self.vivify_literals as u64),
+            ("theory_lemmas", self.theory_lemmas as u64),
+            ("theory_propagations", self.theory_propagations as u64),
+            ("shared_exported", self.shared_exported as u64),
+            ("shared_imported", self.shared_imported as u64),
+            ("shared_used", self.shared_used as u64),
+        ];
+        let mut out: String = String::from("{");
+        for (name, value) in counters {
+            out.push_str(&format!("\"{}\":{},", name, value));
+        }
+        out.push_str(&format!(
+            "\"elapsed_seconds\":{},",
+            self.start_time.elapsed().as_secs_f64()
+        ));
+        match usage {
+            Some(u) => out.push_str(&format!(
+                "\"cpu_seconds\":{},\"peak_rss_kb\":{}}}",
+                u.cpu_time.as_secs_f64(),
+                u.peak_rss_kb
+            )),
+            None => out.push_str("\"cpu_seconds\":null,\"peak_rss_kb\":null}"),
+        }
+        out
+    }
 }
 
 impl Display for SolverStats {
     fn fmt(&self, _: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
-        let duration: u64 = (self.start_time.elapsed().subsec_millis()) as u64;
-        info!("restarts              : {0}", self.starts);
+        let elapsed: f64 = self.start_time.elapsed().as_secs_f64();
+        let usage: Option<ResourceUsage> = resource_usage();
+        // the rates are per second of CPU time, the elapsed time where it is not known
+        let seconds: f64 = usage
+            .map_or(elapsed, |u| u.cpu_time.as_secs_f64())
+            .max(1e-3);
+        info!("restarts              : {0}", self.restarts);
         info!(
-            "conflicts             : {0}    ({1}/ms)",
+            "conflicts             : {0}    ({1:.0}/s)",
             self.conflicts,
-            (self.conflicts / duration as f64)
+            self.conflicts / seconds
         );
         info!(
-            "decisions             : {0}    ({1}/ms)",
+            "decisions             : {0}    ({1:.0}/s, max level {2})",
             self.decisions,
-            (self.decisions / duration as f64)
+            self.decisions / seconds,
+            self.max_decision_level
         );
         info!(
-            "propagations          : {0}    ({1}/ms)",
+            "propagations          : {0}    ({1:.0}/s)",
             self.propagations,
-            (self.propagations / duration as f64)
+            self.propagations / seconds
         );
         info!(
-            "conflict literals     : {0}   ({1} % deleted)",
-            self.tot_literals,
-            (self.max_literals - self.tot_literals) * 100.0 / self.max_literals
+            "conflict literals     : {0}   ({1} minimized away)",
+            self.tot_literals, self.minimized_literals
         );
         info!(
-            "Available Memory      : {0} / {1} MB",
-            mem_used().free,
-            mem_used().total
+            "reductions            : {0}    ({1} clauses deleted), {2} simplifications",
+            self.reductions, self.deleted_clauses, self.simplify_calls
         );
-        info!("CPU time              : {0} ms", duration);
+        info!(
+            "vivified clauses      : {0}    ({1} strengthened, {2} literals removed)",
+            self.vivified_clauses, self.vivify_strengthened, self.vivify_literals
+        );
+        info!(
+            "shared clauses        : {0} exported, {1} imported, {2} used",
+            self.shared_exported, self.shared_imported, self.shared_used
+        );
+        if let Some(u) = usage {
+            info!(
+                "peak memory           : {0:.1} MB",
+                u.peak_rss_kb as f64 / 1024.0
+            );
+            info!("CPU time              : {0:.3} s", u.cpu_time.as_secs_f64());
+        }
+        info!("elapsed time          : {0:.3} s", elapsed);
 
         Ok(())
     }
 }
 
-pub fn mem_used() -> MemInfo {
-    mem_info().unwrap()
+// CPU time of the process, over all of its threads, and the most memory it has held.
+#[derive(Copy, Clone, Debug)]
+pub struct ResourceUsage {
+    pub cpu_time: Duration,
+    pub peak_rss_kb: u64,
+}
+
+#[cfg(unix)]
+pub fn resource_usage() -> Option<ResourceUsage> {
+    // SAFETY: 'getrusage' only writes to the struct it is given, which is valid when zeroed
+    let mut ru: libc::rusage = unsafe { std::mem::zeroed() };
+    if unsafe { libc::getrusage(libc::RUSAGE_SELF, &mut ru) } != 0 {
+        return None;
+    }
+    let time = |t: libc::timeval| {
+        Duration::from_secs(t.tv_sec as u64) + Duration::from_micros(t.tv_usec as u64)
+    };
+    // macOS counts the resident set in bytes, the others in KB
+    let unit: u64 = if cfg!(target_os = "macos") { 1024 } else { 1 };
+    Some(ResourceUsage {
+        cpu_time: time(ru.ru_utime) + time(ru.ru_stime),
+        peak_rss_kb: ru.ru_maxrss as u64 / unit,
+    })
+}
+
+#[cfg(not(unix))]
+pub fn resource_usage() -> Option<ResourceUsage> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::functions::new_clause::*;
+    use crate::functions::solve::*;
+    use crate::models::lit::*;
+    use crate::models::solverstate::*;
+    use crate::models::statsparams::*;
+
+    #[test]
+    fn counters_come_out_as_json() {
+        // 7 pigeons in 6 holes
+        let mut state = SolverState::new();
+        let x = |p: i32, h: i32| Lit::new(p * 6 + h, false);
+        for _ in 0..7 * 6 {
+            state.new_var();
+        }
+        for p in 0..7 {
+            state.add_clause(&mut (0..6).map(|h| x(p, h)).collect());
+        }
+        for h in 0..6 {
+            for p in 0..7 {
+                for q in p + 1..7 {
+                    state.add_clause(&mut vec![!x(p, h), !x(q, h)]);
+                }
+            }
+        }
+        state.solve_no_assumptions();
+        assert!(!state.ok);
+
+        let stats: SolverStats = state.solver_stats;
+        assert_eq!(stats.restarts as f64, stats.starts - 1.0);
+        assert!(stats.reductions > 0 && stats.deleted_clauses > 0);
+        assert!(stats.simplify_calls > 0);
+        assert!(stats.max_decision_level > 0 && stats.max_decision_level < 42);
+        assert!(stats.minimized_literals > 0);
+        assert_eq!(
+            stats.max_literals - stats.tot_literals,
+            stats.minimized_literals as f64
+        );
+
+        let json: String = stats.to_json();
+        assert!(json.starts_with(&format!(
+            "{{\"starts\":{},\"restarts\":{},",
+            stats.starts, stats.restarts
+        )));
+        assert!(json.contains(&format!("\"deleted_clauses\":{},", stats.deleted_clauses)));
+        assert!(json.contains("\"elapsed_seconds\":") && json.ends_with('}'));
+        let usage: Option<ResourceUsage> = resource_usage();
+        assert_eq!(usage.is_some(), cfg!(unix));
+        if let Some(u) = usage {
+            assert!(u.peak_rss_kb > 0);
+            assert!(!json.contains("null"));
+        }
+    }
 }
@@ -0,0 +1,51 @@
+use crate::models::lit::*;
+use std::any::Any;
+
+// A theory solver plugged into the search (DPLL(T)). The solver passes every literal it puts on
+// the trail to 'assign()', in trail order, and calls 'backtrack()' when literals are taken off
+// again. Chronological backtracking can keep literals of lower levels, they are passed again
+// after the backtrack.
+//
+// Once unit propagation is done 'propagate()' is called and 'final_check()' is called on a full
+// assignment. Both may add lemmas, clauses implied by the theory, and propagate literals. The
+// explanation of a propagated literal is only asked for, through 'explain()', when conflict
+// analysis needs it. A model is accepted when 'final_check()' adds nothing.
+//
+// The variables of the theory atoms must be frozen, preprocessing would otherwise eliminate them.
+pub trait Theory: Any + Send {
+    // 'p' was assigned.
+    fn assign(&mut self, p: Lit);
+    // Only the first 'n' assigned literals are still assigned.
+    fn backtrack(&mut self, n: usize);
+    fn propagate(&mut self, out: &mut TheoryOutput);
+    fn final_check(&mut self, out: &mut TheoryOutput);
+    // The clause (p | ~a | ~b | ...) for a literal propagated through 'out', 'a', 'b', ... being
+    // true literals assigned before 'p'. Literals implied by the theory alone are added as unit
+    // lemmas instead, an explanation has at least two literals.
+    fn explain(&mut self, p: Lit) -> Vec<Lit>;
+}
+
+// Lemmas and propagations of a theory, applied by the solver after the callback returns.
+#[derive(Clone, Default, Debug)]
+pub struct TheoryOutput {
+    pub lemmas: Vec<Vec<Lit>>,
+    pub propagations: Vec<Lit>,
+}
+
+pub trait ITheoryOutput {
+    fn add_lemma(&mut self, clause: &[Lit]);
+    fn propagate(&mut self, p: Lit);
+    fn is_empty(&self) -> bool;
+}
+
+impl ITheoryOutput for TheoryOutput {
+    fn add_lemma(&mut self, clause: &[Lit]) {
+        self.lemmas.push(clause.to_vec());
+    }
+    fn propagate(&mut self, p: Lit) {
+        self.propagations.push(p);
+    }
+    fn is_empty(&self) -> bool {
+        self.lemmas.is_empty() && self.propagations.is_empty()
+    }
+}
@@ -1,83 +1,150 @@
 use crate::models::heap::*;
-use crate::models::lbool::is_undefined;
+use crate::models::lbool::*;
 use crate::models::lit::*;
 use crate::models::random::{drand, irand};
-use crate::models::solverstate::*;
 
+// How the sign of a decision literal is chosen. A polarity set through
+// 'set_polarity' always takes precedence over the mode.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum PolarityMode {
+    // last value the variable had before it was unassigned (phase saving)
+    Saved,
+    False,
+    True,
+    Random,
+    // value the variable had on the longest trail since the restart
+    Target,
+}
+
+// The order does not keep copies of the solver's assignments and activities, they are borrowed
+// on every call so that a bump or a decision costs O(log n) instead of a full vector copy.
 #[derive(Clone)]
 pub struct VarOrder {
-    pub assigns: Assigns,
-    pub activity: Activity,
     pub heap: Heap,
     pub random_seed: f64,
+    pub polarity_mode: PolarityMode,
+    pub saved_phase: Vec<bool>,
+    pub user_polarity: Vec<Option<bool>>,
+    pub target_phase: Vec<bool>,
+    pub target_assigned: usize,
+    pub decision: Vec<bool>,
 }
 
 pub trait IVarOrder {
-    fn new_var(&mut self);
-    fn update(&mut self, x: i32);
-    fn undo(&mut self, x: i32);
-    fn select(&mut self, random_var_freq: f64) -> Lit;
+    fn new_var(&mut self, activity: &[f64]);
+    fn update(&mut self, x: i32, activity: &[f64]);
+    fn undo(&mut self, x: i32, activity: &[f64]);
+    fn select(&mut self, random_var_freq: f64, assigns: &[Lbool], activity: &[f64]) -> Lit;
+    fn set_polarity(&mut self, x: i32, value: Option<bool>);
+    fn save_phase(&mut self, x: i32, value: bool);
+    fn update_target(&mut self, trail: &[Lit]);
+    fn reset_target(&mut self);
+    fn polarity(&mut self, x: i32) -> Lit;
+    fn set_decision_var(&mut self, x: i32, decision: bool, activity: &[f64]);
 }
 
 impl Default for VarOrder {
     fn default() -> VarOrder {
         VarOrder {
-            assigns: Assigns { col: Vec::new() },
-            activity: Activity { col: Vec::new() },
             heap: Heap::new(),
             random_seed: 91648253.0,
+            polarity_mode: PolarityMode::Saved,
+            saved_phase: Vec::new(),
+            user_polarity: Vec::new(),
+            target_phase: Vec::new(),
+            target_assigned: 0,
+            decision: Vec::new(),
         }
     }
 }
 
 impl IVarOrder for VarOrder {
-    fn new_var(&mut self) {
-        self.heap.set_bounds(self.assigns.col.len() as i32);
-        self.heap.insert(
-            self.assigns.col.len() as i32 - 1,
-            self.activity.col.to_vec(),
-        );
-    }
-    fn update(&mut self, x: i32) {
+    fn new_var(&mut self, activity: &[f64]) {
+        self.saved_phase.push(false);
+        self.user_polarity.push(None);
+        self.target_phase.push(false);
+        self.decision.push(true);
+        self.heap.set_bounds(activity.len() as i32);
+        self.heap.insert(activity.len() as i32 - 1, activity);
+    }
+    fn update(&mut self, x: i32, activity: &[f64]) {
         if self.heap.in_heap(x) {
-            self.heap.increase(x, self.activity.col.to_vec());
+            self.heap.increase(x, activity);
         }
     }
-    fn undo(&mut self, x: i32) {
-        if !self.heap.in_heap(x) {
-            self.heap.insert(x, self.activity.col.to_vec());
+    fn undo(&mut self, x: i32, activity: &[f64]) {
+        if !self.heap.in_heap(x) && self.decision[x as usize] {
+            self.heap.insert(x, activity);
         }
     }
-    fn select(&mut self, random_var_freq: f64) -> Lit {
+    fn select(&mut self, random_var_freq: f64, assigns: &[Lbool], activity: &[f64]) -> Lit {
         if !self.heap.empty() && drand(&mut self.random_seed) < random_var_freq {
-            let next: i32 = irand(&mut self.random_seed, self.assigns.col.len() as i32);
-            if is_undefined(self.assigns.col[next as usize]) {
-                return !Lit::simple(next);
+            let next: i32 = irand(&mut self.random_seed, assigns.len() as i32);
+            if is_undefined(assigns[next as usize]) && self.decision[next as usize] {
+                return self.polarity(next);
             }
         }
 
         while !self.heap.empty() {
-            let next: i32 = self.heap.getmin(self.activity.col.clone());
+            let next: i32 = self.heap.getmin(activity);
 
-            if is_undefined(self.assigns.col[next as usize]) {
-                return !Lit::simple(next);
+            if is_undefined(assigns[next as usize]) && self.decision[next as usize] {
+                return self.polarity(next);
             }
         }
 
         Lit::undefined()
     }
+    fn set_polarity(&mut self, x: i32, value: Option<bool>) {
+        self.user_polarity[x as usize] = value;
+    }
+    fn save_phase(&mut self, x: i32, value: bool) {
+        self.saved_phase[x as usize] = value;
+    }
+    // Records the whole trail when it is longer than the longest one so far, the target phase
+    // is then the assignment of a single conflict-free trail.
+    fn update_target(&mut self, trail: &[Lit]) {
+        if trail.len() <= self.target_assigned {
+            return;
+        }
+        for p in trail {
+            self.target_phase[var(p) as usize] = !sign(p);
+        }
+        self.target_assigned = trail.len();
+    }
+    fn reset_target(&mut self) {
+        self.target_assigned = 0;
+    }
+    // Variables that are not decision variables (e.g. eliminated ones) are never selected.
+    fn set_decision_var(&mut self, x: i32, decision: bool, activity: &[f64]) {
+        self.decision[x as usize] = decision;
+        if decision && !self.heap.in_heap(x) {
+            self.heap.insert(x, activity);
+        }
+    }
+    fn polarity(&mut self, x: i32) -> Lit {
+        let value: bool = match self.user_polarity[x as usize] {
+            Some(v) => v,
+            None => match self.polarity_mode {
+                PolarityMode::Saved => self.saved_phase[x as usize],
+                PolarityMode::False => false,
+                PolarityMode::True => true,
+                PolarityMode::Random => drand(&mut self.random_seed) < 0.5,
+                PolarityMode::Target => self.target_phase[x as usize],
+            },
+        };
+        Lit::new(x, !value)
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::models::lbool::*;
     use crate::models::varorder::*;
 
     #[test]
     fn new_var_order() {
         let vo = VarOrder::default();
-        assert_eq!(vo.assigns.col.len(), 0);
-        assert_eq!(vo.activity.col.len(), 0);
+        assert_eq!(vo.saved_phase.len(), 0);
         assert_eq!(vo.heap.heap.len(), 1);
         assert_eq!(vo.heap.heap[0], -1);
         assert_eq!(vo.heap.indices.len(), 0);
@@ -87,11 +154,66 @@ mod tests {
 
     fn insert_new_var() {
         let mut vo = VarOrder::default();
-        vo.assigns.col.push(Lbool::True);
-        vo.new_var();
-        assert_eq!(vo.assigns.col.len(), 1);
+        vo.new_var(&[0.0]);
+        assert_eq!(vo.saved_phase.len(), 1);
         assert_eq!(vo.heap.indices.len(), 1);
         assert_eq!(vo.heap.heap.len() as i32, 2);
-        assert_eq!(vo.heap.indices[0] as i32, 1);
+        assert_eq!(vo.heap.indices[0], 1);
+    }
+
+    #[test]
+    fn select_polarity() {
+        let mut vo = VarOrder::default();
+        vo.new_var(&[0.0]);
+        assert_eq!(vo.polarity(0), !Lit::simple(0));
+
+        vo.save_phase(0, true);
+        assert_eq!(vo.polarity(0), Lit::simple(0));
+
+        vo.polarity_mode = PolarityMode::False;
+        assert_eq!(vo.polarity(0), !Lit::simple(0));
+
+        vo.set_polarity(0, Some(true));
+        assert_eq!(vo.select(0.0, &[Lbool::Undef0], &[0.0]), Lit::simple(0));
+    }
+
+    #[test]
+    fn select_most_active() {
+        let mut vo = VarOrder::default();
+        let mut activity: Vec<f64> = Vec::new();
+        for _ in 0..4 {
+            activity.push(0.0);
+            vo.new_var(&activity);
+        }
+        activity[2] = 3.0;
+        vo.update(2, &activity);
+        activity[1] = 2.0;
+        vo.update(1, &activity);
+
+        let assigns = vec![Lbool::Undef0, Lbool::Undef0, Lbool::True, Lbool::Undef0];
+        assert_eq!(vo.select(0.0, &assigns, &activity), !Lit::simple(1));
+
+        vo.undo(2, &activity);
+        assert_eq!(vo.select(0.0, &[Lbool::Undef0; 4], &activity), !Lit::simple(2));
+    }
+
+    #[test]
+    fn target_is_the_longest_trail() {
+        let mut vo = VarOrder::default();
+        for _ in 0..3 {
+            vo.new_var(&[0.0, 0.0, 0.0]);
+        }
+        vo.polarity_mode = PolarityMode::Target;
+        vo.update_target(&[Lit::simple(0), !Lit::simple(1)]);
+        assert_eq!(vo.target_assigned, 2);
+
+        // a shorter trail changes nothing, a longer one replaces the target completely
+        vo.update_target(&[Lit::simple(1)]);
+        assert_eq!(vo.polarity(1), !Lit::simple(1));
+        vo.update_target(&[!Lit::simple(0), Lit::simple(1), Lit::simple(2)]);
+        assert_eq!(vo.target_assigned, 3);
+        assert_eq!(vo.polarity(0), !Lit::simple(0));
+        assert_eq!(vo.polarity(1), Lit::simple(1));
+        assert_eq!(vo.polarity(2), Lit::simple(2));
     }
 }
@@ -0,0 +1,292 @@
+/*_________________________________________________________________________________________________
+|
+|  parse_aiger
+|
+|  Description:
+|    Reads an AND-inverter graph in the ASCII ('aag') or binary ('aig') AIGER format, version 1.9
+|    headers with bad states and invariant constraints included. Literals are kept as in the
+|    file: variable 'v' is the literal 2v, its negation 2v + 1, and literal 0 is FALSE. The AND
+|    gates are returned in topological order, an ASCII file may list them in any order. Justice
+|    and fairness properties are not supported, the symbol table and comments are skipped.
+|________________________________________________________________________________________________@*/
+pub fn parse_aiger(data: &[u8]) -> Result<Aiger, String> {
+    let mut pos: usize = 0;
+    let line: String = read_line(data, &mut pos)?;
+    let mut fields = line.split_whitespace();
+    let binary: bool = match fields.next() {
+        Some("aag") => false,
+        Some("aig") => true,
+        _ => return Err("not an AIGER file".to_string()),
+    };
+    let mut header: Vec<u32> = fields
+        .map(|f| f.parse().map_err(|_| format!("bad header field {}", f)))
+        .collect::<Result<_, _>>()?;
+    if header.len() < 5 || header.len() > 9 {
+        return Err("the header needs 5 to 9 numbers".to_string());
+    }
+    header.resize(9, 0);
+    let (m, i, l, o, a) = (header[0], header[1], header[2], header[3], header[4]);
+    let (b, c, j, f) = (header[5], header[6], header[7], header[8]);
+    if j > 0 || f > 0 {
+        return Err("justice and fairness properties are not supported".to_string());
+    }
+    if i + l + a > m {
+        return Err("more inputs, latches and gates than variables".to_string());
+    }
+
+    let mut aig: Aiger = Aiger {
+        max_var: m,
+        ..Aiger::default()
+    };
+    let check = |lit: u32| -> Result<u32, String> {
+        if lit > 2 * m + 1 {
+            return Err(format!("literal {} exceeds the maximum variable", lit));
+        }
+        Ok(lit)
+    };
+
+    for k in 0..i {
+        let lit: u32 = if binary {
+            2 * (k + 1)
+        } else {
+            check(read_numbers(data, &mut pos, 1, 1)?[0])?
+        };
+        aig.inputs.push(lit);
+    }
+    for k in 0..l {
+        // the reset value is optional
+        let n: usize = if binary { 1 } else { 2 };
+        let mut numbers: Vec<u32> = read_numbers(data, &mut pos, n, n + 1)?;
+        if binary {
+            numbers.insert(0, 2 * (i + k + 1));
+        }
+        let lit: u32 = check(numbers[0])?;
+        let reset: u32 = *numbers.get(2).unwrap_or(&0);
+        if reset > 1 && reset != lit {
+            return Err(format!("bad reset value of latch {}", lit));
+        }
+        aig.latches.push(Latch {
+            lit,
+            next: check(numbers[1])?,
+            reset,
+        });
+    }
+    for _ in 0..o {
+        aig.outputs
+            .push(check(read_numbers(data, &mut pos, 1, 1)?[0])?);
+    }
+    for _ in 0..b {
+        aig.bad.push(check(read_numbers(data, &mut pos, 1, 1)?[0])?);
+    }
+    for _ in 0..c {
+        aig.constraints
+            .push(check(read_numbers(data, &mut pos, 1, 1)?[0])?);
+    }
+
+    for k in 0..a {
+        let gate: AndGate = if binary {
+            let lhs: u32 = 2 * (i + l + k + 1);
+            let rhs0: u32 = lhs
+                .checked_sub(read_delta(data, &mut pos)?)
+                .ok_or("bad delta encoding")?;
+            let rhs1: u32 = rhs0
+                .checked_sub(read_delta(data, &mut pos)?)
+                .ok_or("bad delta encoding")?;
+            AndGate { lhs, rhs0, rhs1 }
+        } else {
+            let numbers: Vec<u32> = read_numbers(data, &mut pos, 3, 3)?;
+            AndGate {
+                lhs: check(numbers[0])?,
+                rhs0: check(numbers[1])?,
+                rhs1: check(numbers[2])?,
+            }
+        };
+        aig.ands.push(gate);
+    }
+
+    sort_ands(&mut aig)?;
+    Ok(aig)
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Aiger {
+    pub max_var: u32,
+    pub inputs: Vec<u32>,
+    pub latches: Vec<Latch>,
+    pub outputs: Vec<u32>,
+    pub bad: Vec<u32>,
+    pub constraints: Vec<u32>,
+    pub ands: Vec<AndGate>,
+}
+
+// A latch with its next state function, a reset of 0 or 1 or its own literal when uninitialized.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Latch {
+    pub lit: u32,
+    pub next: u32,
+    pub reset: u32,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct AndGate {
+    pub lhs: u32,
+    pub rhs0: u32,
+    pub rhs1: u32,
+}
+
+pub trait IAiger {
+    fn properties(&self) -> &[u32];
+}
+
+impl IAiger for Aiger {
+    // The bad state literals, the outputs for files that predate them.
+    fn properties(&self) -> &[u32] {
+        if self.bad.is_empty() {
+            &self.outputs
+        } else {
+            &self.bad
+        }
+    }
+}
+
+// A counterexample in the AIGER witness format: the property that fails, the initial values of
+// the latches and the inputs of every frame.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Witness {
+    pub property: usize,
+    pub init: Vec<bool>,
+    pub inputs: Vec<Vec<bool>>,
+}
+
+impl std::fmt::Display for Witness {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let bits =
+            |v: &Vec<bool>| -> String { v.iter().map(|&b| if b { '1' } else { '0' }).collect() };
+        writeln!(f, "1")?;
+        writeln!(f, "b{}", self.property)?;
+        writeln!(f, "{}", bits(&self.init))?;
+        for frame in &self.inputs {
+            writeln!(f, "{}", bits(frame))?;
+        }
+        writeln!(f, ".")
+    }
+}
+
+fn read_line(data: &[u8], pos: &mut usize) -> Result<String, String> {
+    if *pos >= data.len() {
+        return Err("unexpected end of file".to_string());
+    }
+    let end: usize = data[*pos..]
+        .iter()
+        .position(|&c| c == b'\n')
+        .map_or(data.len(), |k| *pos + k);
+    let line: String = String::from_utf8_lossy(&data[*pos..end]).to_string();
+    *pos = end + 1;
+    Ok(line)
+}
+
+// A line of 'min' to 'max' numbers.
+fn read_numbers(data: &[u8], pos: &mut usize, min: usize, max: usize) -> Result<Vec<u32>, String> {
+    let line: String = read_line(data, pos)?;
+    let numbers: Vec<u32> = line
+        .split_whitespace()
+        .map(|f| f.parse().map_err(|_| format!("bad number {}", f)))
+        .collect::<Result<_, _>>()?;
+    if numbers.len() < min || numbers.len() > max {
+        return Err(format!("unexpected line '{}'", line));
+    }
+    Ok(numbers)
+}
+
+// A number of the binary AND section, 7 bits per byte with the high bit set on all but the last.
+fn read_delta(data: &[u8], pos: &mut usize) -> Result<u32, String> {
+    let mut x: u32 = 0;
+    let mut shift: u32 = 0;
+    loop {
+        let byte: u8 = *data.get(*pos).ok_or("unexpected end of file")?;
+        *pos += 1;
+        if shift > 28 {
+            return Err("delta does not fit".to_string());
+        }
+        x |= ((byte & 0x7f) as u32) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(x);
+        }
+        shift += 7;
+    }
+}
+
+// Orders the AND gates so that every gate comes after the gates of its inputs.
+fn sort_ands(aig: &mut Aiger) -> Result<(), String> {
+    let n: usize = aig.max_var as usize + 1;
+    let mut gate_of: Vec<Option<usize>> = vec![None; n];
+    for (k, g) in aig.ands.iter().enumerate() {
+        if g.lhs & 1 == 1 || g.lhs < 2 || gate_of[(g.lhs >> 1) as usize].is_some() {
+            return Err(format!("bad AND gate {}", g.lhs));
+        }
+        gate_of[(g.lhs >> 1) as usize] = Some(k);
+    }
+
+    // 0 not visited, 1 on the stack, 2 done
+    let mut mark: Vec<u8> = vec![0; aig.ands.len()];
+    let mut order: Vec<AndGate> = Vec::with_capacity(aig.ands.len());
+    for root in 0..aig.ands.len() {
+        let mut stack: Vec<(usize, bool)> = vec![(root, false)];
+        while let Some((k, expanded)) = stack.pop() {
+            if expanded {
+                mark[k] = 2;
+                order.push(aig.ands[k]);
+                continue;
+            }
+            if mark[k] == 2 {
+                continue;
+            }
+            if mark[k] == 1 {
+                return Err(format!("cyclic AND gate {}", aig.ands[k].lhs));
+            }
+            mark[k] = 1;
+            stack.push((k, true));
+            for rhs in [aig.ands[k].rhs0, aig.ands[k].rhs1] {
+                if let Some(d) = gate_of[(rhs >> 1) as usize] {
+                    if mark[d] == 1 {
+                        return Err(format!("cyclic AND gate {}", aig.ands[d].lhs));
+                    }
+                    if mark[d] == 0 {
+                        stack.push((d, false));
+                    }
+                }
+            }
+        }
+    }
+    aig.ands = order;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parsers::aiger::*;
+
+    #[test]
+    fn ascii_and_binary_agree() {
+        // the latch is set while the input is on and it is off, the gate is also the bad state
+        let ascii: &[u8] = b"aag 3 1 1 0 1 1\n2\n4 6\n6\n6 5 2\ni0 enable\nc\nanything\n";
+        let binary: &[u8] = b"aig 3 1 1 0 1 1\n6\n6\n\x01\x03i0 enable\n";
+        let a: Aiger = parse_aiger(ascii).unwrap();
+        assert_eq!(a, parse_aiger(binary).unwrap());
+        assert_eq!(
+            a.ands,
+            vec![AndGate {
+                lhs: 6,
+                rhs0: 5,
+                rhs1: 2
+            }]
+        );
+        assert_eq!(a.properties(), &[6]);
+
+        // gates out of order are sorted, cycles are rejected
+        let a: Aiger = parse_aiger(b"aag 4 2 0 1 2\n2\n4\n8\n8 6 2\n6 4 2\n").unwrap();
+        assert_eq!(a.ands.iter().map(|g| g.lhs).collect::<Vec<_>>(), vec![6, 8]);
+        assert!(parse_aiger(b"aag 3 1 0 1 2\n2\n6\n6 4 2\n4 6 2\n").is_err());
+        assert!(parse_aiger(b"aag 1 1 0 0 0 0 0 1 0\n2\n").is_err());
+    }
+}
@@ -0,0 +1,52 @@
+use crate::functions::new_clause::*;
+use crate::models::lit::*;
+use crate::models::solverstate::*;
+use std::cmp::Ordering;
+
+/*_________________________________________________________________________________________________
+|
+|  parse_dimacs
+|
+|  Description:
+|    Reads a problem in DIMACS CNF format and adds its clauses to the solver. Variables are created
+|    on demand, comment ('c') and problem ('p') lines are skipped.
+|________________________________________________________________________________________________@*/
+pub fn parse_dimacs(buffer: &str, state: &mut SolverState) {
+    let mut lits: Vec<Lit> = Vec::new();
+    let mut last_var_zero = false;
+    for part in buffer.lines() {
+        if last_var_zero {
+            lits = Vec::new();
+            last_var_zero = false;
+        }
+        if !part.is_empty() && !part.starts_with('c') && !part.starts_with('p') {
+            for var in part.split_whitespace() {
+                if !var.is_empty() {
+                    let parsed_lit: i32 = var.parse::<i32>().unwrap();
+                    let zero_based_abs_var = parsed_lit.abs() - 1;
+                    while zero_based_abs_var >= state.n_vars() {
+                        state.new_var();
+                    }
+                    let solver_lit;
+
+                    match parsed_lit.cmp(&0) {
+                        Ordering::Greater => {
+                            solver_lit = Lit::simple(zero_based_abs_var);
+                            lits.push(solver_lit);
+                        }
+                        Ordering::Less => {
+                            solver_lit = !Lit::simple(zero_based_abs_var);
+                            lits.push(solver_lit);
+                        }
+                        Ordering::Equal => {
+                            last_var_zero = true;
+                        }
+                    }
+                }
+            }
+        }
+        if last_var_zero && !lits.is_empty() {
+            state.add_clause(&mut lits);
+        }
+    }
+}
@@ -0,0 +1,147 @@
+use crate::functions::new_clause::*;
+use crate::functions::solve::*;
+use crate::models::lit::*;
+use crate::models::solverstate::*;
+
+/*_________________________________________________________________________________________________
+|
+|  parse_icnf
+|
+|  Description:
+|    Runs an incremental problem in the iCNF format: clause lines as in DIMACS add clauses to the
+|    solver, an assumption line 'a <lits> 0' calls 'solve()' with these assumptions on the clauses
+|    read so far. Every variable is frozen, later clauses can refer to any of them.
+|
+|  Output:
+|    One answer per assumption line, 's SATISFIABLE' or 's UNSATISFIABLE'. An unsatisfiable one is
+|    followed by 'f <lits> 0', the assumptions of the 'conflict' that made it fail (none if the
+|    clauses alone are unsatisfiable).
+|________________________________________________________________________________________________@*/
+pub fn parse_icnf(buffer: &str, state: &mut SolverState) -> String {
+    let mut out: String = String::new();
+    let mut lits: Vec<Lit> = Vec::new();
+    let mut assumptions: bool = false;
+    for line in buffer.lines() {
+        let line: &str = line.trim();
+        if line.is_empty() || line.starts_with('c') || line.starts_with('p') {
+            continue;
+        }
+        for token in line.split_whitespace() {
+            if token == "a" {
+                assumptions = true;
+                continue;
+            }
+            let n: i32 = token.parse::<i32>().unwrap();
+            if n != 0 {
+                let v: i32 = n.abs() - 1;
+                while v >= state.n_vars() {
+                    let x: i32 = state.new_var();
+                    state.set_frozen(x, true);
+                }
+                lits.push(Lit::new(v, n < 0));
+                continue;
+            }
+
+            if assumptions {
+                trace!("{}|{}|{}|{:?}", "icnf_query", file!(), line!(), lits);
+                state.solve(lits.clone());
+                // the model is empty as well when there are no variables
+                if state.ok && state.conflict.is_empty() {
+                    out.push_str("s SATISFIABLE\n");
+                } else {
+                    let failed: Vec<Lit> = lits
+                        .iter()
+                        .copied()
+                        .filter(|&p| state.conflict.contains(&!p))
+                        .collect();
+                    out.push_str("s UNSATISFIABLE\n");
+                    out.push_str(&line_of("f ", &failed));
+                }
+            } else {
+                state.add_clause(&mut lits.clone());
+            }
+            lits.clear();
+            assumptions = false;
+        }
+    }
+    out
+}
+
+/*_________________________________________________________________________________________________
+|
+|  write_icnf
+|
+|  Description:
+|    Writes the problem clauses of the solver and the facts of the root level in the incremental
+|    iCNF format, followed by one assumption line 'a <lits> 0' per cube. Meant to be called
+|    before solving, while the clauses are still the ones of the input.
+|________________________________________________________________________________________________@*/
+pub fn write_icnf(state: &SolverState, cubes: &[Vec<Lit>]) -> String {
+    let mut out: String = String::from("p inccnf\n");
+    let root: usize = state
+        .trail_lim
+        .first()
+        .map_or(state.trail.len(), |&l| l as usize);
+    for p in &state.trail[..root] {
+        out.push_str(&line_of("", &[*p]));
+    }
+    for &cr in &state.clauses {
+        out.push_str(&line_of("", &state.ca[cr].data));
+    }
+    for cube in cubes {
+        out.push_str(&line_of("a ", cube));
+    }
+    out
+}
+
+// A line of literals in DIMACS numbers, ended by 0.
+fn line_of(prefix: &str, lits: &[Lit]) -> String {
+    let mut s: String = prefix.to_string();
+    for p in lits {
+        s.push_str(&format!("{} ", dimacs_lit(p)));
+    }
+    s.push_str("0\n");
+    s
+}
+
+// The DIMACS number of a literal, variables are counted from 1 and negative numbers are negations.
+pub fn dimacs_lit(p: &Lit) -> i32 {
+    if sign(p) { -(var(p) + 1) } else { var(p) + 1 }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::functions::new_clause::*;
+    use crate::parsers::icnf::*;
+
+    #[test]
+    fn cubes_become_assumption_lines() {
+        let mut state = SolverState::new();
+        for _ in 0..3 {
+            state.new_var();
+        }
+        state.add_clause(&mut vec![Lit::new(0, false), Lit::new(1, true)]);
+        state.add_clause(&mut vec![Lit::new(2, false)]);
+        let cubes: Vec<Vec<Lit>> = vec![
+            vec![Lit::new(0, true)],
+            vec![Lit::new(0, false), Lit::new(1, false)],
+        ];
+        assert_eq!(
+            write_icnf(&state, &cubes),
+            "p inccnf\n3 0\n1 -2 0\na -1 0\na 1 2 0\n"
+        );
+    }
+
+    #[test]
+    fn queries_on_growing_clauses() {
+        let script = "c clauses come between the queries\np inccnf\n1 2 0\n-1 3 0\n\
+            a -2 0\na -3 -2 4 0\n-4 -1 0\na 4 0\n-2 0\n-3 0\na 0\n";
+        let mut state = SolverState::new();
+        assert_eq!(
+            parse_icnf(script, &mut state),
+            "s SATISFIABLE\ns UNSATISFIABLE\nf -3 -2 0\n\
+            s SATISFIABLE\ns UNSATISFIABLE\nf 0\n"
+        );
+        assert_eq!(state.n_vars(), 4);
+    }
+}
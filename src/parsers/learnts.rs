@@ -0,0 +1,79 @@
+use crate::models::lit::*;
+use crate::models::solverstate::*;
+use crate::parsers::icnf::*;
+
+// A learnt clause read from a file written by 'write_learnts()'.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LearntHint {
+    pub lits: Vec<Lit>,
+    pub activity: f64,
+    pub lbd: u32,
+}
+
+/*_________________________________________________________________________________________________
+|
+|  write_learnts
+|
+|  Description:
+|    Writes the learnt clauses of the solver in DIMACS, so that a later run on a related formula
+|    can start from them. Each clause is preceded by a comment 'c learnt <activity> <lbd>', other
+|    tools read the file as a plain CNF. The facts of the root level are not written.
+|________________________________________________________________________________________________@*/
+pub fn write_learnts(state: &SolverState) -> String {
+    let mut out: String = format!("p cnf {} {}\n", state.n_vars(), state.learnts.len());
+    for &cr in &state.learnts {
+        let c = &state.ca[cr];
+        out.push_str(&format!("c learnt {} {}\n", c.activity, c.lbd));
+        for p in &c.data {
+            out.push_str(&format!("{} ", dimacs_lit(p)));
+        }
+        out.push_str("0\n");
+    }
+    out
+}
+
+/*_________________________________________________________________________________________________
+|
+|  parse_learnts
+|
+|  Description:
+|    Reads the clauses of a file written by 'write_learnts()'. A clause without a 'c learnt'
+|    comment before it has no activity and its size as LBD.
+|
+|  Output:
+|    The clauses, or the first token that is not a number.
+|________________________________________________________________________________________________@*/
+pub fn parse_learnts(buffer: &str) -> Result<Vec<LearntHint>, String> {
+    let mut hints: Vec<LearntHint> = Vec::new();
+    let mut lits: Vec<Lit> = Vec::new();
+    let mut meta: Option<(f64, u32)> = None;
+    for line in buffer.lines() {
+        let line: &str = line.trim();
+        if let Some(rest) = line.strip_prefix("c learnt ") {
+            let fields: Vec<&str> = rest.split_whitespace().collect();
+            if let [activity, lbd] = fields[..] {
+                meta = activity.parse().ok().zip(lbd.parse().ok());
+            }
+            continue;
+        }
+        if line.is_empty() || line.starts_with('c') || line.starts_with('p') {
+            continue;
+        }
+        for token in line.split_whitespace() {
+            let n: i32 = token
+                .parse::<i32>()
+                .map_err(|_| format!("unexpected '{}'", token))?;
+            if n != 0 {
+                lits.push(Lit::new(n.abs() - 1, n < 0));
+                continue;
+            }
+            let (activity, lbd) = meta.take().unwrap_or((0.0, lits.len() as u32));
+            hints.push(LearntHint {
+                lits: std::mem::take(&mut lits),
+                activity,
+                lbd,
+            });
+        }
+    }
+    Ok(hints)
+}
@@ -0,0 +1,5 @@
+pub mod aiger;
+pub mod dimacs;
+pub mod icnf;
+pub mod learnts;
+pub mod smtlib;
@@ -0,0 +1,1087 @@
+use crate::functions::new_clause::*;
+use crate::functions::solve::*;
+use crate::models::lbool::*;
+use crate::models::lit::*;
+use crate::models::solverstate::*;
+use crate::theories::difference_logic::*;
+use std::any::Any;
+use std::collections::{BTreeMap, HashMap};
+
+/*_________________________________________________________________________________________________
+|
+|  parse_smtlib
+|
+|  Description:
+|    Runs the commands of an SMT-LIB 2 script and returns the responses, one per line. Supported
+|    are 'set-logic', 'set-option', 'set-info', 'declare-sort', 'declare-fun', 'declare-const',
+|    'define-fun', 'assert', 'check-sat', 'get-model', 'push', 'pop' and 'exit' over the Bool and
+|    Int sorts and declared sorts of arity 0, which covers QF_UF, QF_IDL and plain propositional
+|    problems. A failing command is answered with '(error ...)' and the script goes on.
+|
+|    The Boolean structure is Tseitin-encoded into 'add_clause()'. Integer atoms that are
+|    differences, x - y <= c, are handed to a 'DifferenceLogic' theory whose lemmas reach the
+|    solver as theory clauses. Equalities of declared sorts are difference atoms as well, the
+|    applications of a function with arguments are constrained pairwise to be congruent
+|    (Ackermann's reduction).
+|
+|    The assertions of a 'push' level are guarded by an activation literal that 'check-sat'
+|    assumes and 'pop' sets false for good. Every variable is frozen, later assertions can
+|    refer to any of them.
+|________________________________________________________________________________________________@*/
+pub fn parse_smtlib(buffer: &str, smt: &mut SmtLib) -> String {
+    let mut out: String = String::new();
+    let commands: Vec<SExpr> = match parse_sexprs(buffer) {
+        Ok(commands) => commands,
+        Err(msg) => {
+            out.push_str(&format!("(error \"{}\")\n", msg));
+            return out;
+        }
+    };
+
+    for command in &commands {
+        trace!("{}|{}|{}|{}", "smtlib", file!(), line!(), command);
+        if command.head() == Some("exit") {
+            break;
+        }
+        match smt.execute(command) {
+            Ok(response) => out.push_str(&response),
+            Err(msg) => out.push_str(&format!("(error \"{}\")\n", msg.replace('"', "\"\""))),
+        }
+    }
+    out
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum SExpr {
+    Atom(String),
+    List(Vec<SExpr>),
+}
+
+impl SExpr {
+    fn head(&self) -> Option<&str> {
+        match self {
+            SExpr::List(items) => match items.first() {
+                Some(SExpr::Atom(s)) => Some(s),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for SExpr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SExpr::Atom(s) => write!(f, "{}", s),
+            SExpr::List(items) => {
+                write!(f, "(")?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, " ")?;
+                    }
+                    write!(f, "{}", item)?;
+                }
+                write!(f, ")")
+            }
+        }
+    }
+}
+
+// Splits a script into its top-level S-expressions. Comments run from ';' to the end of the line,
+// '|quoted|' symbols lose their bars and string literals keep their quotes.
+pub fn parse_sexprs(buffer: &str) -> Result<Vec<SExpr>, String> {
+    let mut stack: Vec<Vec<SExpr>> = vec![Vec::new()];
+    let mut chars = buffer.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            ';' => {
+                for d in chars.by_ref() {
+                    if d == '\n' {
+                        break;
+                    }
+                }
+            }
+            '(' => stack.push(Vec::new()),
+            ')' => {
+                if stack.len() == 1 {
+                    return Err("unexpected ')'".to_string());
+                }
+                let list: Vec<SExpr> = stack.pop().unwrap();
+                stack.last_mut().unwrap().push(SExpr::List(list));
+            }
+            '|' => {
+                let mut s: String = String::new();
+                loop {
+                    match chars.next() {
+                        Some('|') => break,
+                        Some(d) => s.push(d),
+                        None => return Err("unterminated quoted symbol".to_string()),
+                    }
+                }
+                stack.last_mut().unwrap().push(SExpr::Atom(s));
+            }
+            '"' => {
+                let mut s: String = String::from("\"");
+                loop {
+                    match chars.next() {
+                        Some('"') if chars.peek() == Some(&'"') => {
+                            chars.next();
+                            s.push_str("\"\"");
+                        }
+                        Some('"') => break,
+                        Some(d) => s.push(d),
+                        None => return Err("unterminated string literal".to_string()),
+                    }
+                }
+                s.push('"');
+                stack.last_mut().unwrap().push(SExpr::Atom(s));
+            }
+            c if c.is_whitespace() => {}
+            c => {
+                let mut s: String = c.to_string();
+                while let Some(&d) = chars.peek() {
+                    if d.is_whitespace() || "();|\"".contains(d) {
+                        break;
+                    }
+                    s.push(d);
+                    chars.next();
+                }
+                stack.last_mut().unwrap().push(SExpr::Atom(s));
+            }
+        }
+    }
+
+    if stack.len() > 1 {
+        return Err("missing ')'".to_string());
+    }
+    Ok(stack.pop().unwrap())
+}
+
+// Sum of integer variables of the theory with coefficients, plus a constant.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Linear {
+    pub coefs: BTreeMap<usize, i64>,
+    pub constant: i64,
+}
+
+impl Linear {
+    fn var(x: usize) -> Self {
+        let mut coefs: BTreeMap<usize, i64> = BTreeMap::new();
+        coefs.insert(x, 1);
+        Self { coefs, constant: 0 }
+    }
+
+    fn constant(c: i64) -> Self {
+        Self {
+            coefs: BTreeMap::new(),
+            constant: c,
+        }
+    }
+
+    fn add(&self, other: &Linear, factor: i64) -> Linear {
+        let mut sum: Linear = self.clone();
+        for (&x, &c) in &other.coefs {
+            let coef: &mut i64 = sum.coefs.entry(x).or_insert(0);
+            *coef += factor * c;
+            if *coef == 0 {
+                sum.coefs.remove(&x);
+            }
+        }
+        sum.constant += factor * other.constant;
+        sum
+    }
+
+    fn scale(&self, factor: i64) -> Linear {
+        Linear::default().add(self, factor)
+    }
+}
+
+// The value of a term: a literal for Bool terms, otherwise a linear term and its sort.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Bool(Lit),
+    Term(Linear, String),
+}
+
+#[derive(Clone, Debug)]
+pub enum Symbol {
+    Const(Value),
+    // uninterpreted function with arguments, with the arguments and results of its applications
+    Fun {
+        args: Vec<String>,
+        result: String,
+        apps: Vec<(Vec<Value>, Value)>,
+    },
+    Macro {
+        params: Vec<(String, String)>,
+        body: SExpr,
+    },
+}
+
+#[derive(Clone, Debug)]
+struct Scope {
+    activation: Lit,
+    names: usize,
+    sorts: usize,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+enum Gate {
+    And(Vec<i32>),
+    Xor(i32, i32),
+    Ite(i32, i32, i32),
+}
+
+pub struct SmtLib {
+    pub state: SolverState,
+    symbols: HashMap<String, Symbol>,
+    // declared symbols and sorts in declaration order
+    names: Vec<String>,
+    sorts: Vec<String>,
+    scopes: Vec<Scope>,
+    gates: HashMap<Gate, Lit>,
+    atoms: HashMap<(usize, usize, i64), Lit>,
+    true_lit: Lit,
+    // the model of the last satisfiable 'check-sat' and the values of the integer variables
+    model: Option<(Vec<Lbool>, Vec<i64>)>,
+}
+
+pub trait ISmtLib {
+    fn new() -> Self;
+    fn execute(&mut self, command: &SExpr) -> Result<String, String>;
+    fn check_sat(&mut self) -> String;
+    fn get_model(&self) -> Result<String, String>;
+    fn pop(&mut self, n: usize) -> Result<(), String>;
+    fn declare(&mut self, name: &str, symbol: Symbol) -> Result<(), String>;
+    fn sort_of(&self, sort: &SExpr) -> Result<String, String>;
+    fn fresh(&mut self, sort: &str) -> Value;
+    fn eval(&mut self, e: &SExpr, env: &[(String, Value)]) -> Result<Value, String>;
+    fn eval_bool(&mut self, e: &SExpr, env: &[(String, Value)]) -> Result<Lit, String>;
+    fn eval_term(&mut self, e: &SExpr, env: &[(String, Value)]) -> Result<Linear, String>;
+    fn apply(&mut self, name: &str, args: Vec<Value>) -> Result<Value, String>;
+    fn new_lit(&mut self) -> Lit;
+    fn and(&mut self, ps: Vec<Lit>) -> Lit;
+    fn or(&mut self, ps: Vec<Lit>) -> Lit;
+    fn xor(&mut self, a: Lit, b: Lit) -> Lit;
+    fn ite(&mut self, c: Lit, t: Lit, e: Lit) -> Lit;
+    fn equal(&mut self, a: &Value, b: &Value) -> Result<Lit, String>;
+    fn leq(&mut self, a: &Linear, b: &Linear) -> Result<Lit, String>;
+    fn diff_atom(&mut self, x: usize, y: usize, c: i64) -> Lit;
+    fn dl(&mut self) -> &mut DifferenceLogic;
+    fn eval_model(&self, value: &Value, model: &(Vec<Lbool>, Vec<i64>)) -> i64;
+}
+
+impl ISmtLib for SmtLib {
+    fn new() -> Self {
+        let mut state: SolverState = SolverState::new();
+        let v: i32 = state.new_var();
+        state.set_frozen(v, true);
+        let true_lit: Lit = Lit::simple(v);
+        state.add_clause(&mut vec![true_lit]);
+
+        Self {
+            state,
+            symbols: HashMap::new(),
+            names: Vec::new(),
+            sorts: Vec::new(),
+            scopes: Vec::new(),
+            gates: HashMap::new(),
+            atoms: HashMap::new(),
+            true_lit,
+            model: None,
+        }
+    }
+
+    fn execute(&mut self, command: &SExpr) -> Result<String, String> {
+        let SExpr::List(items) = command else {
+            return Err(format!("not a command: {}", command));
+        };
+        let arg = |i: usize| -> Result<&SExpr, String> {
+            items
+                .get(i)
+                .ok_or_else(|| format!("missing argument: {}", command))
+        };
+        let name = |i: usize| -> Result<String, String> {
+            match arg(i)? {
+                SExpr::Atom(s) => Ok(s.clone()),
+                e => Err(format!("expected a symbol: {}", e)),
+            }
+        };
+        let count = |i: usize| -> Result<usize, String> {
+            match items.get(i) {
+                None => Ok(1),
+                Some(SExpr::Atom(s)) => s.parse().map_err(|_| format!("expected a numeral: {}", s)),
+                Some(e) => Err(format!("expected a numeral: {}", e)),
+            }
+        };
+
+        match command.head().unwrap_or("") {
+            "set-logic" | "set-option" | "set-info" => Ok(String::new()),
+            "declare-sort" => {
+                let s: String = name(1)?;
+                if count(2)? != 0 {
+                    return Err(format!("sort {} has parameters", s));
+                }
+                if s == "Bool" || s == "Int" || self.sorts.contains(&s) {
+                    return Err(format!("sort {} already declared", s));
+                }
+                self.sorts.push(s);
+                Ok(String::new())
+            }
+            "declare-fun" | "declare-const" => {
+                let f: String = name(1)?;
+                let (args, result): (Vec<String>, String) = if command.head() == Some("declare-fun")
+                {
+                    let SExpr::List(args) = arg(2)? else {
+                        return Err(format!("expected a list of sorts: {}", command));
+                    };
+                    let args: Vec<String> = args
+                        .iter()
+                        .map(|s| self.sort_of(s))
+                        .collect::<Result<_, _>>()?;
+                    (args, self.sort_of(arg(3)?)?)
+                } else {
+                    (Vec::new(), self.sort_of(arg(2)?)?)
+                };
+
+                let symbol: Symbol = if args.is_empty() {
+                    Symbol::Const(self.fresh(&result))
+                } else {
+                    Symbol::Fun {
+                        args,
+                        result,
+                        apps: Vec::new(),
+                    }
+                };
+                self.declare(&f, symbol)?;
+                Ok(String::new())
+            }
+            "define-fun" => {
+                let f: String = name(1)?;
+                let SExpr::List(params) = arg(2)? else {
+                    return Err(format!("expected a list of parameters: {}", command));
+                };
+                let mut typed: Vec<(String, String)> = Vec::new();
+                for p in params {
+                    match p {
+                        SExpr::List(pair) if pair.len() == 2 => {
+                            typed.push((pair[0].to_string(), self.sort_of(&pair[1])?));
+                        }
+                        _ => return Err(format!("expected a sorted parameter: {}", p)),
+                    }
+                }
+                let sort: String = self.sort_of(arg(3)?)?;
+                let body: SExpr = arg(4)?.clone();
+
+                let symbol: Symbol = if typed.is_empty() {
+                    let value: Value = self.eval(&body, &[])?;
+                    if sort_name(&value) != sort {
+                        return Err(format!("{} is not of sort {}", body, sort));
+                    }
+                    Symbol::Const(value)
+                } else {
+                    Symbol::Macro {
+                        params: typed,
+                        body,
+                    }
+                };
+                self.declare(&f, symbol)?;
+                Ok(String::new())
+            }
+            "assert" => {
+                let p: Lit = self.eval_bool(arg(1)?, &[])?;
+                let mut clause: Vec<Lit> = vec![p];
+                if let Some(scope) = self.scopes.last() {
+                    clause.push(!scope.activation);
+                }
+                self.state.add_clause(&mut clause);
+                Ok(String::new())
+            }
+            "check-sat" => Ok(self.check_sat()),
+            "get-model" => self.get_model(),
+            "push" => {
+                for _ in 0..count(1)? {
+                    let activation: Lit = self.new_lit();
+                    self.scopes.push(Scope {
+                        activation,
+                        names: self.names.len(),
+                        sorts: self.sorts.len(),
+                    });
+                }
+                Ok(String::new())
+            }
+            "pop" => {
+                self.pop(count(1)?)?;
+                Ok(String::new())
+            }
+            _ => Ok("unsupported\n".to_string()),
+        }
+    }
+
+    fn check_sat(&mut self) -> String {
+        let assumptions: Vec<Lit> = self.scopes.iter().map(|s| s.activation).collect();
+        self.state.solve(assumptions);
+        if self.state.model.is_empty() {
+            self.model = None;
+            return "unsat\n".to_string();
+        }
+
+        let mut ints: Vec<i64> = Vec::new();
+        if self.state.theory.is_some() {
+            let dl: &mut DifferenceLogic = self.dl();
+            ints = (0..dl.dist.len())
+                .map(|x| dl.value(x) - dl.value(0))
+                .collect();
+        }
+        self.model = Some((self.state.model.clone(), ints));
+        "sat\n".to_string()
+    }
+
+    fn get_model(&self) -> Result<String, String> {
+        let Some(model) = &self.model else {
+            return Err("model is not available".to_string());
+        };
+
+        // the elements of a declared sort are printed as abstract values, in order of appearance
+        let mut elements: HashMap<(String, i64), String> = HashMap::new();
+        let mut show = |value: &Value| -> String {
+            let v: i64 = self.eval_model(value, model);
+            match value {
+                Value::Bool(_) => (v != 0).to_string(),
+                Value::Term(_, sort) if sort == "Int" => show_int(v),
+                Value::Term(_, sort) => {
+                    let n: usize = elements.keys().filter(|(s, _)| s == sort).count();
+                    elements
+                        .entry((sort.clone(), v))
+                        .or_insert_with(|| format!("@{}_{}", sort, n))
+                        .clone()
+                }
+            }
+        };
+
+        let mut out: String = String::from("(\n");
+        for name in &self.names {
+            match &self.symbols[name] {
+                Symbol::Const(value) => {
+                    let shown: String = show(value);
+                    out.push_str(&format!(
+                        "  (define-fun {} () {} {})\n",
+                        quote(name),
+                        sort_name(value),
+                        shown
+                    ));
+                }
+                Symbol::Fun { args, result, apps } => {
+                    let params: Vec<String> = (0..args.len()).map(|i| format!("x!{}", i)).collect();
+                    let mut body: String = match apps.last() {
+                        Some((_, r)) => show(r),
+                        None => default_value(result),
+                    };
+                    for (xs, r) in apps.iter().rev().skip(1) {
+                        let conds: Vec<String> = xs
+                            .iter()
+                            .zip(&params)
+                            .map(|(x, p)| format!("(= {} {})", p, show(x)))
+                            .collect();
+                        let cond: String = match conds.len() {
+                            1 => conds[0].clone(),
+                            _ => format!("(and {})", conds.join(" ")),
+                        };
+                        body = format!("(ite {} {} {})", cond, show(r), body);
+                    }
+                    let typed: Vec<String> = params
+                        .iter()
+                        .zip(args)
+                        .map(|(p, s)| format!("({} {})", p, s))
+                        .collect();
+                    out.push_str(&format!(
+                        "  (define-fun {} ({}) {} {})\n",
+                        quote(name),
+                        typed.join(" "),
+                        result,
+                        body
+                    ));
+                }
+                Symbol::Macro { .. } => {}
+            }
+        }
+        out.push_str(")\n");
+        Ok(out)
+    }
+
+    fn pop(&mut self, n: usize) -> Result<(), String> {
+        if n > self.scopes.len() {
+            return Err(format!("cannot pop {} of {} levels", n, self.scopes.len()));
+        }
+        for _ in 0..n {
+            let scope: Scope = self.scopes.pop().unwrap();
+            self.state.add_clause(&mut vec![!scope.activation]);
+            for name in self.names.drain(scope.names..) {
+                self.symbols.remove(&name);
+            }
+            self.sorts.truncate(scope.sorts);
+        }
+        self.model = None;
+        Ok(())
+    }
+
+    fn declare(&mut self, name: &str, symbol: Symbol) -> Result<(), String> {
+        if self.symbols.contains_key(name) || builtin(name) {
+            return Err(format!("{} already declared", name));
+        }
+        self.symbols.insert(name.to_string(), symbol);
+        self.names.push(name.to_string());
+        Ok(())
+    }
+
+    fn sort_of(&self, sort: &SExpr) -> Result<String, String> {
+        match sort {
+            SExpr::Atom(s) if s == "Bool" || s == "Int" || self.sorts.contains(s) => Ok(s.clone()),
+            _ => Err(format!("unknown sort {}", sort)),
+        }
+    }
+
+    // A new unconstrained term of 'sort'.
+    fn fresh(&mut self, sort: &str) -> Value {
+        if sort == "Bool" {
+            return Value::Bool(self.new_lit());
+        }
+        let x: usize = self.dl().new_int();
+        Value::Term(Linear::var(x), sort.to_string())
+    }
+
+    fn eval(&mut self, e: &SExpr, env: &[(String, Value)]) -> Result<Value, String> {
+        let items: &Vec<SExpr> = match e {
+            SExpr::Atom(s) => {
+                if let Some((_, v)) = env.iter().rev().find(|(n, _)| n == s) {
+                    return Ok(v.clone());
+                }
+                return match s.as_str() {
+                    "true" => Ok(Value::Bool(self.true_lit)),
+                    "false" => Ok(Value::Bool(!self.true_lit)),
+                    _ if s.chars().all(|c| c.is_ascii_digit()) => {
+                        let c: i64 = s
+                            .parse()
+                            .map_err(|_| format!("numeral {} is too large", s))?;
+                        Ok(Value::Term(Linear::constant(c), "Int".to_string()))
+                    }
+                    _ => self.apply(s, Vec::new()),
+                };
+            }
+            SExpr::List(items) if items.is_empty() => return Err("empty term ()".to_string()),
+            SExpr::List(items) => items,
+        };
+
+        let op: &str = match &items[0] {
+            SExpr::Atom(op) => op,
+            _ => return Err(format!("unsupported term {}", e)),
+        };
+        let args: &[SExpr] = &items[1..];
+        let bool_args = |smt: &mut SmtLib| -> Result<Vec<Lit>, String> {
+            args.iter().map(|a| smt.eval_bool(a, env)).collect()
+        };
+        let term_args = |smt: &mut SmtLib| -> Result<Vec<Linear>, String> {
+            args.iter().map(|a| smt.eval_term(a, env)).collect()
+        };
+        let lit_value = |p: Lit| Ok(Value::Bool(p));
+        let int_value = |t: Linear| Ok(Value::Term(t, "Int".to_string()));
+
+        match op {
+            "let" => {
+                let (Some(SExpr::List(bindings)), Some(body)) = (args.first(), args.get(1)) else {
+                    return Err(format!("malformed let: {}", e));
+                };
+                let mut inner: Vec<(String, Value)> = env.to_vec();
+                for b in bindings {
+                    match b {
+                        SExpr::List(pair) if pair.len() == 2 => {
+                            let v: Value = self.eval(&pair[1], env)?;
+                            inner.push((pair[0].to_string(), v));
+                        }
+                        _ => return Err(format!("malformed binding {}", b)),
+                    }
+                }
+                self.eval(body, &inner)
+            }
+            "!" => match args.first() {
+                Some(t) => self.eval(t, env),
+                None => Err(format!("malformed annotation: {}", e)),
+            },
+            "not" if args.len() == 1 => lit_value(!self.eval_bool(&args[0], env)?),
+            "and" => {
+                let ps: Vec<Lit> = bool_args(self)?;
+                lit_value(self.and(ps))
+            }
+            "or" => {
+                let ps: Vec<Lit> = bool_args(self)?;
+                lit_value(self.or(ps))
+            }
+            "xor" if !args.is_empty() => {
+                let ps: Vec<Lit> = bool_args(self)?;
+                let mut p: Lit = ps[0];
+                for &q in &ps[1..] {
+                    p = self.xor(p, q);
+                }
+                lit_value(p)
+            }
+            "=>" if !args.is_empty() => {
+                let ps: Vec<Lit> = bool_args(self)?;
+                let mut p: Lit = ps[ps.len() - 1];
+                for &q in ps[..ps.len() - 1].iter().rev() {
+                    p = self.or(vec![!q, p]);
+                }
+                lit_value(p)
+            }
+            "ite" if args.len() == 3 => {
+                let c: Lit = self.eval_bool(&args[0], env)?;
+                let t: Value = self.eval(&args[1], env)?;
+                let f: Value = self.eval(&args[2], env)?;
+                match (&t, &f) {
+                    (Value::Bool(t), Value::Bool(f)) => lit_value(self.ite(c, *t, *f)),
+                    (Value::Term(_, s), Value::Term(_, u)) if s == u => {
+                        // a new term equal to the chosen branch
+                        let r: Value = self.fresh(s);
+                        let eq_t: Lit = self.equal(&r, &t)?;
+                        let eq_f: Lit = self.equal(&r, &f)?;
+                        self.state.add_clause(&mut vec![!c, eq_t]);
+                        self.state.add_clause(&mut vec![c, eq_f]);
+                        Ok(r)
+                    }
+                    _ => Err(format!("branches of different sorts: {}", e)),
+                }
+            }
+            "=" | "distinct" if args.len() > 1 => {
+                let vs: Vec<Value> = args
+                    .iter()
+                    .map(|a| self.eval(a, env))
+                    .collect::<Result<_, _>>()?;
+                let mut ps: Vec<Lit> = Vec::new();
+                for i in 0..vs.len() {
+                    for j in i + 1..vs.len() {
+                        if op == "=" && j > i + 1 {
+                            break;
+                        }
+                        let p: Lit = self.equal(&vs[i], &vs[j])?;
+                        ps.push(if op == "=" { p } else { !p });
+                    }
+                }
+                lit_value(self.and(ps))
+            }
+            "<=" | "<" | ">=" | ">" if args.len() > 1 => {
+                let ts: Vec<Linear> = term_args(self)?;
+                let mut ps: Vec<Lit> = Vec::new();
+                for w in ts.windows(2) {
+                    let (a, b) = match op {
+                        "<=" => (w[0].clone(), w[1].clone()),
+                        "<" => (w[0].add(&Linear::constant(1), 1), w[1].clone()),
+                        ">=" => (w[1].clone(), w[0].clone()),
+                        _ => (w[1].add(&Linear::constant(1), 1), w[0].clone()),
+                    };
+                    ps.push(self.leq(&a, &b)?);
+                }
+                lit_value(self.and(ps))
+            }
+            "+" => {
+                let ts: Vec<Linear> = term_args(self)?;
+                int_value(ts.iter().fold(Linear::default(), |s, t| s.add(t, 1)))
+            }
+            "-" if args.len() == 1 => int_value(self.eval_term(&args[0], env)?.scale(-1)),
+            "-" if args.len() > 1 => {
+                let ts: Vec<Linear> = term_args(self)?;
+                int_value(ts[1..].iter().fold(ts[0].clone(), |s, t| s.add(t, -1)))
+            }
+            "*" if !args.is_empty() => {
+                let ts: Vec<Linear> = term_args(self)?;
+                let mut product: Linear = Linear::constant(1);
+                for t in ts {
+                    if t.coefs.is_empty() {
+                        product = product.scale(t.constant);
+                    } else if product.coefs.is_empty() {
+                        product = t.scale(product.constant);
+                    } else {
+                        return Err(format!("non-linear term {}", e));
+                    }
+                }
+                int_value(product)
+            }
+            _ => {
+                let vs: Vec<Value> = args
+                    .iter()
+                    .map(|a| self.eval(a, env))
+                    .collect::<Result<_, _>>()?;
+                self.apply(op, vs)
+            }
+        }
+    }
+
+    fn eval_bool(&mut self, e: &SExpr, env: &[(String, Value)]) -> Result<Lit, String> {
+        match self.eval(e, env)? {
+            Value::Bool(p) => Ok(p),
+            Value::Term(_, sort) => Err(format!("{} is of sort {}, not Bool", e, sort)),
+        }
+    }
+
+    fn eval_term(&mut self, e: &SExpr, env: &[(String, Value)]) -> Result<Linear, String> {
+        match self.eval(e, env)? {
+            Value::Term(t, sort) if sort == "Int" => Ok(t),
+            v => Err(format!("{} is of sort {}, not Int", e, sort_name(&v))),
+        }
+    }
+
+    // Applies a declared or defined symbol.
+    fn apply(&mut self, name: &str, args: Vec<Value>) -> Result<Value, String> {
+        let Some(symbol) = self.symbols.get(name).cloned() else {
+            return Err(format!("unknown symbol {}", name));
+        };
+        let check = |sorts: &[&String]| -> Result<(), String> {
+            if sorts.len() != args.len()
+                || sorts.iter().zip(&args).any(|(s, a)| **s != sort_name(a))
+            {
+                return Err(format!("wrong arguments for {}", name));
+            }
+            Ok(())
+        };
+
+        match symbol {
+            Symbol::Const(value) => {
+                check(&[])?;
+                Ok(value)
+            }
+            Symbol::Macro { params, body } => {
+                check(&params.iter().map(|(_, s)| s).collect::<Vec<_>>())?;
+                let env: Vec<(String, Value)> =
+                    params.into_iter().map(|(p, _)| p).zip(args).collect();
+                self.eval(&body, &env)
+            }
+            Symbol::Fun {
+                args: sorts,
+                result,
+                apps,
+            } => {
+                check(&sorts.iter().collect::<Vec<_>>())?;
+                if let Some((_, r)) = apps.iter().find(|(xs, _)| *xs == args) {
+                    return Ok(r.clone());
+                }
+
+                // equal arguments imply equal results
+                let r: Value = self.fresh(&result);
+                for (xs, s) in &apps {
+                    let mut clause: Vec<Lit> = Vec::new();
+                    for (x, a) in xs.iter().zip(&args) {
+                        clause.push(!self.equal(x, a)?);
+                    }
+                    clause.push(self.equal(s, &r)?);
+                    self.state.add_clause(&mut clause);
+                }
+                if let Some(Symbol::Fun { apps, .. }) = self.symbols.get_mut(name) {
+                    apps.push((args, r.clone()));
+                }
+                Ok(r)
+            }
+        }
+    }
+
+    fn new_lit(&mut self) -> Lit {
+        let v: i32 = self.state.new_var();
+        self.state.set_frozen(v, true);
+        Lit::simple(v)
+    }
+
+    fn and(&mut self, ps: Vec<Lit>) -> Lit {
+        let mut ps: Vec<Lit> = ps.into_iter().filter(|p| *p != self.true_lit).collect();
+        ps.sort_by_key(|p| p.x);
+        ps.dedup();
+        if ps.contains(&!self.true_lit) || ps.windows(2).any(|w| w[0] == !w[1]) {
+            return !self.true_lit;
+        }
+        match ps.len() {
+            0 => return self.true_lit,
+            1 => return ps[0],
+            _ => {}
+        }
+
+        let gate: Gate = Gate::And(ps.iter().map(|p| p.x).collect());
+        if let Some(&g) = self.gates.get(&gate) {
+            return g;
+        }
+        let g: Lit = self.new_lit();
+        let mut long: Vec<Lit> = vec![g];
+        for &p in &ps {
+            self.state.add_clause(&mut vec![!g, p]);
+            long.push(!p);
+        }
+        self.state.add_clause(&mut long);
+        self.gates.insert(gate, g);
+        g
+    }
+
+    fn or(&mut self, ps: Vec<Lit>) -> Lit {
+        !self.and(ps.into_iter().map(|p| !p).collect())
+    }
+
+    fn xor(&mut self, a: Lit, b: Lit) -> Lit {
+        // the result is negated with the inputs, only positive inputs are hashed
+        let flip: bool = sign(&a) != sign(&b);
+        let (a, b) = (Lit::simple(var(&a)), Lit::simple(var(&b)));
+        let g: Lit = if a == b {
+            !self.true_lit
+        } else if a == self.true_lit || b == self.true_lit {
+            !(if a == self.true_lit { b } else { a })
+        } else {
+            let gate: Gate = Gate::Xor(a.x.min(b.x), a.x.max(b.x));
+            match self.gates.get(&gate) {
+                Some(&g) => g,
+                None => {
+                    let g: Lit = self.new_lit();
+                    self.state.add_clause(&mut vec![!g, a, b]);
+                    self.state.add_clause(&mut vec![!g, !a, !b]);
+                    self.state.add_clause(&mut vec![g, !a, b]);
+                    self.state.add_clause(&mut vec![g, a, !b]);
+                    self.gates.insert(gate, g);
+                    g
+                }
+            }
+        };
+        if flip { !g } else { g }
+    }
+
+    fn ite(&mut self, c: Lit, t: Lit, e: Lit) -> Lit {
+        if c == self.true_lit || t == e {
+            return t;
+        }
+        if c == !self.true_lit {
+            return e;
+        }
+        if t == !e {
+            return self.xor(c, e);
+        }
+        if sign(&c) {
+            return self.ite(!c, e, t);
+        }
+
+        let gate: Gate = Gate::Ite(c.x, t.x, e.x);
+        if let Some(&g) = self.gates.get(&gate) {
+            return g;
+        }
+        let g: Lit = self.new_lit();
+        self.state.add_clause(&mut vec![!g, !c, t]);
+        self.state.add_clause(&mut vec![!g, c, e]);
+        self.state.add_clause(&mut vec![g, !c, !t]);
+        self.state.add_clause(&mut vec![g, c, !e]);
+        self.gates.insert(gate, g);
+        g
+    }
+
+    fn equal(&mut self, a: &Value, b: &Value) -> Result<Lit, String> {
+        match (a, b) {
+            (Value::Bool(p), Value::Bool(q)) => Ok(!self.xor(*p, *q)),
+            (Value::Term(s, u), Value::Term(t, w)) if u == w => {
+                let below: Lit = self.leq(s, t)?;
+                let above: Lit = self.leq(t, s)?;
+                Ok(self.and(vec![below, above]))
+            }
+            _ => Err(format!("equality of {} and {}", sort_name(a), sort_name(b))),
+        }
+    }
+
+    // The literal of a <= b, which has to be a difference constraint.
+    fn leq(&mut self, a: &Linear, b: &Linear) -> Result<Lit, String> {
+        let d: Linear = a.add(b, -1);
+        let mut x: usize = 0;
+        let mut y: usize = 0;
+        for (&v, &c) in &d.coefs {
+            match c {
+                1 if x == 0 => x = v,
+                -1 if y == 0 => y = v,
+                _ => return Err("not a difference constraint".to_string()),
+            }
+        }
+        if x == y {
+            return Ok(if d.constant <= 0 {
+                self.true_lit
+            } else {
+                !self.true_lit
+            });
+        }
+        Ok(self.diff_atom(x, y, -d.constant))
+    }
+
+    // The atom x - y <= c, the negation of y - x <= -c - 1 if that one exists.
+    fn diff_atom(&mut self, x: usize, y: usize, c: i64) -> Lit {
+        if let Some(&p) = self.atoms.get(&(x, y, c)) {
+            return p;
+        }
+        if let Some(&p) = self.atoms.get(&(y, x, -c - 1)) {
+            return !p;
+        }
+        let p: Lit = self.new_lit();
+        self.dl().add_atom(var(&p), x, y, c);
+        self.atoms.insert((x, y, c), p);
+        p
+    }
+
+    // The difference logic theory, with integer variable 0 as the zero of the constants.
+    fn dl(&mut self) -> &mut DifferenceLogic {
+        if self.state.theory.is_none() {
+            let mut dl: DifferenceLogic = DifferenceLogic::default();
+            dl.new_int();
+            self.state.theory = Some(Box::new(dl));
+        }
+        let theory: &mut dyn Any = self.state.theory.as_mut().unwrap().as_mut();
+        theory.downcast_mut().unwrap()
+    }
+
+    // A Bool value as 0 or 1, a term as its integer.
+    fn eval_model(&self, value: &Value, model: &(Vec<Lbool>, Vec<i64>)) -> i64 {
+        match value {
+            Value::Bool(p) => (model.0[var(p) as usize] == to_bool(!sign(p))) as i64,
+            Value::Term(t, _) => t
+                .coefs
+                .iter()
+                .fold(t.constant, |s, (&x, &c)| s + c * model.1[x]),
+        }
+    }
+}
+
+fn sort_name(value: &Value) -> String {
+    match value {
+        Value::Bool(_) => "Bool".to_string(),
+        Value::Term(_, sort) => sort.clone(),
+    }
+}
+
+fn builtin(name: &str) -> bool {
+    [
+        "true", "false", "not", "and", "or", "xor", "=>", "ite", "=", "distinct", "<=", "<", ">=",
+        ">", "+", "-", "*", "let", "!",
+    ]
+    .contains(&name)
+}
+
+fn show_int(v: i64) -> String {
+    if v < 0 {
+        format!("(- {})", -v)
+    } else {
+        v.to_string()
+    }
+}
+
+fn default_value(sort: &str) -> String {
+    match sort {
+        "Bool" => "false".to_string(),
+        "Int" => "0".to_string(),
+        _ => format!("@{}_0", sort),
+    }
+}
+
+// Symbols outside the simple symbol characters are printed between bars.
+fn quote(name: &str) -> String {
+    let simple = |c: char| c.is_ascii_alphanumeric() || "~!@$%^&*_-+=<>.?/".contains(c);
+    if !name.is_empty()
+        && name.chars().all(simple)
+        && !name.starts_with(|c: char| c.is_ascii_digit())
+    {
+        name.to_string()
+    } else {
+        format!("|{}|", name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parsers::smtlib::*;
+
+    fn run(script: &str) -> String {
+        let mut smt: SmtLib = SmtLib::new();
+        parse_smtlib(script, &mut smt)
+    }
+
+    #[test]
+    fn propositional_push_pop() {
+        let out: String = run(r#"
+(set-logic QF_UF)
+(declare-fun a () Bool)
+(declare-fun b () Bool)
+(assert (xor a b))
+(assert (=> a b))
+(check-sat)
+(get-model)
+(push 1)
+(assert (not b))
+(check-sat)
+(pop 1)
+(check-sat)
+"#);
+        assert_eq!(
+            out,
+            "sat\n(\n  (define-fun a () Bool false)\n  (define-fun b () Bool true)\n)\nunsat\nsat\n"
+        );
+    }
+
+    #[test]
+    fn difference_logic_schedule() {
+        let script = |horizon: i64| {
+            format!(
+                r#"
+(set-logic QF_IDL)
+(declare-const s1 Int)
+(declare-const s2 Int)
+(assert (and (>= s1 0) (>= s2 0) (<= (+ s1 3) {0}) (<= (+ s2 2) {0})))
+(assert (or (<= (- s1 s2) (- 3)) (<= (- s2 s1) (- 2))))
+(check-sat)
+(get-model)
+"#,
+                horizon
+            )
+        };
+        assert_eq!(
+            run(&script(4)),
+            "unsat\n(error \"model is not available\")\n"
+        );
+
+        let out: String = run(&script(5));
+        assert!(out.starts_with("sat\n"));
+        let value = |name: &str| -> i64 {
+            let line: &str = out.lines().find(|l| l.contains(name)).unwrap();
+            let v: &str = line
+                .trim()
+                .trim_end_matches(')')
+                .rsplit(' ')
+                .next()
+                .unwrap();
+            v.parse().unwrap()
+        };
+        let (s1, s2) = (value("s1"), value("s2"));
+        assert!(s1 >= 0 && s1 + 3 <= 5 && s2 >= 0 && s2 + 2 <= 5);
+        assert!(s1 + 3 <= s2 || s2 + 2 <= s1);
+    }
+
+    #[test]
+    fn congruence_of_uninterpreted_functions() {
+        let out: String = run(r#"
+(set-logic QF_UF)
+(declare-sort U 0)
+(declare-fun f (U) U)
+(declare-fun p (U) Bool)
+(declare-const a U)
+(declare-const b U)
+(assert (= (f a) b))
+(assert (= (f b) a))
+(push 1)
+(assert (and (p a) (not (p (f (f a))))))
+(check-sat)
+(pop 1)
+(assert (distinct a b))
+(check-sat)
+(assert (= a (f a)))
+(check-sat)
+(frobnicate)
+(pop 1)
+"#);
+        assert_eq!(
+            out,
+            "unsat\nsat\nunsat\nunsupported\n(error \"cannot pop 1 of 0 levels\")\n"
+        );
+    }
+}
@@ -0,0 +1,267 @@
+use crate::functions::new_clause::*;
+use crate::models::lit::*;
+use crate::models::solverstate::*;
+use crate::models::theory::*;
+use std::collections::VecDeque;
+
+// Integer difference logic, atoms of the form x - y <= c. An asserted atom is the edge y -> x with
+// weight c of the constraint graph, its negation y - x <= -c - 1 the edge x -> y. The constraints
+// are satisfiable as long as the graph has no negative cycle.
+//
+// 'dist' is kept as a feasible potential of the graph, dist[x] - dist[y] <= c for every edge, and
+// doubles as the model. Adding an edge only has to relax the distances reachable from its head,
+// reaching its tail again closes a negative cycle (Cotton & Maler). The negations of the atoms on
+// the cycle are added as a conflict lemma. Removing edges on backtracking keeps the potential
+// feasible, so nothing is recomputed.
+#[derive(Clone, Debug, Default)]
+pub struct DifferenceLogic {
+    pub dist: Vec<i64>,
+    // (x, y, c) of the atom of every solver variable
+    atoms: Vec<Option<(usize, usize, i64)>>,
+    // asserted atoms in trail order, 'edges[..checked]' are in the graph
+    edges: Vec<Edge>,
+    checked: usize,
+    out_edges: Vec<Vec<usize>>,
+    assigned: usize,
+    pred: Vec<usize>,
+    in_queue: Vec<bool>,
+    queue: VecDeque<usize>,
+    undo: Vec<(usize, i64)>,
+}
+
+#[derive(Copy, Clone, Debug)]
+struct Edge {
+    from: usize,
+    to: usize,
+    weight: i64,
+    lit: Lit,
+    // position of 'lit' among the assigned literals
+    pos: usize,
+}
+
+pub trait IDifferenceLogic {
+    fn new_int(&mut self) -> usize;
+    fn add_atom(&mut self, v: i32, x: usize, y: usize, c: i64);
+    fn new_atom(&mut self, state: &mut SolverState, x: usize, y: usize, c: i64) -> Lit;
+    fn value(&self, x: usize) -> i64;
+    fn insert_edge(&mut self, i: usize) -> Option<Vec<Lit>>;
+}
+
+impl IDifferenceLogic for DifferenceLogic {
+    fn new_int(&mut self) -> usize {
+        self.dist.push(0);
+        self.out_edges.push(Vec::new());
+        self.pred.push(0);
+        self.in_queue.push(false);
+        self.dist.len() - 1
+    }
+
+    // Variable 'v' of the solver stands for x - y <= c.
+    fn add_atom(&mut self, v: i32, x: usize, y: usize, c: i64) {
+        assert!(x < self.dist.len() && y < self.dist.len());
+        if self.atoms.len() <= v as usize {
+            self.atoms.resize(v as usize + 1, None);
+        }
+        self.atoms[v as usize] = Some((x, y, c));
+    }
+
+    // A new frozen variable of 'state' for x - y <= c.
+    fn new_atom(&mut self, state: &mut SolverState, x: usize, y: usize, c: i64) -> Lit {
+        let v: i32 = state.new_var();
+        state.set_frozen(v, true);
+        self.add_atom(v, x, y, c);
+        Lit::simple(v)
+    }
+
+    fn value(&self, x: usize) -> i64 {
+        self.dist[x]
+    }
+
+    // Adds 'edges[i]' to the graph, or returns the conflict lemma if it closes a negative cycle.
+    fn insert_edge(&mut self, i: usize) -> Option<Vec<Lit>> {
+        let e: Edge = self.edges[i];
+        if self.dist[e.from] + e.weight < self.dist[e.to] {
+            self.undo.clear();
+            self.undo.push((e.to, self.dist[e.to]));
+            self.dist[e.to] = self.dist[e.from] + e.weight;
+            self.pred[e.to] = i;
+            self.queue.push_back(e.to);
+            self.in_queue[e.to] = true;
+
+            while let Some(x) = self.queue.pop_front() {
+                self.in_queue[x] = false;
+                for k in 0..self.out_edges[x].len() {
+                    let j: usize = self.out_edges[x][k];
+                    let f: Edge = self.edges[j];
+                    let d: i64 = self.dist[x] + f.weight;
+                    if d >= self.dist[f.to] {
+                        continue;
+                    }
+
+                    if f.to == e.from {
+                        let mut lemma: Vec<Lit> = vec![!e.lit, !f.lit];
+                        let mut y: usize = x;
+                        while y != e.to {
+                            let g: Edge = self.edges[self.pred[y]];
+                            lemma.push(!g.lit);
+                            y = g.from;
+                        }
+
+                        for &(z, d) in self.undo.iter().rev() {
+                            self.dist[z] = d;
+                        }
+                        for z in self.queue.drain(..) {
+                            self.in_queue[z] = false;
+                        }
+                        return Some(lemma);
+                    }
+
+                    self.undo.push((f.to, self.dist[f.to]));
+                    self.dist[f.to] = d;
+                    self.pred[f.to] = j;
+                    if !self.in_queue[f.to] {
+                        self.queue.push_back(f.to);
+                        self.in_queue[f.to] = true;
+                    }
+                }
+            }
+        }
+        self.out_edges[e.from].push(i);
+        None
+    }
+}
+
+impl Theory for DifferenceLogic {
+    fn assign(&mut self, p: Lit) {
+        let pos: usize = self.assigned;
+        self.assigned += 1;
+
+        let v: usize = var(&p) as usize;
+        if let Some(Some((x, y, c))) = self.atoms.get(v).copied() {
+            let (from, to, weight) = if sign(&p) { (x, y, -c - 1) } else { (y, x, c) };
+            self.edges.push(Edge {
+                from,
+                to,
+                weight,
+                lit: p,
+                pos,
+            });
+        }
+    }
+
+    fn backtrack(&mut self, n: usize) {
+        self.assigned = n;
+        while let Some(e) = self.edges.last() {
+            if e.pos < n {
+                break;
+            }
+            if self.edges.len() <= self.checked {
+                self.out_edges[e.from].pop();
+            }
+            self.edges.pop();
+        }
+        self.checked = self.checked.min(self.edges.len());
+    }
+
+    fn propagate(&mut self, out: &mut TheoryOutput) {
+        while self.checked < self.edges.len() {
+            if let Some(lemma) = self.insert_edge(self.checked) {
+                trace!("{}|{}|{}|{:?}", "negative_cycle", file!(), line!(), lemma);
+                out.add_lemma(&lemma);
+                return;
+            }
+            self.checked += 1;
+        }
+    }
+
+    fn final_check(&mut self, out: &mut TheoryOutput) {
+        self.propagate(out);
+    }
+
+    fn explain(&mut self, _p: Lit) -> Vec<Lit> {
+        unreachable!("difference logic only adds conflict lemmas")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::functions::solve::*;
+    use crate::models::lbool::*;
+    use crate::theories::difference_logic::*;
+    use std::any::Any;
+
+    // Two jobs of length 3 and 2 on one machine, both done by 'horizon'.
+    fn schedule(horizon: i64) -> Option<(i64, i64)> {
+        let mut state = SolverState::new();
+        let mut dl = DifferenceLogic::default();
+        let zero: usize = dl.new_int();
+        let s1: usize = dl.new_int();
+        let s2: usize = dl.new_int();
+
+        let mut units: Vec<Lit> = vec![
+            dl.new_atom(&mut state, zero, s1, 0),
+            dl.new_atom(&mut state, zero, s2, 0),
+            dl.new_atom(&mut state, s1, zero, horizon - 3),
+            dl.new_atom(&mut state, s2, zero, horizon - 2),
+        ];
+        let first: Lit = dl.new_atom(&mut state, s1, s2, -3);
+        let second: Lit = dl.new_atom(&mut state, s2, s1, -2);
+        for p in units.drain(..) {
+            state.add_clause(&mut vec![p]);
+        }
+        state.add_clause(&mut vec![first, second]);
+
+        state.theory = Some(Box::new(dl));
+        state.solve_no_assumptions();
+        let theory: Box<dyn Any> = state.theory.take().unwrap();
+        let dl: Box<DifferenceLogic> = theory.downcast().unwrap();
+        if !state.ok || state.model.is_empty() {
+            return None;
+        }
+        assert!(
+            state.model[var(&first) as usize] == L_TRUE
+                || state.model[var(&second) as usize] == L_TRUE
+        );
+        Some((dl.value(s1) - dl.value(zero), dl.value(s2) - dl.value(zero)))
+    }
+
+    #[test]
+    fn job_shop_schedule() {
+        assert_eq!(schedule(4), None);
+
+        let (s1, s2) = schedule(5).unwrap();
+        assert!(s1 >= 0 && s1 + 3 <= 5 && s2 >= 0 && s2 + 2 <= 5);
+        assert!(s1 + 3 <= s2 || s2 + 2 <= s1);
+    }
+
+    #[test]
+    fn negative_cycle_is_a_lemma() {
+        let mut dl = DifferenceLogic::default();
+        let x: usize = dl.new_int();
+        let y: usize = dl.new_int();
+        let z: usize = dl.new_int();
+        dl.add_atom(0, x, y, -1);
+        dl.add_atom(1, y, z, -1);
+        dl.add_atom(2, z, x, 1);
+
+        let mut out = TheoryOutput::default();
+        for v in 0..3 {
+            dl.assign(Lit::simple(v));
+        }
+        dl.propagate(&mut out);
+        assert_eq!(out.lemmas.len(), 1);
+        let mut lemma: Vec<Lit> = out.lemmas[0].clone();
+        lemma.sort_by_key(|p| p.x);
+        assert_eq!(
+            lemma,
+            vec![!Lit::simple(0), !Lit::simple(1), !Lit::simple(2)]
+        );
+
+        // without the last atom the potential is a model again
+        dl.backtrack(2);
+        let mut out = TheoryOutput::default();
+        dl.propagate(&mut out);
+        assert!(out.is_empty());
+        assert!(dl.value(x) - dl.value(y) <= -1 && dl.value(y) - dl.value(z) <= -1);
+    }
+}
@@ -0,0 +1 @@
+pub mod difference_logic;
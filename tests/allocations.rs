@@ -0,0 +1,58 @@
+mod common;
+
+use common::*;
+use sat_rs::functions::solve::*;
+use sat_rs::models::solverstate::*;
+use sat_rs::parsers::dimacs::*;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+// Regression benchmark for the number of heap allocations done while solving. Solving must not
+// copy the solver state, so the allocations per conflict have to stay bounded by a small constant
+// (the learnt clause itself and the amortised growth of the clause and watch lists).
+
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        unsafe { System.alloc(layout) }
+    }
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        unsafe { System.realloc(ptr, layout, new_size) }
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+const MAX_ALLOCATIONS_PER_CONFLICT: f64 = 16.0;
+
+#[test]
+fn allocations_per_conflict_are_bounded() {
+    let mut seed: u64 = 0x2545F4914F6CDD1D;
+    for n in [50, 75, 100, 100, 100] {
+        let problem = uniform_3sat(n, &mut seed);
+        let mut state = SolverState::new();
+        parse_dimacs(&problem, &mut state);
+        // preprocessing is a one-off cost proportional to the formula, not to the conflicts
+        state.preprocess_parms.enabled = false;
+
+        let before = ALLOCATIONS.load(Ordering::Relaxed);
+        state.solve_no_assumptions();
+        let allocations = (ALLOCATIONS.load(Ordering::Relaxed) - before) as f64;
+
+        let per_conflict = allocations / state.solver_stats.conflicts.max(1.0);
+        println!(
+            "uf{}: {} conflicts, {} allocations, {:.2} per conflict",
+            n, state.solver_stats.conflicts, allocations, per_conflict
+        );
+        assert!(per_conflict <= MAX_ALLOCATIONS_PER_CONFLICT);
+    }
+}
@@ -0,0 +1,83 @@
+use std::path::PathBuf;
+use std::process::Command;
+
+// Runs the sat_rs binary the way a script would and checks what it leaves on stdout.
+
+// Parses a flat JSON object of numbers and nulls, the shape of the '--stats-json' output.
+fn parse_flat_object(text: &str) -> Result<Vec<(String, Option<f64>)>, String> {
+    let inner: &str = text
+        .trim()
+        .strip_prefix('{')
+        .and_then(|t| t.strip_suffix('}'))
+        .ok_or_else(|| format!("not an object: {:?}", text))?;
+    let mut fields: Vec<(String, Option<f64>)> = Vec::new();
+    for field in inner.split(',') {
+        let (key, value) = field
+            .split_once(':')
+            .ok_or_else(|| format!("no value in {:?}", field))?;
+        let key: &str = key
+            .strip_prefix('"')
+            .and_then(|k| k.strip_suffix('"'))
+            .ok_or_else(|| format!("unquoted key {:?}", key))?;
+        let value: Option<f64> = match value {
+            "null" => None,
+            v => Some(v.parse().map_err(|_| format!("not a number: {:?}", v))?),
+        };
+        fields.push((key.to_string(), value));
+    }
+    Ok(fields)
+}
+
+fn scratch_dir(name: &str) -> PathBuf {
+    let dir: PathBuf = std::env::temp_dir().join(format!("sat_rs_{}_{}", name, std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn run(dir: &PathBuf, args: &[&str]) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_sat_rs"))
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    String::from_utf8(output.stdout).unwrap()
+}
+
+fn problem(name: &str) -> String {
+    format!("{}/benches/instances/{}", env!("CARGO_MANIFEST_DIR"), name)
+}
+
+#[test]
+fn stats_json_is_the_only_output_on_stdout() {
+    let problem: String = problem("aim-100-no.cnf");
+    for (name, extra) in [("single", vec![]), ("portfolio", vec!["--threads", "2"])] {
+        let mut args: Vec<&str> = vec![problem.as_str(), "--stats-json"];
+        args.extend(extra);
+        let dir: PathBuf = scratch_dir(name);
+        let stdout: String = run(&dir, &args);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(stdout.lines().count(), 1, "stdout: {}", stdout);
+        let fields = parse_flat_object(&stdout).unwrap();
+        assert!(fields.iter().any(|(k, _)| k == "conflicts"));
+        assert!(fields.iter().any(|(k, _)| k == "elapsed_seconds"));
+    }
+}
+
+#[test]
+fn cubes_are_written_only_where_asked() {
+    let problem: String = problem("aim-50-yes.cnf");
+    let dir: PathBuf = scratch_dir("cubes");
+    run(&dir, &[problem.as_str(), "--cubes", "2"]);
+    assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1); // only sat.log
+
+    run(
+        &dir,
+        &[problem.as_str(), "--cubes", "2", "--cubes-out", "out.icnf"],
+    );
+    let icnf: String = std::fs::read_to_string(dir.join("out.icnf")).unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+    assert!(icnf.starts_with("p inccnf"));
+    assert!(icnf.lines().any(|l| l.starts_with("a ")));
+}
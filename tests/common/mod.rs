@@ -0,0 +1,21 @@
+// Helpers shared by the integration tests and the benchmarks, the benches include this file
+// with a #[path] attribute.
+
+// Uniform random 3-SAT in DIMACS (uf-style, clause/variable ratio 4.26), drawn with xorshift64
+// from 'seed' so that every run sees the same problems.
+pub fn uniform_3sat(n: i32, seed: &mut u64) -> String {
+    let m = (n as f64 * 4.26) as i32;
+    let mut out = format!("p cnf {} {}\n", n, m);
+    for _ in 0..m {
+        for _ in 0..3 {
+            *seed ^= *seed << 13;
+            *seed ^= *seed >> 7;
+            *seed ^= *seed << 17;
+            let v = (*seed % n as u64) as i32 + 1;
+            let l = if (*seed >> 32) & 1 == 1 { v } else { -v };
+            out.push_str(&format!("{} ", l));
+        }
+        out.push_str("0\n");
+    }
+    out
+}